@@ -292,7 +292,8 @@ export function render(state, setState) {
                         $set: {
                             Road: {
                                 n_lanes_forward: 2,
-                                n_lanes_backward: 2
+                                n_lanes_backward: 2,
+                                class: "Residential"
                             }
                         }
                     }
@@ -1,4 +1,4 @@
-#![cfg_attr(feature = "server", allow(unused_variables, unused_imports))]
+#![cfg_attr(not(feature = "browser"), allow(unused_variables, unused_imports))]
 
 use kay::{World, ActorSystem, Actor, RawID, External};
 use compact::{CVec, CHashMap};
@@ -191,6 +191,8 @@ SwitchLanePrototype, IntersectionPrototype};
                         PrototypeKind::Road(RoadPrototype::Lane(LanePrototype(
                             ref lane_path,
                             _,
+                            _,
+                            _,
                         ))) => {
                             let mesh = lane_mesh(lane_path);
                             if is_construct && !is_morph {
@@ -216,7 +218,7 @@ SwitchLanePrototype, IntersectionPrototype};
                                 ..
                             },
                         )) => {
-                            for &LanePrototype(ref lane_path, _) in
+                            for &LanePrototype(ref lane_path, ..) in
                                 connecting_lanes.values().flat_map(|lanes| lanes)
                             {
                                 let mesh = lane_mesh(lane_path);
@@ -2,6 +2,8 @@ use kay::{World, Fate, ActorSystem};
 use compact::{CVec, CHashMap};
 use planning::{PrototypeID, Prototype, PrototypeKind};
 use simulation::{Simulatable, SimulatableID, Instant};
+use statistics::StatisticsID;
+use persistence::PersistenceID;
 
 pub trait Constructable {
     fn morph(&mut self, new_prototype: &Prototype, report_to: ConstructionID, world: &mut World);
@@ -13,6 +15,9 @@ impl Prototype {
         match self.kind {
             PrototypeKind::Road(ref road_prototype) => road_prototype.construct(report_to, world),
             PrototypeKind::Lot(ref lot_prototype) => lot_prototype.construct(report_to, world),
+            PrototypeKind::TransitLine(ref line_prototype) => {
+                line_prototype.construct(report_to, world)
+            }
         }
     }
 
@@ -24,6 +29,10 @@ impl Prototype {
             (&PrototypeKind::Lot(ref self_lot), &PrototypeKind::Lot(ref other_lot)) => {
                 self_lot.morphable_from(other_lot)
             }
+            (
+                &PrototypeKind::TransitLine(ref self_line),
+                &PrototypeKind::TransitLine(ref other_line),
+            ) => self_line.morphable_from(other_line),
             _ => false,
         }
     }
@@ -35,6 +44,8 @@ pub struct Construction {
     constructed: CHashMap<PrototypeID, CVec<ConstructableID>>,
     pending_constructables: CVec<ConstructableID>,
     queued_actions: CVec<CVec<Action>>,
+    // told once everything queued so far is constructed
+    report_done_to: CVec<PersistenceID>,
 }
 
 #[derive(Compact, Clone, Debug, Serialize, Deserialize)]
@@ -51,6 +62,7 @@ impl Construction {
             constructed: CHashMap::new(),
             pending_constructables: CVec::new(),
             queued_actions: CVec::new(),
+            report_done_to: CVec::new(),
         }
     }
 
@@ -99,6 +111,18 @@ impl Construction {
     pub fn implement(&mut self, actions_to_implement: &CVec<CVec<Action>>, _world: &mut World) {
         self.queued_actions.extend(actions_to_implement.clone());
     }
+
+    pub fn report_done_to(&mut self, requester: PersistenceID, _: &mut World) {
+        self.report_done_to.push(requester);
+    }
+
+    pub fn report_queue_to(&mut self, statistics: StatisticsID, world: &mut World) {
+        statistics.on_construction_queue(
+            self.queued_actions.len(),
+            self.pending_constructables.len(),
+            world,
+        );
+    }
 }
 
 impl Simulatable for Construction {
@@ -111,6 +135,10 @@ impl Simulatable for Construction {
                     self.start_action(action, world);
                 }
                 println!("\nFinished construction group:");
+            } else {
+                for requester in self.report_done_to.drain() {
+                    requester.on_construction_done(world);
+                }
             }
         } else {
             println!(
@@ -6,6 +6,7 @@ use economy::market::{Deal, EvaluationRequester, EvaluationRequesterID, Evaluate
 use land_use::buildings::BuildingID;
 
 use super::{Household, HouseholdID, HouseholdCore, MemberIdx, Offer};
+use economy::immigration_and_development::HouseholdTypeToSpawn;
 
 #[derive(Compact, Clone)]
 pub struct Bakery {
@@ -45,13 +46,7 @@ impl Bakery {
                         30,
                         false,
                     ),
-                    Offer::new(
-                        MemberIdx(0),
-                        TimeOfDayRange::new(5, 0, 15, 0),
-                        Deal::new(Some((Resource::Money, 50.0)), Duration::from_hours(5)),
-                        3,
-                        false,
-                    ),
+                    Offer::job(MemberIdx(0), TimeOfDayRange::new(4, 0, 12, 0), 80.0, 3),
                 ].into(),
             ),
         }
@@ -102,19 +97,21 @@ impl Household for Bakery {
     }
 
     fn decay(&mut self, dt: Duration, _: &mut World) {
+        let days_worked = self.core.productivity() * dt.as_days();
+
         {
             let baked = self.core.resources.mut_entry_or(BakedGoods, 0.0);
-            *baked += 300.0 * dt.as_days();
+            *baked += 300.0 * days_worked;
         }
 
         {
             let flour = self.core.resources.mut_entry_or(Flour, 0.0);
-            *flour += 300.0 * 0.1 * dt.as_days();
+            *flour += 300.0 * 0.1 * days_worked;
         }
 
         {
             let dairy = self.core.resources.mut_entry_or(DairyGoods, 0.0);
-            *dairy += 300.0 * 0.05 * dt.as_days();
+            *dairy += 300.0 * 0.05 * days_worked;
         }
     }
 
@@ -122,6 +119,10 @@ impl Household for Bakery {
         self.site.remove_household(self.id_as(), world);
     }
 
+    fn household_type(&self) -> HouseholdTypeToSpawn {
+        HouseholdTypeToSpawn::Bakery
+    }
+
     fn household_name(&self) -> String {
         "Bakery".to_owned()
     }
@@ -131,16 +132,11 @@ impl Household for Bakery {
     }
 }
 
-use simulation::{Simulatable, SimulatableID, Sleeper, SleeperID, Instant, TICKS_PER_SIM_SECOND};
-const UPDATE_EVERY_N_SECS: usize = 4;
+use simulation::{Simulatable, SimulatableID, Sleeper, SleeperID, Instant};
 
 impl Simulatable for Bakery {
     fn tick(&mut self, _dt: f32, current_instant: Instant, world: &mut World) {
-        if (current_instant.ticks() + self.id.as_raw().instance_id as usize)
-            % (UPDATE_EVERY_N_SECS * TICKS_PER_SIM_SECOND) == 0
-        {
-            self.decay(Duration(UPDATE_EVERY_N_SECS * TICKS_PER_SIM_SECOND), world);
-        }
+        self.on_tick(current_instant, world);
     }
 }
 
@@ -164,6 +160,7 @@ impl RoughLocation for Bakery {
 }
 
 use transport::pathfinding::trip::{TripListener, TripListenerID, TripID, TripResult};
+use transport::parking::ParkingSpot;
 
 impl TripListener for Bakery {
     fn trip_created(&mut self, trip: TripID, world: &mut World) {
@@ -180,6 +177,10 @@ impl TripListener for Bakery {
     ) {
         self.on_trip_result(trip, result, rough_source, rough_destination, world);
     }
+
+    fn trip_parked(&mut self, trip: TripID, spot: ParkingSpot, world: &mut World) {
+        self.on_trip_parked(trip, spot, world);
+    }
 }
 
 pub fn setup(system: &mut ActorSystem) {
@@ -14,6 +14,7 @@ pub mod names;
 use self::names::{family_name, member_name};
 
 use super::{Household, HouseholdID, HouseholdCore, MemberIdx, Offer, OfferID, OfferIdx};
+use economy::immigration_and_development::HouseholdTypeToSpawn;
 
 #[derive(Compact, Clone)]
 pub struct Family {
@@ -88,6 +89,7 @@ impl EvaluationRequester for Family {
 }
 
 use transport::pathfinding::trip::{TripListener, TripID};
+use transport::parking::ParkingSpot;
 
 impl TripListener for Family {
     fn trip_created(&mut self, trip: TripID, world: &mut World) {
@@ -104,6 +106,10 @@ impl TripListener for Family {
     ) {
         self.on_trip_result(trip, result, rough_source, rough_destination, world);
     }
+
+    fn trip_parked(&mut self, trip: TripID, spot: ParkingSpot, world: &mut World) {
+        self.on_trip_parked(trip, spot, world);
+    }
 }
 
 impl Household for Family {
@@ -154,6 +160,18 @@ impl Household for Family {
             .unwrap_or(0.0)
     }
 
+    fn seeks_jobs() -> bool {
+        true
+    }
+
+    fn may_use_transit() -> bool {
+        true
+    }
+
+    fn may_walk() -> bool {
+        true
+    }
+
     fn interesting_resources() -> &'static [Resource] {
         &[
             Awakeness,
@@ -212,6 +230,10 @@ impl Household for Family {
         self.home.remove_household(self.id_as(), world);
     }
 
+    fn household_type(&self) -> HouseholdTypeToSpawn {
+        HouseholdTypeToSpawn::Family
+    }
+
     fn household_name(&self) -> String {
         format!("The {} Family", family_name(self.id))
     }
@@ -6,6 +6,7 @@ use economy::market::{Deal, EvaluationRequester, EvaluationRequesterID, Evaluate
 use land_use::buildings::BuildingID;
 
 use super::{Household, HouseholdID, HouseholdCore, MemberIdx, Offer};
+use economy::immigration_and_development::HouseholdTypeToSpawn;
 
 #[derive(Compact, Clone)]
 pub struct GrainFarm {
@@ -42,13 +43,7 @@ impl GrainFarm {
                         4,
                         false,
                     ),
-                    Offer::new(
-                        MemberIdx(0),
-                        TimeOfDayRange::new(5, 0, 15, 0),
-                        Deal::new(Some((Resource::Money, 40.0)), Duration::from_hours(4)),
-                        2,
-                        false,
-                    ),
+                    Offer::job(MemberIdx(0), TimeOfDayRange::new(5, 0, 13, 0), 80.0, 2),
                 ].into(),
             ),
         }
@@ -85,9 +80,11 @@ impl Household for GrainFarm {
     }
 
     fn decay(&mut self, dt: Duration, _: &mut World) {
+        let days_worked = self.core.productivity() * dt.as_days();
+
         {
             let grain = self.core.resources.mut_entry_or(Grain, 0.0);
-            *grain += 800.0 * dt.as_days();
+            *grain += 800.0 * days_worked;
         }
     }
 
@@ -95,6 +92,10 @@ impl Household for GrainFarm {
         self.site.remove_household(self.id_as(), world);
     }
 
+    fn household_type(&self) -> HouseholdTypeToSpawn {
+        HouseholdTypeToSpawn::GrainFarm
+    }
+
     fn household_name(&self) -> String {
         "Grain Farm".to_owned()
     }
@@ -104,16 +105,11 @@ impl Household for GrainFarm {
     }
 }
 
-use simulation::{Simulatable, SimulatableID, Sleeper, SleeperID, Instant, TICKS_PER_SIM_SECOND};
-const UPDATE_EVERY_N_SECS: usize = 4;
+use simulation::{Simulatable, SimulatableID, Sleeper, SleeperID, Instant};
 
 impl Simulatable for GrainFarm {
     fn tick(&mut self, _dt: f32, current_instant: Instant, world: &mut World) {
-        if (current_instant.ticks() + self.id.as_raw().instance_id as usize)
-            % (UPDATE_EVERY_N_SECS * TICKS_PER_SIM_SECOND) == 0
-        {
-            self.decay(Duration(UPDATE_EVERY_N_SECS * TICKS_PER_SIM_SECOND), world);
-        }
+        self.on_tick(current_instant, world);
     }
 }
 
@@ -137,6 +133,7 @@ impl RoughLocation for GrainFarm {
 }
 
 use transport::pathfinding::trip::{TripListener, TripListenerID, TripID, TripResult};
+use transport::parking::ParkingSpot;
 
 impl TripListener for GrainFarm {
     fn trip_created(&mut self, trip: TripID, world: &mut World) {
@@ -153,6 +150,10 @@ impl TripListener for GrainFarm {
     ) {
         self.on_trip_result(trip, result, rough_source, rough_destination, world);
     }
+
+    fn trip_parked(&mut self, trip: TripID, spot: ParkingSpot, world: &mut World) {
+        self.on_trip_parked(trip, spot, world);
+    }
 }
 
 pub fn setup(system: &mut ActorSystem) {
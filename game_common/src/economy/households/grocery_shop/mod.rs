@@ -6,6 +6,7 @@ use economy::market::{Deal, EvaluationRequester, EvaluationRequesterID, Evaluate
 use land_use::buildings::BuildingID;
 
 use super::{Household, HouseholdID, HouseholdCore, MemberIdx, Offer};
+use economy::immigration_and_development::HouseholdTypeToSpawn;
 
 #[derive(Compact, Clone)]
 pub struct GroceryShop {
@@ -42,13 +43,7 @@ impl GroceryShop {
                         16,
                         false,
                     ),
-                    Offer::new(
-                        MemberIdx(0),
-                        TimeOfDayRange::new(7, 0, 15, 0),
-                        Deal::new(Some((Money, 50.0)), Duration::from_hours(5)),
-                        5,
-                        false,
-                    ),
+                    Offer::job(MemberIdx(0), TimeOfDayRange::new(7, 0, 15, 0), 80.0, 5),
                 ].into(),
             ),
         }
@@ -98,39 +93,41 @@ impl Household for GroceryShop {
     }
 
     fn decay(&mut self, dt: Duration, _: &mut World) {
+        let days_worked = self.core.productivity() * dt.as_days();
+
         {
             let groceries = self.core.resources.mut_entry_or(Groceries, 0.0);
-            *groceries += 500.0 * dt.as_days();
+            *groceries += 500.0 * days_worked;
         }
 
         {
             let meat = self.core.resources.mut_entry_or(Meat, 0.0);
-            *meat -= 500.0 * 0.2 * dt.as_days();
+            *meat -= 500.0 * 0.2 * days_worked;
         }
 
         {
             let dairy = self.core.resources.mut_entry_or(DairyGoods, 0.0);
-            *dairy -= 500.0 * 0.1 * dt.as_days();
+            *dairy -= 500.0 * 0.1 * days_worked;
         }
 
         {
             let produce = self.core.resources.mut_entry_or(Produce, 0.0);
-            *produce -= 500.0 * 0.1 * dt.as_days();
+            *produce -= 500.0 * 0.1 * days_worked;
         }
 
         {
             let grain = self.core.resources.mut_entry_or(Grain, 0.0);
-            *grain -= 500.0 * 0.05 * dt.as_days();
+            *grain -= 500.0 * 0.05 * days_worked;
         }
 
         {
             let flour = self.core.resources.mut_entry_or(Flour, 0.0);
-            *flour -= 500.0 * 0.01 * dt.as_days();
+            *flour -= 500.0 * 0.01 * days_worked;
         }
 
         {
             let baked = self.core.resources.mut_entry_or(BakedGoods, 0.0);
-            *baked -= 500.0 * 0.3 * dt.as_days();
+            *baked -= 500.0 * 0.3 * days_worked;
         }
     }
 
@@ -145,6 +142,10 @@ impl Household for GroceryShop {
     fn on_destroy(&mut self, world: &mut World) {
         self.site.remove_household(self.id_as(), world);
     }
+
+    fn household_type(&self) -> HouseholdTypeToSpawn {
+        HouseholdTypeToSpawn::GroceryShop
+    }
 }
 
 use super::ResultAspect;
@@ -168,16 +169,11 @@ impl EvaluationRequester for GroceryShop {
     }
 }
 
-use simulation::{Simulatable, SimulatableID, Sleeper, SleeperID, Instant, TICKS_PER_SIM_SECOND};
-const UPDATE_EVERY_N_SECS: usize = 4;
+use simulation::{Simulatable, SimulatableID, Sleeper, SleeperID, Instant};
 
 impl Simulatable for GroceryShop {
     fn tick(&mut self, _dt: f32, current_instant: Instant, world: &mut World) {
-        if (current_instant.ticks() + self.id.as_raw().instance_id as usize)
-            % (UPDATE_EVERY_N_SECS * TICKS_PER_SIM_SECOND) == 0
-        {
-            self.decay(Duration(UPDATE_EVERY_N_SECS * TICKS_PER_SIM_SECOND), world);
-        }
+        self.on_tick(current_instant, world);
     }
 }
 
@@ -196,6 +192,7 @@ impl RoughLocation for GroceryShop {
 }
 
 use transport::pathfinding::trip::{TripListener, TripListenerID, TripID, TripResult};
+use transport::parking::ParkingSpot;
 
 impl TripListener for GroceryShop {
     fn trip_created(&mut self, trip: TripID, world: &mut World) {
@@ -212,6 +209,10 @@ impl TripListener for GroceryShop {
     ) {
         self.on_trip_result(trip, result, rough_source, rough_destination, world);
     }
+
+    fn trip_parked(&mut self, trip: TripID, spot: ParkingSpot, world: &mut World) {
+        self.on_trip_parked(trip, spot, world);
+    }
 }
 
 pub fn setup(system: &mut ActorSystem) {
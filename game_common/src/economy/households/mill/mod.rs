@@ -6,6 +6,7 @@ use economy::market::{Deal, EvaluationRequester, EvaluationRequesterID, Evaluate
 use land_use::buildings::BuildingID;
 
 use super::{Household, HouseholdID, HouseholdCore, MemberIdx, Offer};
+use economy::immigration_and_development::HouseholdTypeToSpawn;
 
 #[derive(Compact, Clone)]
 pub struct Mill {
@@ -42,13 +43,7 @@ impl Mill {
                         4,
                         false,
                     ),
-                    Offer::new(
-                        MemberIdx(0),
-                        TimeOfDayRange::new(5, 0, 15, 0),
-                        Deal::new(Some((Resource::Money, 40.0)), Duration::from_hours(4)),
-                        3,
-                        false,
-                    ),
+                    Offer::job(MemberIdx(0), TimeOfDayRange::new(6, 0, 14, 0), 80.0, 3),
                 ].into(),
             ),
         }
@@ -94,14 +89,16 @@ impl Household for Mill {
     }
 
     fn decay(&mut self, dt: Duration, _: &mut World) {
+        let days_worked = self.core.productivity() * dt.as_days();
+
         {
             let flour = self.core.resources.mut_entry_or(Flour, 0.0);
-            *flour += 800.0 * dt.as_days();
+            *flour += 800.0 * days_worked;
         }
 
         {
             let grain = self.core.resources.mut_entry_or(Grain, 0.0);
-            *grain -= 800.0 * 1.0 * dt.as_days();
+            *grain -= 800.0 * 1.0 * days_worked;
         }
     }
 
@@ -109,6 +106,10 @@ impl Household for Mill {
         self.site.remove_household(self.id_as(), world);
     }
 
+    fn household_type(&self) -> HouseholdTypeToSpawn {
+        HouseholdTypeToSpawn::Mill
+    }
+
     fn household_name(&self) -> String {
         "Mill".to_owned()
     }
@@ -118,16 +119,11 @@ impl Household for Mill {
     }
 }
 
-use simulation::{Simulatable, SimulatableID, Sleeper, SleeperID, Instant, TICKS_PER_SIM_SECOND};
-const UPDATE_EVERY_N_SECS: usize = 4;
+use simulation::{Simulatable, SimulatableID, Sleeper, SleeperID, Instant};
 
 impl Simulatable for Mill {
     fn tick(&mut self, _dt: f32, current_instant: Instant, world: &mut World) {
-        if (current_instant.ticks() + self.id.as_raw().instance_id as usize)
-            % (UPDATE_EVERY_N_SECS * TICKS_PER_SIM_SECOND) == 0
-        {
-            self.decay(Duration(UPDATE_EVERY_N_SECS * TICKS_PER_SIM_SECOND), world);
-        }
+        self.on_tick(current_instant, world);
     }
 }
 
@@ -151,6 +147,7 @@ impl RoughLocation for Mill {
 }
 
 use transport::pathfinding::trip::{TripListener, TripListenerID, TripID, TripResult};
+use transport::parking::ParkingSpot;
 
 impl TripListener for Mill {
     fn trip_created(&mut self, trip: TripID, world: &mut World) {
@@ -167,6 +164,10 @@ impl TripListener for Mill {
     ) {
         self.on_trip_result(trip, result, rough_source, rough_destination, world);
     }
+
+    fn trip_parked(&mut self, trip: TripID, spot: ParkingSpot, world: &mut World) {
+        self.on_trip_parked(trip, spot, world);
+    }
 }
 
 pub fn setup(system: &mut ActorSystem) {
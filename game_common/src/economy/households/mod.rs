@@ -23,7 +23,7 @@ pub struct MemberIdx(usize);
 
 #[cfg(feature = "server")]
 type UI = External<::imgui::Ui<'static>>;
-#[cfg(feature = "browser")]
+#[cfg(not(feature = "server"))]
 type UI = ();
 
 use kay::External;
@@ -33,13 +33,32 @@ TripCostEstimatorID, EvaluatedSearchResult};
 use land_use::buildings::rendering::BuildingInspectorID;
 use super::resources::{Resource, ResourceAmount, ResourceMap, Entry, Inventory};
 use transport::pathfinding::{RoughLocationID, RoughLocation};
-use transport::pathfinding::trip::{TripListener, TripID, TripResult, TripFate};
+use transport::pathfinding::trip::{TripListener, TripID, TripResult, TripFate, TripMode};
+use transport::pedestrians::MAX_WALKING_TRIP_DISTANCE;
+use transport::parking::ParkingSpot;
 use self::tasks::{Task, TaskState, TaskEndScheduler};
 pub use self::offers::{Offer, OfferIdx, OfferID};
+use economy::immigration_and_development::{HouseholdTypeToSpawn, ImmigrationManagerID};
+use economy::shipments::ShipmentID;
+use persistence::PersistenceID;
+use replay::JournalID;
+use statistics::StatisticsID;
+use governance::{TreasuryID, PROPERTY_TAX_PER_DAY, INCOME_TAX_RATE};
+use fnv::FnvHasher;
+use std::hash::{Hash, Hasher};
+use descartes::P2;
 
 const N_TOP_PROBLEMS: usize = 5;
 const DECISION_PAUSE: Ticks = Ticks(200);
 const UPDATE_EVERY_N_SECS: usize = 4;
+const CREDIT_LIMIT: ResourceAmount = 300.0;
+const BANKRUPT_AFTER_HOURS_IN_DEBT: usize = 48;
+const DISSATISFACTION_AVERAGING_HOURS: f32 = 24.0;
+// graveness is importance (0-7) times how much is lacking, so this is about a member
+// that keeps lacking 4 hours of sleep at night (importance 7) or a household
+// that keeps lacking 6 money during the day (importance 5), on average over a day
+const EMIGRATE_ABOVE_DISSATISFACTION: f32 = 30.0;
+const REVIEW_PRICES_EVERY_N_SECS: usize = 60 * 60;
 
 // TODO: make kay_codegen figure this out on it's own
 impl Into<RoughLocationID> for HouseholdID {
@@ -62,6 +81,37 @@ pub trait Household:
         -amount * Self::importance(resource, time)
     }
     fn interesting_resources() -> &'static [Resource];
+    fn can_go_bankrupt() -> bool {
+        true
+    }
+    fn has_fixed_prices() -> bool {
+        false
+    }
+    // businesses only sell while some of their workers are present
+    fn requires_staff() -> bool {
+        true
+    }
+    fn seeks_jobs() -> bool {
+        false
+    }
+    // households that stay unsatisfied for too long move away
+    fn may_emigrate() -> bool {
+        true
+    }
+    // average dissatisfaction above which they do
+    fn emigration_dissatisfaction() -> f32 {
+        EMIGRATE_ABOVE_DISSATISFACTION
+    }
+    fn pays_taxes() -> bool {
+        true
+    }
+    // only people ride transit or walk, goods are always moved by car
+    fn may_use_transit() -> bool {
+        false
+    }
+    fn may_walk() -> bool {
+        false
+    }
     fn decay(&mut self, dt: Duration, world: &mut World);
 
     fn household_name(&self) -> String;
@@ -69,6 +119,12 @@ pub trait Household:
 
     fn receive_deal(&mut self, deal: &Deal, member: MemberIdx, _: &mut World) {
         let core = self.core_mut();
+        core.taxable_income += deal
+            .delta
+            .get(Resource::Money)
+            .cloned()
+            .unwrap_or(0.0)
+            .max(0.0);
         deal.delta.give_to_shared_private(
             &mut core.resources,
             &mut core.member_resources[member.0],
@@ -80,6 +136,12 @@ pub trait Household:
         let core = self.core_mut();
         let provide_awakeness =
             deal.delta.len() == 1 && deal.delta.get(Resource::Awakeness).is_some();
+        core.taxable_income -= deal
+            .delta
+            .get(Resource::Money)
+            .cloned()
+            .unwrap_or(0.0)
+            .min(0.0);
         if !provide_awakeness {
             deal.delta.take_from_shared_private(
                 &mut core.resources,
@@ -189,6 +251,12 @@ pub trait Household:
             }
         }
 
+        for maybe_spot in self.core().member_parking.iter() {
+            if let Some(spot) = *maybe_spot {
+                spot.release(world);
+            }
+        }
+
         for (idx, offer) in self.core().provided_offers.iter().enumerate() {
             Market::local_first(world).withdraw(
                 offer.deal.main_given(),
@@ -203,6 +271,91 @@ pub trait Household:
         self.on_destroy(world);
     }
     fn on_destroy(&mut self, world: &mut World);
+    fn household_type(&self) -> HouseholdTypeToSpawn;
+
+    fn save_snapshot(&mut self, home: P2, requester: PersistenceID, world: &mut World) {
+        let snapshot = self.core().snapshot(self.household_type(), home);
+        requester.on_household_snapshot(snapshot, world);
+    }
+
+    fn restore_snapshot(&mut self, snapshot: &HouseholdSnapshot, _: &mut World) {
+        self.core_mut().restore(snapshot);
+    }
+
+    fn add_to_checksum(&mut self, instant: Instant, journal: JournalID, world: &mut World) {
+        journal.on_checksum_part(instant, self.core().checksum(), world);
+    }
+
+    fn pay_taxes(&mut self, treasury: TreasuryID, world: &mut World) {
+        if !Self::pays_taxes() || self.core().being_destroyed {
+            return;
+        }
+
+        let core = self.core_mut();
+        let taxes = PROPERTY_TAX_PER_DAY + INCOME_TAX_RATE * core.taxable_income;
+        core.taxable_income = 0.0;
+
+        let money = if Self::is_shared(Resource::Money) {
+            core.resources.mut_entry_or(Resource::Money, 0.0)
+        } else {
+            core.member_resources[0].mut_entry_or(Resource::Money, 0.0)
+        };
+        *money -= taxes;
+
+        treasury.receive_taxes(taxes, world);
+    }
+
+    fn add_to_statistics(&mut self, statistics: StatisticsID, world: &mut World) {
+        statistics.on_household_resources(self.core().resource_totals(), world);
+
+        let (employed, unemployed) = if Self::seeks_jobs() {
+            let employed = self.core().employed_members();
+            (employed, self.core().member_tasks.len() - employed)
+        } else {
+            (0, 0)
+        };
+        let vacancies = if Self::requires_staff() {
+            self.core().vacancies()
+        } else {
+            0
+        };
+        statistics.on_household_employment(employed, unemployed, vacancies, world);
+    }
+
+    fn report_employment_to(
+        &mut self,
+        immigration_manager: ImmigrationManagerID,
+        world: &mut World,
+    ) {
+        if self.core().being_destroyed {
+            return;
+        }
+
+        let unemployed = if Self::seeks_jobs() {
+            self.core().member_tasks.len() - self.core().employed_members()
+        } else {
+            0
+        };
+        let vacancies = if Self::requires_staff() {
+            self.core().vacancies()
+        } else {
+            0
+        };
+        immigration_manager.on_household_employment(unemployed, vacancies, world);
+    }
+
+    /// How badly the household is doing right now, per member
+    fn current_dissatisfaction(&self, time: TimeOfDay) -> f32 {
+        let n_members = self.core().member_resources.len();
+        if n_members == 0 {
+            return 0.0;
+        }
+        let total: f32 = (0..n_members)
+            .flat_map(|member| most_grave(self.problems(MemberIdx(member), time)))
+            .map(|(_, graveness)| graveness)
+            .sum();
+        total / n_members as f32
+    }
 
     fn update_core(&mut self, current_instant: Instant, world: &mut World) {
         if let DecisionState::None = self.core().decision_state {
@@ -229,9 +382,8 @@ pub trait Household:
         };
     }
 
-    fn top_problems(&self, member: MemberIdx, time: TimeOfDay) -> Vec<(Resource, f32)> {
-        let mut resource_graveness = self
-            .core()
+    fn problems(&self, member: MemberIdx, time: TimeOfDay) -> Vec<(Resource, f32)> {
+        self.core()
             .resources
             .iter()
             .chain(self.core().member_resources[member.0].iter())
@@ -243,7 +395,11 @@ pub trait Household:
                     None
                 }
             })
-            .collect::<Vec<_>>();
+            .collect()
+    }
+
+    fn top_problems(&self, member: MemberIdx, time: TimeOfDay) -> Vec<(Resource, f32)> {
+        let mut resource_graveness = self.problems(member, time);
         resource_graveness.sort_by_key(|&(_r, i)| OrderedFloat(i));
 
         resource_graveness.truncate(N_TOP_PROBLEMS);
@@ -324,6 +480,12 @@ pub trait Household:
         let done = {
             let core = self.core_mut();
 
+            let available_money = if let DecisionState::Choosing(member, ..) = core.decision_state {
+                core.money(member)
+            } else {
+                0.0
+            };
+
             if let DecisionState::Choosing(_, instant, ref top_problems, ref mut entries) =
                 core.decision_state
             {
@@ -348,6 +510,7 @@ pub trait Household:
                                         &mut core.log,
                                         top_problems,
                                         evaluated_deal,
+                                        available_money,
                                     );
                                     if new_deal_usefulness > entry.best_deal_usefulness {
                                         entry.best_deal = COption(Some(evaluated_deal.clone()));
@@ -392,7 +555,26 @@ pub trait Household:
         log: &mut HouseholdLog,
         top_problems: &[(Resource, f32)],
         evaluated: &EvaluatedDeal,
+        available_money: ResourceAmount,
     ) -> f32 {
+        let cost = -evaluated
+            .deal
+            .delta
+            .get(Resource::Money)
+            .cloned()
+            .unwrap_or(0.0);
+        if cost > 0.0 && cost > available_money + CREDIT_LIMIT {
+            log.log(
+                format!(
+                    "Can't afford {} (costs {}, has {})\n",
+                    evaluated.deal.main_given(),
+                    cost,
+                    available_money
+                ).as_str(),
+            );
+            return 0.0;
+        }
+
         let resource_graveness_improvement: f32 = top_problems
             .iter()
             .map(|&(resource, graveness)| {
@@ -423,7 +605,9 @@ pub trait Household:
             let core = self.core_mut();
 
             if let DecisionState::Choosing(member, instant, _, ref entries) = core.decision_state {
-                let maybe_best = most_useful_evaluated_deal(entries);
+                // money might have changed since the deals were evaluated
+                let maybe_best =
+                    most_useful_evaluated_deal(entries, |deal| core.can_afford(member, deal));
 
                 if let Some(best) = maybe_best {
                     let task = &mut core.member_tasks[member.0];
@@ -432,6 +616,7 @@ pub trait Household:
                         Task {
                             goal: Some((best.deal.main_given(), best.offer)),
                             duration: best.deal.duration,
+                            travel_distance: best.travel_distance,
                             state: TaskState::GettingReadyAt(location),
                         }
                     } else {
@@ -480,12 +665,15 @@ pub trait Household:
             }
 
             self.core_mut().decision_state = DecisionState::WaitingForTrip(member);
-            best.offer.household.request_receive_deal(
-                best.offer.idx,
-                id_as_household,
-                member,
-                world,
-            );
+            // bulk goods are only shipped once the member arrived at the supplier
+            if !best.deal.main_given().is_freight() {
+                best.offer.household.request_receive_deal(
+                    best.offer.idx,
+                    id_as_household,
+                    member,
+                    world,
+                );
+            }
             self.start_trip(member, instant, world);
         } else {
             self.core_mut()
@@ -495,13 +683,19 @@ pub trait Household:
             Simulation::local_first(world).wake_up_in(DECISION_PAUSE, id_as_sleeper, world);
         }
 
-        fn most_useful_evaluated_deal(
+        fn most_useful_evaluated_deal<F: Fn(&Deal) -> bool>(
             entries: &CDict<Resource, DecisionResourceEntry>,
+            can_afford: F,
         ) -> Option<EvaluatedDeal> {
-            entries
-                .values()
-                .max_by_key(|decision_entry| OrderedFloat(decision_entry.best_deal_usefulness))
-                .and_then(|best_entry| best_entry.best_deal.as_ref().cloned())
+            most_useful_affordable(
+                entries.values().filter_map(|decision_entry| {
+                    decision_entry
+                        .best_deal
+                        .as_ref()
+                        .map(|best_deal| (decision_entry.best_deal_usefulness, best_deal))
+                }),
+                |evaluated| can_afford(&evaluated.deal),
+            ).cloned()
         }
     }
 
@@ -509,13 +703,29 @@ pub trait Household:
         if let Task {
             goal: Some((_, offer)),
             state: TaskState::GettingReadyAt(source),
+            travel_distance,
             ..
         } = self.core().member_tasks[member.0]
         {
+            let mode = if Self::may_walk() && travel_distance < MAX_WALKING_TRIP_DISTANCE {
+                TripMode::Walking
+            } else if Self::may_use_transit() {
+                TripMode::CarOrTransit
+            } else {
+                TripMode::Car
+            };
+
+            // a walking member leaves their car where it is parked
+            if mode != TripMode::Walking {
+                if let Some(spot) = self.core_mut().member_parking[member.0].take() {
+                    spot.release(world);
+                }
+            }
             TripID::spawn(
                 source,
                 offer.household.into(),
                 Some(self.id_as()),
+                mode,
                 instant,
                 world,
             );
@@ -535,6 +745,26 @@ pub trait Household:
             };
     }
 
+    fn on_trip_parked(&mut self, trip: TripID, spot: ParkingSpot, world: &mut World) {
+        let maybe_member = self.core().member_tasks.iter().position(|task| {
+            if let TaskState::InTrip(task_trip_id) = task.state {
+                task_trip_id == trip
+            } else {
+                false
+            }
+        });
+
+        if let Some(member) = maybe_member {
+            // walks without a sidewalk are driven after all, only the newest spot is kept
+            if let Some(previous_spot) = self.core_mut().member_parking[member].take() {
+                previous_spot.release(world);
+            }
+            self.core_mut().member_parking[member] = Some(spot);
+        } else {
+            spot.release(world);
+        }
+    }
+
     fn on_trip_result(
         &mut self,
         trip: TripID,
@@ -594,6 +824,15 @@ pub trait Household:
         match result.fate {
             TripFate::Success(instant) => {
                 self.start_task(matching_task_member, instant, rough_destination, world);
+                if matching_resource.is_freight() {
+                    matching_offer.household.request_shipment(
+                        matching_offer.idx,
+                        self.id_as(),
+                        matching_task_member,
+                        instant,
+                        world,
+                    );
+                }
             }
             fate => {
                 self.core_mut().log.log(
@@ -603,9 +842,9 @@ pub trait Household:
                     ).as_str(),
                 );
 
-                if let Some((_, offer)) = self.core().member_tasks[matching_task_member.0].goal {
-                    offer.household.request_receive_undo_deal(
-                        offer.idx,
+                if !matching_resource.is_freight() {
+                    matching_offer.household.request_receive_undo_deal(
+                        matching_offer.idx,
                         self.id_as(),
                         matching_task_member,
                         world,
@@ -681,9 +920,66 @@ pub trait Household:
             % (UPDATE_EVERY_N_SECS * TICKS_PER_SIM_SECOND) == 0
         {
             self.decay(Duration(UPDATE_EVERY_N_SECS * TICKS_PER_SIM_SECOND), world);
+
+            if Self::can_go_bankrupt()
+                && !self.core().being_destroyed
+                && self.core_mut().update_solvency(current_instant)
+            {
+                println!("{} went bankrupt and emigrates", self.household_name());
+                self.destroy(world);
+            } else if Self::may_emigrate() && !self.core().being_destroyed {
+                let current = self.current_dissatisfaction(TimeOfDay::from(current_instant));
+                let dt = Duration(UPDATE_EVERY_N_SECS * TICKS_PER_SIM_SECOND);
+
+                let threshold = Self::emigration_dissatisfaction();
+
+                if self
+                    .core_mut()
+                    .update_dissatisfaction(current, dt, threshold)
+                {
+                    println!(
+                        "{} is chronically unsatisfied and emigrates",
+                        self.household_name()
+                    );
+                    self.destroy(world);
+                }
+            }
+        }
+
+        if !Self::has_fixed_prices()
+            && (current_instant.ticks() + self.id().as_raw().instance_id as usize)
+                % (REVIEW_PRICES_EVERY_N_SECS * TICKS_PER_SIM_SECOND) == 0
+        {
+            self.review_prices(current_instant, world);
+        }
+    }
+
+    fn review_prices(&mut self, instant: Instant, world: &mut World) {
+        if self.core().being_destroyed {
+            return;
+        }
+
+        for (idx, offer) in self.core().provided_offers.iter().enumerate() {
+            if !offer.is_internal && offer.has_price() {
+                Market::global_first(world).report_offer(
+                    OfferID {
+                        household: self.id_as(),
+                        idx: OfferIdx(idx as u16),
+                    },
+                    offer.deal.main_given(),
+                    offer.utilization(),
+                    offer.unit_price(),
+                    instant,
+                    world,
+                );
+            }
         }
     }
 
+    fn adjust_offer_price(&mut self, offer_idx: OfferIdx, pressure: f32, _: &mut World) {
+        self.get_offer_mut(offer_idx).adjust_price(pressure);
+    }
+
     fn get_offer(&self, idx: OfferIdx) -> &Offer {
         &self.core().provided_offers[idx.0 as usize]
     }
@@ -701,10 +997,15 @@ pub trait Household:
         world: &mut World,
     ) {
         let offer = self.get_offer(offer_idx);
-
-        if offer
-            .opening_hours
-            .end_after_on_same_day(TimeOfDay::from(instant))
+        let lacks_staff = Self::requires_staff()
+            && !offer.is_job()
+            && self.core().has_jobs()
+            && self.core().workers_present() == 0;
+
+        if !lacks_staff
+            && offer
+                .opening_hours
+                .end_after_on_same_day(TimeOfDay::from(instant))
         {
             let search_result = EvaluatedSearchResult {
                 resource: offer.deal.main_given(),
@@ -715,6 +1016,9 @@ pub trait Household:
                     },
                     deal: offer.deal.clone(),
                     opening_hours: offer.opening_hours,
+                    // only known once the trip cost estimator found the actual route,
+                    // until then nobody should assume they can walk there
+                    travel_distance: ::std::f32::INFINITY,
                 }].into(),
             };
             TripCostEstimatorID::spawn(
@@ -764,6 +1068,28 @@ pub trait Household:
         requester.provide_deal(offer.deal, requester_member, world);
     }
 
+    fn request_shipment(
+        &mut self,
+        offer_idx: OfferIdx,
+        requester: HouseholdID,
+        requester_member: MemberIdx,
+        instant: Instant,
+        world: &mut World,
+    ) {
+        let offer = self.get_offer(offer_idx).clone();
+        // loaded right away, so the same goods can't be shipped twice
+        self.provide_deal(&offer.deal.given_part(), offer.offering_member, world);
+        ShipmentID::spawn(
+            self.id_as(),
+            offer.offering_member,
+            requester,
+            requester_member,
+            offer.deal,
+            instant,
+            world,
+        );
+    }
+
     fn started_using(
         &mut self,
         offer_idx: OfferIdx,
@@ -913,6 +1239,25 @@ pub trait Household:
             ui.window(im_str!("Building")).build(|| {
                 ui.tree_node(im_str!("{}", self.household_name()))
                     .build(|| {
+                        match self.core().solvency {
+                            Solvency::Solvent => {}
+                            Solvency::InDebtSince(_) => ui.text(im_str!("In debt")),
+                            Solvency::Bankrupt => ui.text(im_str!("Bankrupt")),
+                        }
+                        if Self::seeks_jobs() {
+                            ui.text(im_str!(
+                                "Employed: {} of {}",
+                                self.core().employed_members(),
+                                self.core().member_tasks.len()
+                            ));
+                        }
+                        if self.core().has_jobs() {
+                            ui.text(im_str!(
+                                "Workers present: {}, vacancies: {}",
+                                self.core().workers_present(),
+                                self.core().vacancies()
+                            ));
+                        }
                         // ui.text(im_str!(
                         //     "({})",
                         //     match self.decision_state {
@@ -1021,9 +1366,21 @@ pub struct HouseholdCore {
     decision_state: DecisionState,
     used_offers: ResourceMap<OfferID>,
     member_used_offers: CVec<ResourceMap<OfferID>>,
+    member_parking: CVec<Option<ParkingSpot>>,
     provided_offers: CVec<Offer>,
     log: HouseholdLog,
     being_destroyed: bool,
+    solvency: Solvency,
+    taxable_income: ResourceAmount,
+    // follows the current dissatisfaction slowly, so only lasting problems count
+    dissatisfaction: f32,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Solvency {
+    Solvent,
+    InDebtSince(Instant),
+    Bankrupt,
 }
 
 impl HouseholdCore {
@@ -1054,10 +1411,200 @@ impl HouseholdCore {
             decision_state: DecisionState::None,
             used_offers: ResourceMap::new(),
             member_used_offers: vec![ResourceMap::new(); n_members].into(),
+            member_parking: vec![None; n_members].into(),
             provided_offers,
             log: HouseholdLog(CString::new()),
             being_destroyed: false,
+            solvency: Solvency::Solvent,
+            taxable_income: 0.0,
+            dissatisfaction: 0.0,
+        }
+    }
+
+    fn jobs(&self) -> impl Iterator<Item = &Offer> {
+        self.provided_offers.iter().filter(|offer| offer.is_job())
+    }
+
+    pub fn has_jobs(&self) -> bool {
+        self.jobs().next().is_some()
+    }
+
+    pub fn workers_present(&self) -> usize {
+        self.jobs().map(|job| job.active_users.len()).sum()
+    }
+
+    pub fn vacancies(&self) -> usize {
+        self.jobs().map(|job| job.vacancies()).sum()
+    }
+
+    /// Members that hold a job somewhere
+    pub fn employed_members(&self) -> usize {
+        self.member_used_offers
+            .iter()
+            .filter(|used_offers| used_offers.get(Resource::Money).is_some())
+            .count()
+    }
+
+    /// How much a business produces right now, relative to its nominal daily rate.
+    /// Work only happens during shifts, so a fully staffed business
+    /// works faster while it's open to reach the same daily output.
+    pub fn productivity(&self) -> f32 {
+        let (n_jobs, total) = self.jobs().fold((0, 0.0), |(n_jobs, total), job| {
+            let staffed = job.active_users.len() as f32 / job.max_users.max(1) as f32;
+            let shift_hours = job.opening_hours.length().as_hours().max(1.0);
+            (n_jobs + 1, total + staffed * 24.0 / shift_hours)
+        });
+
+        if n_jobs == 0 {
+            1.0
+        } else {
+            total / n_jobs as f32
+        }
+    }
+
+    /// Money available to a member, including what the household shares
+    pub fn money(&self, member: MemberIdx) -> ResourceAmount {
+        self.resources.get(Resource::Money).cloned().unwrap_or(0.0)
+            + self.member_resources[member.0]
+                .get(Resource::Money)
+                .cloned()
+                .unwrap_or(0.0)
+    }
+
+    pub fn can_afford(&self, member: MemberIdx, deal: &Deal) -> bool {
+        let cost = -deal.delta.get(Resource::Money).cloned().unwrap_or(0.0);
+        cost <= 0.0 || cost <= self.money(member) + CREDIT_LIMIT
+    }
+
+    /// Returns true if the household has been unsatisfied for long enough to emigrate
+    pub fn update_dissatisfaction(&mut self, current: f32, dt: Duration, threshold: f32) -> bool {
+        let factor = (dt.as_hours() / DISSATISFACTION_AVERAGING_HOURS).min(1.0);
+        self.dissatisfaction += factor * (current - self.dissatisfaction);
+        self.dissatisfaction > threshold
+    }
+
+    /// Returns true if the household just went bankrupt
+    pub fn update_solvency(&mut self, instant: Instant) -> bool {
+        let total_money: ResourceAmount = Some(&self.resources)
+            .into_iter()
+            .chain(&self.member_resources)
+            .map(|inventory| inventory.get(Resource::Money).cloned().unwrap_or(0.0))
+            .sum();
+
+        let was_bankrupt = self.solvency == Solvency::Bankrupt;
+        self.solvency = self.solvency.next(total_money, instant);
+        !was_bankrupt && self.solvency == Solvency::Bankrupt
+    }
+}
+
+/// The gravest problems first, so the least grave ones are dropped
+// only used for dissatisfaction, task choice keeps going through `top_problems`
+fn most_grave(mut resource_graveness: Vec<(Resource, f32)>) -> Vec<(Resource, f32)> {
+    resource_graveness.sort_by_key(|&(_r, graveness)| ::std::cmp::Reverse(OrderedFloat(graveness)));
+    resource_graveness.truncate(N_TOP_PROBLEMS);
+    resource_graveness
+}
+
+/// The most useful of the given options that can still be afforded,
+/// so a deal that became too expensive falls back to the next best one
+pub fn most_useful_affordable<T, I: Iterator<Item = (f32, T)>, F: Fn(&T) -> bool>(
+    options: I,
+    can_afford: F,
+) -> Option<T> {
+    let mut ranked = options.collect::<Vec<_>>();
+    ranked.sort_by_key(|&(usefulness, _)| ::std::cmp::Reverse(OrderedFloat(usefulness)));
+    ranked
+        .into_iter()
+        .map(|(_, option)| option)
+        .find(|option| can_afford(option))
+}
+
+impl Solvency {
+    pub fn next(self, total_money: ResourceAmount, instant: Instant) -> Solvency {
+        match self {
+            Solvency::Bankrupt => Solvency::Bankrupt,
+            _ if total_money >= 0.0 => Solvency::Solvent,
+            Solvency::Solvent => Solvency::InDebtSince(instant),
+            // time moves backwards when a snapshot is loaded, then the debt starts now
+            Solvency::InDebtSince(since) if since > instant => Solvency::InDebtSince(instant),
+            Solvency::InDebtSince(since) => {
+                if instant.ticks().saturating_sub(since.ticks())
+                    > Ticks::from(Duration::from_hours(BANKRUPT_AFTER_HOURS_IN_DEBT)).0
+                {
+                    Solvency::Bankrupt
+                } else {
+                    Solvency::InDebtSince(since)
+                }
+            }
+        }
+    }
+}
+
+/// The part of a household that survives a save and load. Tasks, trips and
+/// used offers refer to actors that don't outlive the process, so members
+/// just start deciding again after being restored.
+#[derive(Compact, Clone, Serialize, Deserialize)]
+pub struct HouseholdSnapshot {
+    pub household_type: HouseholdTypeToSpawn,
+    pub home: P2,
+    pub resources: CVec<(Resource, ResourceAmount)>,
+    pub member_resources: CVec<CVec<(Resource, ResourceAmount)>>,
+}
+
+impl HouseholdCore {
+    pub fn snapshot(&self, household_type: HouseholdTypeToSpawn, home: P2) -> HouseholdSnapshot {
+        fn to_pairs(inventory: &Inventory) -> CVec<(Resource, ResourceAmount)> {
+            inventory
+                .iter()
+                .map(|&Entry(resource, amount)| (resource, amount))
+                .collect()
+        }
+
+        HouseholdSnapshot {
+            household_type,
+            home,
+            resources: to_pairs(&self.resources),
+            member_resources: self.member_resources.iter().map(to_pairs).collect(),
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: &HouseholdSnapshot) {
+        self.resources = snapshot.resources.iter().cloned().collect();
+        for (member_resources, saved) in self
+            .member_resources
+            .iter_mut()
+            .zip(&snapshot.member_resources)
+        {
+            *member_resources = saved.iter().cloned().collect();
+        }
+    }
+}
+
+impl HouseholdCore {
+    pub fn checksum(&self) -> u64 {
+        let mut hasher = FnvHasher::default();
+        for inventory in Some(&self.resources).into_iter().chain(&self.member_resources) {
+            for &Entry(resource, amount) in inventory.iter() {
+                resource.hash(&mut hasher);
+                amount.to_bits().hash(&mut hasher);
+            }
         }
+        hasher.finish()
+    }
+
+    // household and member resources together
+    pub fn resource_totals(&self) -> CVec<(Resource, ResourceAmount)> {
+        let mut totals = CVec::<(Resource, ResourceAmount)>::new();
+        for inventory in Some(&self.resources).into_iter().chain(&self.member_resources) {
+            for &Entry(resource, amount) in inventory.iter() {
+                if let Some(total) = totals.iter_mut().find(|total| total.0 == resource) {
+                    total.1 += amount;
+                    continue;
+                }
+                totals.push((resource, amount));
+            }
+        }
+        totals
     }
 }
 
@@ -1093,3 +1640,70 @@ pub fn spawn(world: &mut World) {
 
 mod kay_auto;
 pub use self::kay_auto::*;
+
+#[test]
+fn test_solvency_goes_bankrupt_after_lasting_debt() {
+    let start = Instant::new(1000);
+    let bankrupt_after = Ticks::from(Duration::from_hours(BANKRUPT_AFTER_HOURS_IN_DEBT));
+
+    let solvency = Solvency::Solvent.next(50.0, start);
+    assert_eq!(solvency, Solvency::Solvent);
+
+    let solvency = solvency.next(-10.0, start);
+    assert_eq!(solvency, Solvency::InDebtSince(start));
+
+    let solvency = solvency.next(-10.0, start + Ticks(bankrupt_after.0 - 1));
+    assert_eq!(solvency, Solvency::InDebtSince(start));
+
+    let solvency = solvency.next(-10.0, start + Ticks(bankrupt_after.0 + 1));
+    assert_eq!(solvency, Solvency::Bankrupt);
+
+    assert_eq!(solvency.next(1000.0, start), Solvency::Bankrupt);
+}
+
+#[test]
+fn test_solvency_recovers_when_paying_debt() {
+    let start = Instant::new(1000);
+    let solvency = Solvency::Solvent.next(-10.0, start);
+    assert_eq!(solvency.next(0.0, start + Ticks(10)), Solvency::Solvent);
+}
+
+#[test]
+fn test_solvency_survives_time_moving_backwards() {
+    let solvency = Solvency::InDebtSince(Instant::new(1_000_000));
+    let earlier = Instant::new(10);
+    assert_eq!(
+        solvency.next(-10.0, earlier),
+        Solvency::InDebtSince(earlier)
+    );
+}
+
+#[test]
+fn test_most_useful_affordable_falls_back() {
+    let options = vec![(0.5, 20.0), (2.0, 500.0), (1.0, 80.0)];
+    let affordable = |cost: &f32| *cost <= 100.0;
+    assert_eq!(
+        most_useful_affordable(options.clone().into_iter(), affordable),
+        Some(80.0)
+    );
+    assert_eq!(
+        most_useful_affordable(options.into_iter(), |_: &f32| false),
+        None
+    );
+}
+
+#[test]
+fn test_most_grave_keeps_the_gravest_problems() {
+    let problems = vec![
+        (Resource::Groceries, 1.0),
+        (Resource::Awakeness, 28.0),
+        (Resource::Satiety, 3.0),
+        (Resource::Money, 15.0),
+        (Resource::Groceries, 0.5),
+        (Resource::Satiety, 10.0),
+    ];
+    let top = most_grave(problems);
+    assert_eq!(top.len(), N_TOP_PROBLEMS);
+    assert_eq!(top[0], (Resource::Awakeness, 28.0));
+    assert!(top.iter().all(|&(_, graveness)| graveness > 0.5));
+}
@@ -7,8 +7,10 @@ use economy::market::{Deal, EvaluationRequester, EvaluationRequesterID, Evaluate
 use land_use::buildings::BuildingID;
 use transport::pathfinding::RoughLocationID;
 use transport::pathfinding::trip::{TripListener, TripListenerID, TripID, TripResult};
+use transport::parking::ParkingSpot;
 
 use super::{Household, HouseholdID, HouseholdCore, MemberIdx, Offer};
+use economy::immigration_and_development::HouseholdTypeToSpawn;
 
 #[derive(Compact, Clone)]
 pub struct NeighboringTownTrade {
@@ -27,13 +29,7 @@ impl NeighboringTownTrade {
         simulation.wake_up_in(Ticks(0), id.into(), world);
 
         let offers = vec![
-            Offer::new(
-                MemberIdx(0),
-                TimeOfDayRange::new(5, 0, 15, 0),
-                Deal::new(Some((Resource::Money, 50.0)), Duration::from_hours(5)),
-                300,
-                false,
-            ),
+            Offer::job(MemberIdx(0), TimeOfDayRange::new(7, 0, 15, 0), 80.0, 300),
             // Offer::new(
             //     MemberIdx(0),
             //     TimeOfDayRange::new(7, 0, 20, 0),
@@ -234,6 +230,27 @@ impl Household for NeighboringTownTrade {
         ]
     }
 
+    // stands in for the whole outside economy
+    fn can_go_bankrupt() -> bool {
+        false
+    }
+
+    fn may_emigrate() -> bool {
+        false
+    }
+
+    fn has_fixed_prices() -> bool {
+        true
+    }
+
+    fn requires_staff() -> bool {
+        false
+    }
+
+    fn pays_taxes() -> bool {
+        false
+    }
+
     fn decay(&mut self, _dt: Duration, _: &mut World) {}
 
     fn household_name(&self) -> String {
@@ -247,6 +264,10 @@ impl Household for NeighboringTownTrade {
     fn on_destroy(&mut self, world: &mut World) {
         self.town.remove_household(self.id_as(), world);
     }
+
+    fn household_type(&self) -> HouseholdTypeToSpawn {
+        HouseholdTypeToSpawn::NeighboringTownTrade
+    }
 }
 
 use simulation::{Sleeper, SleeperID};
@@ -293,6 +314,10 @@ impl TripListener for NeighboringTownTrade {
     ) {
         self.on_trip_result(trip, result, rough_source, rough_destination, world);
     }
+
+    fn trip_parked(&mut self, trip: TripID, spot: ParkingSpot, world: &mut World) {
+        self.on_trip_parked(trip, spot, world);
+    }
 }
 
 impl Simulatable for NeighboringTownTrade {
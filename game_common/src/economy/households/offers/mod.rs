@@ -1,7 +1,8 @@
 use compact::CVec;
 use economy::market::Deal;
+use economy::resources::{Resource, ResourceAmount};
 use super::{HouseholdID, MemberIdx};
-use simulation::TimeOfDayRange;
+use simulation::{TimeOfDayRange, Duration};
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub struct OfferIdx(pub u16);
@@ -22,8 +23,17 @@ pub struct Offer {
     pub users: CVec<(HouseholdID, Option<MemberIdx>)>,
     pub active_users: CVec<(HouseholdID, MemberIdx)>,
     pub being_withdrawn: bool,
+    pub base_money: ResourceAmount,
 }
 
+// How far a price can move in one review, at full pressure
+const MAX_PRICE_CHANGE: f32 = 0.05;
+// Prices stay within these factors of the initial price
+const MIN_PRICE_FACTOR: f32 = 0.25;
+const MAX_PRICE_FACTOR: f32 = 4.0;
+// Unit prices are per hour or per unit, this keeps tiny deals from yielding infinite prices
+const MIN_PRICED_AMOUNT: f32 = 0.01;
+
 impl Offer {
     pub fn new(
         offering_member: MemberIdx,
@@ -32,6 +42,7 @@ impl Offer {
         max_users: usize,
         is_internal: bool,
     ) -> Offer {
+        let base_money = deal.delta.get(Resource::Money).cloned().unwrap_or(0.0);
         Offer {
             offering_member,
             opening_hours,
@@ -41,8 +52,80 @@ impl Offer {
             is_internal,
             max_users,
             being_withdrawn: false,
+            base_money,
+        }
+    }
+
+    /// An employment contract: workers come in for the whole shift and get paid the wage
+    pub fn job(
+        employing_member: MemberIdx,
+        shift: TimeOfDayRange,
+        wage: ResourceAmount,
+        max_workers: usize,
+    ) -> Offer {
+        Offer::new(
+            employing_member,
+            shift,
+            Deal::new(Some((Resource::Money, wage)), shift.length()),
+            max_workers,
+            false,
+        )
+    }
+
+    pub fn is_job(&self) -> bool {
+        self.deal.main_given() == Resource::Money
+    }
+
+    pub fn vacancies(&self) -> usize {
+        self.max_users.saturating_sub(self.users.len())
+    }
+
+    pub fn has_price(&self) -> bool {
+        // a job without a shift has no wage per hour that could be compared or adjusted
+        self.base_money != 0.0 && !(self.is_job() && self.deal.duration == Duration(0))
+    }
+
+    pub fn utilization(&self) -> f32 {
+        self.users.len() as f32 / self.max_users.max(1) as f32
+    }
+
+    /// Money per unit of the main given resource, or for jobs, wage per hour
+    pub fn unit_price(&self) -> ResourceAmount {
+        let money = self.deal.delta.get(Resource::Money).cloned().unwrap_or(0.0);
+        let main_given = self.deal.main_given();
+
+        if self.is_job() {
+            money / self.deal.duration.as_hours().max(MIN_PRICED_AMOUNT)
+        } else {
+            let amount = self.deal.delta.get(main_given).cloned().unwrap_or(1.0);
+            -money / amount.abs().max(MIN_PRICED_AMOUNT)
         }
     }
+
+    /// Positive pressure means demand outstrips supply
+    pub fn adjust_price(&mut self, pressure: f32) {
+        if !self.has_price() {
+            return;
+        }
+
+        let factor = 1.0 + MAX_PRICE_CHANGE * pressure.max(-1.0).min(1.0);
+        let is_job = self.is_job();
+        let base_money = self.base_money;
+        let money = self.deal.delta.mut_entry_or(Resource::Money, base_money);
+
+        // high demand for goods raises their price, high demand for jobs lowers wages
+        let adjusted = if is_job {
+            *money / factor
+        } else {
+            *money * factor
+        };
+
+        *money = adjusted
+            .abs()
+            .max(MIN_PRICE_FACTOR * base_money.abs())
+            .min(MAX_PRICE_FACTOR * base_money.abs())
+            * base_money.signum();
+    }
 }
 
 //     // The offer stays alive until the withdrawal is confirmed
@@ -56,3 +139,37 @@ impl Offer {
 //     pub fn withdraw_internal(&mut self, _: &mut World) -> Fate {
 //         Fate::Die
 //     }
+
+#[test]
+fn test_zero_duration_job_has_no_price() {
+    // a shift that starts and ends at the same time
+    let mut job = Offer::job(MemberIdx(0), TimeOfDayRange::new(9, 0, 9, 0), 10.0, 2);
+
+    assert!(!job.has_price());
+    assert!(job.unit_price().is_finite());
+
+    job.adjust_price(1.0);
+    assert_eq!(job.deal.delta.get(Resource::Money).cloned(), Some(10.0));
+}
+
+#[test]
+fn test_price_adjustment_stays_finite_and_bounded() {
+    let mut offer = Offer::new(
+        MemberIdx(0),
+        TimeOfDayRange::new(7, 0, 20, 0),
+        Deal::new(
+            vec![(Resource::Groceries, 3.0), (Resource::Money, -15.0)],
+            Duration(0),
+        ),
+        5,
+        false,
+    );
+
+    for _ in 0..1000 {
+        offer.adjust_price(1.0);
+    }
+
+    let price = offer.unit_price();
+    assert!(price.is_finite());
+    assert!(price <= MAX_PRICE_FACTOR * 5.0 + 0.001);
+}
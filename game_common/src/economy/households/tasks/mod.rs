@@ -20,6 +20,7 @@ pub enum TaskState {
 pub struct Task {
     pub goal: Option<(Resource, OfferID)>,
     pub duration: Duration,
+    pub travel_distance: f32,
     pub state: TaskState,
 }
 
@@ -28,6 +29,7 @@ impl Task {
         Task {
             goal: None,
             duration: Duration(0),
+            travel_distance: 0.0,
             state: TaskState::IdleAt(location),
         }
     }
@@ -1,9 +1,10 @@
 use kay::{World, Actor, ActorSystem};
-use compact::COption;
+use compact::{COption, CVec};
 use land_use::buildings::{UnitType, Building, BuildingID, UnitIdx};
 use simulation::{Sleeper, SleeperID, Instant, SimulationID, Duration};
 use util::random::{seed, Rng};
 
+use economy::households::HouseholdID;
 use economy::households::family::FamilyID;
 use economy::households::grocery_shop::GroceryShopID;
 use economy::households::cow_farm::CowFarmID;
@@ -15,12 +16,14 @@ use economy::households::neighboring_town_trade::NeighboringTownTradeID;
 use land_use::buildings::BuildingStyle;
 use land_use::vacant_lots::VacantLot;
 use land_use::zone_planning::BuildingIntent;
+use economy::market::Market;
+use economy::resources::Resource;
 use planning::{PlanManagerID, Proposal, StepID, Plan, GestureID, Gesture, GestureIntent};
 
 // TODO: somehow get rid of this horrible duplication by having something like
 // a pointer to an abstract Household trait...
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum HouseholdTypeToSpawn {
     Family,
     GroceryShop,
@@ -45,10 +48,23 @@ pub fn unit_type_for(household_type: HouseholdTypeToSpawn) -> UnitType {
     }
 }
 
-pub fn building_style_for(household_type: HouseholdTypeToSpawn) -> BuildingStyle {
+// how often new development is a multi-unit building instead of a single-unit one
+const MULTI_UNIT_DEVELOPMENT_SHARE: f32 = 0.3;
+
+pub fn building_style_for(household_type: HouseholdTypeToSpawn, instant: Instant) -> BuildingStyle {
+    let multi_unit = seed(instant).next_f32() < MULTI_UNIT_DEVELOPMENT_SHARE;
+
     match household_type {
-        HouseholdTypeToSpawn::Family => BuildingStyle::FamilyHouse,
-        HouseholdTypeToSpawn::GroceryShop => BuildingStyle::GroceryShop,
+        HouseholdTypeToSpawn::Family => if multi_unit {
+            BuildingStyle::ApartmentBlock
+        } else {
+            BuildingStyle::FamilyHouse
+        },
+        HouseholdTypeToSpawn::GroceryShop => if multi_unit {
+            BuildingStyle::ShopWithFlats
+        } else {
+            BuildingStyle::GroceryShop
+        },
         HouseholdTypeToSpawn::GrainFarm
         | HouseholdTypeToSpawn::CowFarm
         | HouseholdTypeToSpawn::VegetableFarm => BuildingStyle::Field,
@@ -58,12 +74,47 @@ pub fn building_style_for(household_type: HouseholdTypeToSpawn) -> BuildingStyle
     }
 }
 
+pub fn spawn_household(
+    household_type: HouseholdTypeToSpawn,
+    building_id: BuildingID,
+    simulation: SimulationID,
+    world: &mut World,
+) -> HouseholdID {
+    match household_type {
+        HouseholdTypeToSpawn::Family => {
+            FamilyID::move_into(3, building_id, simulation, world).into()
+        }
+        HouseholdTypeToSpawn::GroceryShop => {
+            GroceryShopID::move_into(building_id, simulation, world).into()
+        }
+        HouseholdTypeToSpawn::GrainFarm => {
+            GrainFarmID::move_into(building_id, simulation, world).into()
+        }
+        HouseholdTypeToSpawn::CowFarm => {
+            CowFarmID::move_into(building_id, simulation, world).into()
+        }
+        HouseholdTypeToSpawn::VegetableFarm => {
+            VegetableFarmID::move_into(building_id, simulation, world).into()
+        }
+        HouseholdTypeToSpawn::Mill => MillID::move_into(building_id, simulation, world).into(),
+        HouseholdTypeToSpawn::Bakery => BakeryID::move_into(building_id, simulation, world).into(),
+        HouseholdTypeToSpawn::NeighboringTownTrade => {
+            NeighboringTownTradeID::move_into(building_id, simulation, world).into()
+        }
+    }
+}
+
 #[derive(Compact, Clone)]
 pub struct ImmigrationManager {
     id: ImmigrationManagerID,
     simulation: SimulationID,
     development_manager: DevelopmentManagerID,
     state: ImmigrationManagerState,
+    requested: CVec<HouseholdTypeToSpawn>,
+    only_requested: bool,
+    demand: ImmigrationDemand,
+    // the last complete survey
+    last_demand: ImmigrationDemand,
 }
 
 impl ImmigrationManager {
@@ -80,8 +131,125 @@ impl ImmigrationManager {
             simulation,
             development_manager,
             state: ImmigrationManagerState::Idle,
+            requested: CVec::new(),
+            only_requested: false,
+            demand: ImmigrationDemand::default(),
+            last_demand: ImmigrationDemand::default(),
+        }
+    }
+
+    // answers arrive until the next survey starts
+    fn survey_demand(&mut self, world: &mut World) {
+        self.last_demand = ::std::mem::replace(&mut self.demand, ImmigrationDemand::default());
+        Market::global_first(world).report_unmet_demand_to(self.id, world);
+        Building::global_broadcast(world).report_demand_to(self.id, world);
+    }
+
+    pub fn on_unmet_demand(&mut self, unmet_searches: &CVec<(Resource, u32)>, _: &mut World) {
+        self.demand.unmet_searches = unmet_searches.clone();
+    }
+
+    pub fn on_household_employment(
+        &mut self,
+        unemployed: usize,
+        job_vacancies: usize,
+        _: &mut World,
+    ) {
+        self.demand.unemployed += unemployed;
+        self.demand.job_vacancies += job_vacancies;
+    }
+
+    pub fn on_vacant_dwellings(&mut self, vacant_dwellings: usize, _: &mut World) {
+        self.demand.vacant_dwellings += vacant_dwellings;
+    }
+
+    // Once households are requested explicitly, no random ones immigrate anymore
+    pub fn request_households(
+        &mut self,
+        household_types: &CVec<HouseholdTypeToSpawn>,
+        _: &mut World,
+    ) {
+        for household_type in household_types {
+            self.requested.push(*household_type);
+        }
+        self.only_requested = true;
+    }
+}
+
+// the resources that immigrating businesses of each type supply
+fn supplier_of(resource: Resource) -> Option<HouseholdTypeToSpawn> {
+    match resource {
+        Resource::Groceries => Some(HouseholdTypeToSpawn::GroceryShop),
+        Resource::Produce => Some(HouseholdTypeToSpawn::VegetableFarm),
+        Resource::Grain => Some(HouseholdTypeToSpawn::GrainFarm),
+        Resource::Flour => Some(HouseholdTypeToSpawn::Mill),
+        Resource::BakedGoods => Some(HouseholdTypeToSpawn::Bakery),
+        Resource::Meat | Resource::DairyGoods => Some(HouseholdTypeToSpawn::CowFarm),
+        _ => None,
+    }
+}
+
+const BUSINESS_TYPES: [HouseholdTypeToSpawn; 6] = [
+    HouseholdTypeToSpawn::GroceryShop,
+    HouseholdTypeToSpawn::CowFarm,
+    HouseholdTypeToSpawn::VegetableFarm,
+    HouseholdTypeToSpawn::GrainFarm,
+    HouseholdTypeToSpawn::Mill,
+    HouseholdTypeToSpawn::Bakery,
+];
+
+const FAMILY_BASE_SHARE: f32 = 1.0;
+const BUSINESS_BASE_SHARE: f32 = 0.01;
+const JOB_VACANCY_WEIGHT: f32 = 0.2;
+const VACANT_DWELLING_WEIGHT: f32 = 0.1;
+const UNEMPLOYMENT_WEIGHT: f32 = 0.2;
+const UNMET_SEARCH_WEIGHT: f32 = 0.05;
+
+/// What the city lacks, surveyed from the market, buildings and households
+#[derive(Compact, Clone, Default)]
+pub struct ImmigrationDemand {
+    unmet_searches: CVec<(Resource, u32)>,
+    unemployed: usize,
+    job_vacancies: usize,
+    vacant_dwellings: usize,
+}
+
+fn household_type_by_demand(demand: &ImmigrationDemand, instant: Instant) -> HouseholdTypeToSpawn {
+    // families come for jobs and homes, but not while many are looking for work
+    let family_share = FAMILY_BASE_SHARE
+        * (1.0
+            + JOB_VACANCY_WEIGHT * demand.job_vacancies as f32
+            + VACANT_DWELLING_WEIGHT * demand.vacant_dwellings as f32)
+        / (1.0 + UNEMPLOYMENT_WEIGHT * demand.unemployed as f32);
+
+    // businesses come for customers that found nothing and for available workers
+    let labor_factor = 1.0 + UNEMPLOYMENT_WEIGHT * demand.unemployed as f32;
+
+    let shares = Some((HouseholdTypeToSpawn::Family, family_share))
+        .into_iter()
+        .chain(BUSINESS_TYPES.iter().map(|&business| {
+            let unmet_searches: u32 = demand
+                .unmet_searches
+                .iter()
+                .filter(|&&(resource, _)| supplier_of(resource) == Some(business))
+                .map(|&(_, n)| n)
+                .sum();
+            let share = BUSINESS_BASE_SHARE + UNMET_SEARCH_WEIGHT * unmet_searches as f32;
+            (business, share * labor_factor)
+        }))
+        .collect::<Vec<_>>();
+
+    let total_share: f32 = shares.iter().map(|&(_, share)| share).sum();
+    let mut dot = seed(instant).gen_range(0.0, total_share);
+
+    for &(household_type, share) in &shares {
+        if dot < share {
+            return household_type;
         }
+        dot -= share;
     }
+
+    HouseholdTypeToSpawn::Family
 }
 
 #[derive(Copy, Clone)]
@@ -92,69 +260,45 @@ pub enum ImmigrationManagerState {
 
 impl Sleeper for ImmigrationManager {
     fn wake(&mut self, current_instant: Instant, world: &mut World) {
+        self.survey_demand(world);
+
         self.state = match self.state {
             ImmigrationManagerState::Idle => {
-                let family_share = 1.0;
-                let grocery_share = 0.02;
-                let cow_farm_share = 0.09;
-                let veg_farm_share = 0.026;
-                let grain_farm_share = 0.02; //0.0016;
-                let mill_share = 0.02; //0.001;
-                let bakery_share = 0.02; //0.01;
-
-                let total_share = family_share
-                    + grocery_share
-                    + cow_farm_share
-                    + veg_farm_share
-                    + grain_farm_share
-                    + mill_share
-                    + bakery_share;
-
-                let dot = seed(current_instant).gen_range(0.0, total_share);
-
-                let household_type_to_spawn = if dot < family_share {
-                    HouseholdTypeToSpawn::Family
-                } else if dot < family_share + grocery_share {
-                    HouseholdTypeToSpawn::GroceryShop
-                } else if dot < family_share + grocery_share + cow_farm_share {
-                    HouseholdTypeToSpawn::CowFarm
-                } else if dot < family_share + grocery_share + cow_farm_share + veg_farm_share {
-                    HouseholdTypeToSpawn::VegetableFarm
-                } else if dot < family_share
-                    + grocery_share
-                    + cow_farm_share
-                    + veg_farm_share
-                    + grain_farm_share
-                {
-                    HouseholdTypeToSpawn::GrainFarm
-                } else if dot < family_share
-                    + grocery_share
-                    + cow_farm_share
-                    + veg_farm_share
-                    + grain_farm_share
-                    + mill_share
-                {
-                    HouseholdTypeToSpawn::Mill
+                let maybe_household_type = if !self.requested.is_empty() {
+                    Some(self.requested.remove(0))
+                } else if self.only_requested {
+                    None
                 } else {
-                    HouseholdTypeToSpawn::Bakery
+                    Some(household_type_by_demand(&self.last_demand, current_instant))
                 };
 
-                println!("Trying to spawn {:?}", household_type_to_spawn);
+                if let Some(household_type_to_spawn) = maybe_household_type {
+                    println!("Trying to spawn {:?}", household_type_to_spawn);
 
-                let required_unit_type = unit_type_for(household_type_to_spawn);
+                    let required_unit_type = unit_type_for(household_type_to_spawn);
 
-                Building::global_broadcast(world).try_offer_unit(
-                    required_unit_type,
-                    self.id,
-                    world,
-                );
+                    Building::global_broadcast(world).try_offer_unit(
+                        required_unit_type,
+                        self.id,
+                        world,
+                    );
 
-                ImmigrationManagerState::FindingBuilding(household_type_to_spawn)
+                    ImmigrationManagerState::FindingBuilding(household_type_to_spawn)
+                } else {
+                    ImmigrationManagerState::Idle
+                }
             }
             ImmigrationManagerState::FindingBuilding(household_type_to_spawn) => {
                 // didn't find a building in time
-                self.development_manager
-                    .try_develop(building_style_for(household_type_to_spawn), world);
+                self.development_manager.try_develop(
+                    building_style_for(household_type_to_spawn, current_instant),
+                    world,
+                );
+
+                if self.only_requested {
+                    // try again once something was developed
+                    self.requested.insert(0, household_type_to_spawn);
+                }
 
                 ImmigrationManagerState::Idle
             }
@@ -172,33 +316,8 @@ impl ImmigrationManager {
             ImmigrationManagerState::FindingBuilding(household_type_to_spawn) => {
                 println!("Moving in");
 
-                let household_id = match household_type_to_spawn {
-                    HouseholdTypeToSpawn::Family => {
-                        FamilyID::move_into(3, building_id, self.simulation, world).into()
-                    }
-                    HouseholdTypeToSpawn::GroceryShop => {
-                        GroceryShopID::move_into(building_id, self.simulation, world).into()
-                    }
-                    HouseholdTypeToSpawn::GrainFarm => {
-                        GrainFarmID::move_into(building_id, self.simulation, world).into()
-                    }
-                    HouseholdTypeToSpawn::CowFarm => {
-                        CowFarmID::move_into(building_id, self.simulation, world).into()
-                    }
-                    HouseholdTypeToSpawn::VegetableFarm => {
-                        VegetableFarmID::move_into(building_id, self.simulation, world).into()
-                    }
-                    HouseholdTypeToSpawn::Mill => {
-                        MillID::move_into(building_id, self.simulation, world).into()
-                    }
-                    HouseholdTypeToSpawn::Bakery => {
-                        BakeryID::move_into(building_id, self.simulation, world).into()
-                    }
-                    HouseholdTypeToSpawn::NeighboringTownTrade => {
-                        NeighboringTownTradeID::move_into(building_id, self.simulation, world)
-                            .into()
-                    }
-                };
+                let household_id =
+                    spawn_household(household_type_to_spawn, building_id, self.simulation, world);
 
                 building_id.add_household(household_id, unit_idx, world);
 
@@ -209,12 +328,22 @@ impl ImmigrationManager {
     }
 }
 
+#[derive(Compact, Clone)]
+pub struct LotSuggestion {
+    building_intent: BuildingIntent,
+    based_on: StepID,
+    land_value: f32,
+}
+
 #[derive(Compact, Clone)]
 pub struct DevelopmentManager {
     id: DevelopmentManagerID,
     simulation: SimulationID,
     plan_manager: PlanManagerID,
     building_to_develop: COption<BuildingStyle>,
+    // the most valuable lot suggested so far for the building to develop
+    best_suggestion: COption<LotSuggestion>,
+    queued: CVec<BuildingStyle>,
 }
 
 impl DevelopmentManager {
@@ -229,6 +358,19 @@ impl DevelopmentManager {
             simulation,
             plan_manager,
             building_to_develop: COption(None),
+            best_suggestion: COption(None),
+            queued: CVec::new(),
+        }
+    }
+
+    pub fn queue_development(&mut self, building_styles: &CVec<BuildingStyle>, world: &mut World) {
+        for building_style in building_styles {
+            self.queued.push(*building_style);
+        }
+
+        if self.building_to_develop.is_none() && !self.queued.is_empty() {
+            let next = self.queued.remove(0);
+            self.try_develop(next, world);
         }
     }
 
@@ -246,31 +388,57 @@ impl DevelopmentManager {
         &mut self,
         building_intent: &BuildingIntent,
         based_on: StepID,
-        world: &mut World,
+        land_value: f32,
+        _: &mut World,
     ) {
         if let Some(building_to_develop) = *self.building_to_develop {
             if building_to_develop == building_intent.building_style {
-                println!("Adding to plan {:?}", building_intent.building_style);
-                self.plan_manager.implement_artificial_proposal(
-                    Proposal::from_plan(Plan::from_gestures(Some((
-                        GestureID::new(),
-                        Gesture::new(
-                            vec![building_intent.lot.center_point()].into(),
-                            GestureIntent::Building(building_intent.clone()),
-                        ),
-                    )))),
-                    based_on,
-                    world,
-                );
-                self.building_to_develop = COption(None);
+                let is_better = self
+                    .best_suggestion
+                    .as_ref()
+                    .map(|best| land_value > best.land_value)
+                    .unwrap_or(true);
+
+                if is_better {
+                    self.best_suggestion = COption(Some(LotSuggestion {
+                        building_intent: building_intent.clone(),
+                        based_on,
+                        land_value,
+                    }));
+                }
             }
         }
     }
 }
 
 impl Sleeper for DevelopmentManager {
-    fn wake(&mut self, _: Instant, _world: &mut World) {
+    fn wake(&mut self, _: Instant, world: &mut World) {
+        let best_suggestion = ::std::mem::replace(&mut self.best_suggestion, COption(None));
+        if let COption(Some(suggestion)) = best_suggestion {
+            let building_intent = suggestion.building_intent;
+            println!(
+                "Adding to plan {:?} with land value {}",
+                building_intent.building_style, suggestion.land_value
+            );
+            self.plan_manager.implement_artificial_proposal(
+                Proposal::from_plan(Plan::from_gestures(Some((
+                    GestureID::new(),
+                    Gesture::new(
+                        vec![building_intent.lot.center_point()].into(),
+                        GestureIntent::Building(building_intent),
+                    ),
+                )))),
+                suggestion.based_on,
+                world,
+            );
+        }
+
         self.building_to_develop = COption(None);
+
+        if !self.queued.is_empty() {
+            let next = self.queued.remove(0);
+            self.try_develop(next, world);
+        }
     }
 }
 
@@ -2,8 +2,10 @@ use kay::{ActorSystem, Fate, World, Actor};
 use compact::{CVec, CDict};
 use super::resources::{Inventory, Entry, Resource, ResourceAmount};
 use super::households::OfferID;
-use simulation::{TimeOfDayRange, Duration, Instant};
+use simulation::{TimeOfDayRange, Duration, Instant, Ticks};
 use transport::pathfinding::{RoughLocationID, LocationRequesterID};
+use statistics::StatisticsID;
+use economy::immigration_and_development::ImmigrationManagerID;
 
 #[derive(Compact, Clone)]
 pub struct Deal {
@@ -29,6 +31,28 @@ impl Deal {
             .next()
             .unwrap()
     }
+
+    // what the provider of the deal gives away
+    pub fn given_part(&self) -> Deal {
+        Deal::new(
+            self.delta
+                .iter()
+                .filter(|&&Entry(_, amount)| amount > 0.0)
+                .map(|&Entry(resource, amount)| (resource, amount)),
+            self.duration,
+        )
+    }
+
+    // what the provider of the deal gets in return
+    pub fn received_part(&self) -> Deal {
+        Deal::new(
+            self.delta
+                .iter()
+                .filter(|&&Entry(_, amount)| amount <= 0.0)
+                .map(|&Entry(resource, amount)| (resource, amount)),
+            self.duration,
+        )
+    }
 }
 
 pub trait EvaluationRequester {
@@ -36,10 +60,44 @@ pub trait EvaluationRequester {
     fn on_result(&mut self, result: &EvaluatedSearchResult, world: &mut World);
 }
 
+/// What happened on the market for one resource during one window
+#[derive(Copy, Clone, Default)]
+pub struct MarketActivity {
+    pub searches: u32,
+    // searches that didn't find a single offer
+    pub unmet_searches: u32,
+    pub reported_offers: u32,
+    pub total_utilization: f32,
+    pub total_unit_price: ResourceAmount,
+}
+
+impl MarketActivity {
+    pub fn mean_unit_price(&self) -> Option<ResourceAmount> {
+        if self.reported_offers > 0 {
+            Some(self.total_unit_price / self.reported_offers as f32)
+        } else {
+            None
+        }
+    }
+
+    pub fn searches_per_offer(&self) -> f32 {
+        self.searches as f32 / self.reported_offers.max(1) as f32
+    }
+}
+
+const ACTIVITY_WINDOW_HOURS: usize = 1;
+// Below this utilization, suppliers lower their prices
+const TARGET_UTILIZATION: f32 = 0.75;
+const EXPECTED_SEARCHES_PER_OFFER: f32 = 5.0;
+const SEARCH_PRESSURE_WEIGHT: f32 = 0.5;
+
 #[derive(Compact, Clone)]
 pub struct Market {
     id: MarketID,
     offers_by_resource: CDict<Resource, CVec<OfferID>>,
+    window_start: Instant,
+    activity: CDict<Resource, MarketActivity>,
+    last_activity: CDict<Resource, MarketActivity>,
 }
 
 impl Market {
@@ -47,9 +105,30 @@ impl Market {
         Market {
             id,
             offers_by_resource: CDict::new(),
+            window_start: Instant::new(0),
+            activity: CDict::new(),
+            last_activity: CDict::new(),
         }
     }
 
+    fn update_window(&mut self, instant: Instant) {
+        let window_length = Ticks::from(Duration::from_hours(ACTIVITY_WINDOW_HOURS)).0;
+
+        if instant.ticks() >= self.window_start.ticks() + window_length {
+            self.last_activity = ::std::mem::replace(&mut self.activity, CDict::new());
+            self.window_start = instant;
+        }
+    }
+
+    fn activity_mut(&mut self, resource: Resource) -> &mut MarketActivity {
+        if self.activity.get(resource).is_none() {
+            self.activity.insert(resource, MarketActivity::default());
+        }
+        self.activity
+            .get_mut(resource)
+            .expect("just made sure it exists")
+    }
+
     pub fn search(
         &mut self,
         instant: Instant,
@@ -58,6 +137,9 @@ impl Market {
         requester: EvaluationRequesterID,
         world: &mut World,
     ) {
+        self.update_window(instant);
+        self.activity_mut(resource).searches += 1;
+
         let n_to_expect = if let Some(offers) = self.offers_by_resource.get(resource) {
             for offer in offers.iter() {
                 offer
@@ -70,6 +152,10 @@ impl Market {
             0
         };
 
+        if n_to_expect == 0 {
+            self.activity_mut(resource).unmet_searches += 1;
+        }
+
         requester.expect_n_results(resource, n_to_expect as u32, world);
     }
 
@@ -83,6 +169,70 @@ impl Market {
         }
         offer.household.withdrawal_confirmed(offer.idx, world);
     }
+
+    // Suppliers regularly report their offers and get told how to adjust
+    // their price, based on their own utilization and the overall demand
+    pub fn report_offer(
+        &mut self,
+        offer: OfferID,
+        resource: Resource,
+        utilization: f32,
+        unit_price: ResourceAmount,
+        instant: Instant,
+        world: &mut World,
+    ) {
+        self.update_window(instant);
+
+        {
+            let activity = self.activity_mut(resource);
+            activity.reported_offers += 1;
+            activity.total_utilization += utilization;
+            activity.total_unit_price += unit_price;
+        }
+
+        let search_pressure = self
+            .last_activity
+            .get(resource)
+            .map(|last| {
+                (last.searches_per_offer() / EXPECTED_SEARCHES_PER_OFFER)
+                    .ln()
+                    .max(-1.0)
+                    .min(1.0)
+            })
+            .unwrap_or(0.0);
+
+        let pressure =
+            (utilization - TARGET_UTILIZATION) + SEARCH_PRESSURE_WEIGHT * search_pressure;
+
+        offer
+            .household
+            .adjust_offer_price(offer.idx, pressure, world);
+    }
+
+    pub fn report_unmet_demand_to(
+        &mut self,
+        immigration_manager: ImmigrationManagerID,
+        world: &mut World,
+    ) {
+        let unmet_demand: CVec<(Resource, u32)> = self
+            .last_activity
+            .pairs()
+            .filter(|&(_, activity)| activity.unmet_searches > 0)
+            .map(|(resource, activity)| (*resource, activity.unmet_searches))
+            .collect();
+        immigration_manager.on_unmet_demand(unmet_demand, world);
+    }
+
+    pub fn report_prices_to(&mut self, statistics: StatisticsID, world: &mut World) {
+        let prices: CVec<(Resource, ResourceAmount)> = self
+            .last_activity
+            .pairs()
+            .filter_map(|(resource, activity)| {
+                activity.mean_unit_price().map(|price| (*resource, price))
+            })
+            .collect();
+        statistics.on_market_prices(prices, world);
+    }
 }
 
 #[derive(Compact, Clone)]
@@ -90,6 +240,8 @@ pub struct EvaluatedDeal {
     pub offer: OfferID,
     pub deal: Deal,
     pub opening_hours: TimeOfDayRange,
+    // along the route from the member's current location to the offer
+    pub travel_distance: f32,
 }
 
 #[derive(Compact, Clone)]
@@ -183,22 +335,20 @@ impl LocationRequester for TripCostEstimator {
 }
 
 impl DistanceRequester for TripCostEstimator {
-    fn on_distance(&mut self, maybe_distance: Option<f32>, world: &mut World) {
-        const ASSUMED_AVG_SPEED: f32 = 10.0; // m/s
-
-        let result = if let Some(distance) = maybe_distance {
+    fn on_distance(&mut self, maybe_distance_and_time: Option<(f32, f32)>, world: &mut World) {
+        let result = if let Some((distance, travel_time)) = maybe_distance_and_time {
             EvaluatedSearchResult {
                 evaluated_deals: self
                     .base_result
                     .evaluated_deals
                     .iter()
                     .map(|evaluated_deal| {
-                        let estimated_travel_time =
-                            Duration((distance / ASSUMED_AVG_SPEED) as usize);
+                        let estimated_travel_time = Duration::from_seconds(travel_time as usize);
                         let mut new_deal = evaluated_deal.clone();
                         new_deal.deal.duration += estimated_travel_time;
                         new_deal.opening_hours =
                             new_deal.opening_hours.earlier_by(estimated_travel_time);
+                        new_deal.travel_distance = distance;
                         // TODO: adjust resources to incorporate travel costs
                         new_deal
                     })
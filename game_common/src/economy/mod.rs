@@ -6,11 +6,13 @@ use planning::PlanManagerID;
 pub mod resources;
 pub mod market;
 pub mod households;
+pub mod shipments;
 pub mod immigration_and_development;
 
 pub fn setup(system: &mut ActorSystem) {
     market::setup(system);
     households::setup(system);
+    shipments::setup(system);
     immigration_and_development::setup(system);
 }
 
@@ -1,4 +1,4 @@
-#[derive(Copy, Clone, Debug, Hash, PartialOrd, Ord, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, Hash, PartialOrd, Ord, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Resource {
     Awakeness,
     Satiety,
@@ -49,6 +49,14 @@ impl Resource {
              * Devices => "Devices", */
         }
     }
+
+    // moved between businesses as shipments, in delivery trucks
+    pub fn is_freight(&self) -> bool {
+        match *self {
+            Grain | Flour | BakedGoods => true,
+            _ => false,
+        }
+    }
 }
 
 use compact::{CVec, Compact};
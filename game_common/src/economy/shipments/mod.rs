@@ -0,0 +1,116 @@
+use kay::{ActorSystem, World, Fate, Actor};
+use simulation::{Simulation, Sleeper, Instant, Duration, Ticks};
+use transport::pathfinding::RoughLocationID;
+use transport::pathfinding::trip::{TripListener, TripID, TripResult, TripFate, TripMode};
+use transport::parking::ParkingSpot;
+
+use super::market::Deal;
+use super::households::{HouseholdID, MemberIdx};
+
+const MAX_SHIPMENT_ATTEMPTS: u8 = 3;
+const SHIPMENT_RETRY_DELAY_MINUTES: usize = 30;
+
+// Bulk goods of a deal on their way from the supplier to the requester in a delivery
+// truck. The supplier loads the goods when the shipment is requested, everything else
+// about the deal only happens once the truck arrived. Goods that never arrive are lost
+#[derive(Compact, Clone)]
+pub struct Shipment {
+    id: ShipmentID,
+    supplier: HouseholdID,
+    supplier_member: MemberIdx,
+    requester: HouseholdID,
+    requester_member: MemberIdx,
+    deal: Deal,
+    attempts: u8,
+}
+
+impl Shipment {
+    #[cfg_attr(feature = "cargo-clippy", allow(too_many_arguments))]
+    pub fn spawn(
+        id: ShipmentID,
+        supplier: HouseholdID,
+        supplier_member: MemberIdx,
+        requester: HouseholdID,
+        requester_member: MemberIdx,
+        deal: &Deal,
+        instant: Instant,
+        world: &mut World,
+    ) -> Self {
+        let shipment = Shipment {
+            id,
+            supplier,
+            supplier_member,
+            requester,
+            requester_member,
+            deal: deal.clone(),
+            attempts: 1,
+        };
+        shipment.dispatch(instant, world);
+        shipment
+    }
+
+    fn dispatch(&self, instant: Instant, world: &mut World) {
+        TripID::spawn(
+            self.supplier.into(),
+            self.requester.into(),
+            Some(self.id_as()),
+            TripMode::Freight,
+            instant,
+            world,
+        );
+    }
+
+    pub fn done(&mut self, _: &mut World) -> Fate {
+        Fate::Die
+    }
+}
+
+impl Sleeper for Shipment {
+    fn wake(&mut self, current_instant: Instant, world: &mut World) {
+        self.attempts += 1;
+        self.dispatch(current_instant, world);
+    }
+}
+
+impl TripListener for Shipment {
+    fn trip_created(&mut self, _trip: TripID, _: &mut World) {}
+
+    fn trip_result(
+        &mut self,
+        _trip: TripID,
+        result: TripResult,
+        _rough_source: RoughLocationID,
+        _rough_destination: RoughLocationID,
+        world: &mut World,
+    ) {
+        if let TripFate::Success(_) = result.fate {
+            self.supplier
+                .provide_deal(self.deal.received_part(), self.supplier_member, world);
+            self.requester
+                .receive_deal(self.deal.clone(), self.requester_member, world);
+            self.id.done(world);
+        } else if self.attempts < MAX_SHIPMENT_ATTEMPTS {
+            Simulation::local_first(world).wake_up_in(
+                Ticks::from(Duration::from_minutes(SHIPMENT_RETRY_DELAY_MINUTES)),
+                self.id_as(),
+                world,
+            );
+        } else {
+            // nobody pays for the goods
+            self.id.done(world);
+        }
+    }
+
+    // delivery trucks leave again right away
+    fn trip_parked(&mut self, _trip: TripID, spot: ParkingSpot, world: &mut World) {
+        spot.release(world);
+    }
+}
+
+pub fn setup(system: &mut ActorSystem) {
+    system.register::<Shipment>();
+    auto_setup(system);
+}
+
+mod kay_auto;
+pub use self::kay_auto::*;
@@ -0,0 +1,206 @@
+use kay::{ActorSystem, World, Actor};
+use compact::CVec;
+use stagemaster::{UserInterfaceID, Interactable2d, Interactable2dID};
+#[cfg(feature = "server")]
+use imgui::ImGuiSetCond_FirstUseEver;
+
+use simulation::{Simulatable, SimulatableID, Instant, TICKS_PER_SIM_MINUTE};
+use planning::{PlanManagerID, ProposalID, PlanResult, Prototype, PrototypeKind};
+use construction::Action;
+use transport::transport_planning::{RoadPrototype, LanePrototype};
+use land_use::buildings::Building;
+use economy::resources::ResourceAmount;
+use persistence::PersistenceID;
+
+pub const INITIAL_BALANCE: ResourceAmount = 1_500_000.0;
+pub const ROAD_COST_PER_LANE_METER: ResourceAmount = 5.0;
+pub const INTERSECTION_COST: ResourceAmount = 2_000.0;
+pub const TRANSIT_STOP_COST: ResourceAmount = 5_000.0;
+pub const PROPERTY_TAX_PER_DAY: ResourceAmount = 5.0;
+pub const INCOME_TAX_RATE: ResourceAmount = 0.1;
+const COLLECT_TAXES_EVERY_N_TICKS: usize = 24 * 60 * TICKS_PER_SIM_MINUTE;
+
+fn prototype_cost(prototype: &Prototype) -> ResourceAmount {
+    match prototype.kind {
+        PrototypeKind::Road(RoadPrototype::Lane(LanePrototype(ref path, ..))) => {
+            ROAD_COST_PER_LANE_METER * path.length()
+        }
+        PrototypeKind::Road(RoadPrototype::Intersection(_)) => INTERSECTION_COST,
+        PrototypeKind::TransitLine(ref line) => TRANSIT_STOP_COST * line.stops.len() as f32,
+        _ => 0.0,
+    }
+}
+
+// Morphing only costs what is added on top of the existing prototype,
+// destruction and lots (developed by households themselves) are free
+pub fn construction_cost(existing: &PlanResult, actions: &CVec<CVec<Action>>) -> ResourceAmount {
+    actions
+        .iter()
+        .flat_map(|action_group| action_group.iter())
+        .map(|action| match *action {
+            Action::Construct(_, ref prototype) => prototype_cost(prototype),
+            Action::Morph(old_prototype_id, _, ref new_prototype) => {
+                let old_cost = existing
+                    .prototypes
+                    .get(old_prototype_id)
+                    .map(prototype_cost)
+                    .unwrap_or(0.0);
+                (prototype_cost(new_prototype) - old_cost).max(0.0)
+            }
+            Action::Destruct(_) => 0.0,
+        })
+        .sum()
+}
+
+#[derive(Compact, Clone)]
+pub struct Treasury {
+    id: TreasuryID,
+    balance: ResourceAmount,
+    taxes_since_collection: ResourceAmount,
+    last_taxes: ResourceAmount,
+    construction_spending: ResourceAmount,
+    // plans that couldn't be afforded yet, implemented in order once there's money
+    deferred_construction: CVec<(PlanManagerID, ProposalID, ResourceAmount)>,
+}
+
+impl Treasury {
+    pub fn spawn(id: TreasuryID, _: &mut World) -> Treasury {
+        Treasury {
+            id,
+            balance: INITIAL_BALANCE,
+            taxes_since_collection: 0.0,
+            last_taxes: 0.0,
+            construction_spending: 0.0,
+            deferred_construction: CVec::new(),
+        }
+    }
+
+    pub fn request_construction_funds(
+        &mut self,
+        requester: PlanManagerID,
+        proposal_id: ProposalID,
+        cost: ResourceAmount,
+        world: &mut World,
+    ) {
+        let already_deferred = self
+            .deferred_construction
+            .iter()
+            .any(|&(_, deferred_id, _)| deferred_id == proposal_id);
+
+        if already_deferred {
+            return;
+        }
+
+        if self.deferred_construction.is_empty() && cost <= self.balance {
+            self.charge(cost, world);
+            requester.implement_funded(proposal_id, cost, world);
+        } else {
+            println!(
+                "Not enough funds to implement proposal yet: costs {:.0}, balance {:.0}",
+                cost, self.balance
+            );
+            self.deferred_construction
+                .push((requester, proposal_id, cost));
+        }
+    }
+
+    fn fund_deferred_construction(&mut self, world: &mut World) {
+        while let Some(&(requester, proposal_id, cost)) = self.deferred_construction.first() {
+            if cost > self.balance {
+                break;
+            }
+            self.deferred_construction.remove(0);
+            self.charge(cost, world);
+            requester.implement_funded(proposal_id, cost, world);
+        }
+    }
+
+    // for construction that has to happen regardless of funds,
+    // which might leave the treasury in debt
+    pub fn charge(&mut self, cost: ResourceAmount, _: &mut World) {
+        self.balance -= cost;
+        self.construction_spending += cost;
+    }
+
+    pub fn refund(&mut self, cost: ResourceAmount, world: &mut World) {
+        self.balance += cost;
+        self.construction_spending -= cost;
+        self.fund_deferred_construction(world);
+    }
+
+    pub fn receive_taxes(&mut self, amount: ResourceAmount, world: &mut World) {
+        self.balance += amount;
+        self.taxes_since_collection += amount;
+        self.fund_deferred_construction(world);
+    }
+
+    pub fn report_balance_to(&mut self, requester: PersistenceID, world: &mut World) {
+        requester.on_treasury_balance(self.balance, world);
+    }
+
+    pub fn restore_balance(&mut self, balance: ResourceAmount, world: &mut World) {
+        self.balance = balance;
+        self.fund_deferred_construction(world);
+    }
+}
+
+impl Simulatable for Treasury {
+    fn tick(&mut self, _dt: f32, current_instant: Instant, world: &mut World) {
+        if current_instant.ticks() > 0 && current_instant.ticks() % COLLECT_TAXES_EVERY_N_TICKS == 0
+        {
+            // taxes of the previous collection have all arrived by now
+            self.last_taxes = self.taxes_since_collection;
+            self.taxes_since_collection = 0.0;
+            Building::global_broadcast(world).collect_taxes(self.id, world);
+        }
+    }
+}
+
+impl Building {
+    pub fn collect_taxes(&mut self, treasury: TreasuryID, world: &mut World) {
+        for household in &self.all_households() {
+            household.pay_taxes(treasury, world);
+        }
+    }
+}
+
+impl Interactable2d for Treasury {
+    #[cfg(feature = "server")]
+    fn draw(&mut self, _world: &mut World, ui: &::imgui::Ui<'static>) {
+        ui.window(im_str!("Treasury"))
+            .size((250.0, 100.0), ImGuiSetCond_FirstUseEver)
+            .collapsible(true)
+            .build(|| {
+                ui.text(im_str!("Balance: ${:.0}", self.balance));
+                ui.text(im_str!("Taxes last day: ${:.0}", self.last_taxes));
+                ui.text(im_str!(
+                    "Spent on construction: ${:.0}",
+                    self.construction_spending
+                ));
+                for &(_, _, cost) in &self.deferred_construction {
+                    ui.text(im_str!("Waiting for funds: ${:.0}", cost));
+                }
+            });
+    }
+
+    #[cfg(not(feature = "server"))]
+    fn draw(&mut self, world: &mut World, ui: &()) {}
+}
+
+impl Treasury {
+    pub fn add_to_ui(&mut self, user_interface: UserInterfaceID, world: &mut World) {
+        user_interface.add_2d(self.id_as(), world);
+    }
+}
+
+pub fn setup(system: &mut ActorSystem) {
+    system.register::<Treasury>();
+    auto_setup(system);
+}
+
+pub fn spawn(world: &mut World) -> TreasuryID {
+    TreasuryID::spawn(world)
+}
+
+mod kay_auto;
+pub use self::kay_auto::*;
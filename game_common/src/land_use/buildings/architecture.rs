@@ -8,6 +8,8 @@ pub fn ideal_lot_shape(building_style: BuildingStyle) -> (f32, f32) {
     match building_style {
         BuildingStyle::FamilyHouse => (15.0, 30.0),
         BuildingStyle::GroceryShop => (10.0, 30.0),
+        BuildingStyle::ApartmentBlock => (25.0, 35.0),
+        BuildingStyle::ShopWithFlats => (15.0, 30.0),
         BuildingStyle::Bakery => (15.0, 30.0),
         BuildingStyle::Mill => (15.0, 30.0),
         BuildingStyle::Field => (50.0, 100.0),
@@ -15,6 +17,43 @@ pub fn ideal_lot_shape(building_style: BuildingStyle) -> (f32, f32) {
     }
 }
 
+// lots with a lower height limit can't be used for this style
+pub fn min_height(building_style: BuildingStyle) -> N {
+    match building_style {
+        BuildingStyle::FamilyHouse => 3.0,
+        BuildingStyle::GroceryShop => 3.0,
+        BuildingStyle::ApartmentBlock => 2.0 * STORY_HEIGHT,
+        BuildingStyle::ShopWithFlats => 2.0 * STORY_HEIGHT,
+        BuildingStyle::Bakery => 3.0,
+        BuildingStyle::Mill => 5.0,
+        BuildingStyle::Field => 0.0,
+        BuildingStyle::NeighboringTownConnection => 0.0,
+    }
+}
+
+pub const STORY_HEIGHT: N = 3.0;
+// used for multi-story buildings on lots without a height limit
+const DEFAULT_STORIES: usize = 4;
+const MAX_STORIES: usize = 10;
+
+pub fn stories(lot: &Lot) -> usize {
+    let stories = if lot.max_height > 0 {
+        (f32::from(lot.max_height) / STORY_HEIGHT) as usize
+    } else {
+        DEFAULT_STORIES
+    };
+    stories.max(1).min(MAX_STORIES)
+}
+
+// the height limit applies to walls, roofs may still rise above it
+fn within_height_limit(lot: &Lot, height: N) -> N {
+    if lot.max_height > 0 {
+        height.min(f32::from(lot.max_height))
+    } else {
+        height
+    }
+}
+
 #[derive(Compact, Clone)]
 pub struct BuildingMesh {
     pub wall: Mesh,
@@ -35,8 +74,8 @@ pub fn build_building<R: Rng>(
 
     match building_type {
         BuildingStyle::FamilyHouse => {
-            let height = 3.0 + 3.0 * rng.next_f32();
-            let entrance_height = 2.0 + rng.next_f32();
+            let height = within_height_limit(lot, 3.0 + 3.0 * rng.next_f32());
+            let entrance_height = within_height_limit(lot, 2.0 + rng.next_f32());
 
             let (roof_brick_mesh, roof_wall_mesh) =
                 main_footprint.open_gable_roof_mesh(height, 0.3);
@@ -54,7 +93,7 @@ pub fn build_building<R: Rng>(
             }
         }
         BuildingStyle::GroceryShop => {
-            let height = 3.0 + rng.next_f32();
+            let height = within_height_limit(lot, 3.0 + rng.next_f32());
             let entrance_height = height - 0.7;
 
             BuildingMesh {
@@ -66,6 +105,37 @@ pub fn build_building<R: Rng>(
                 field: Mesh::empty(),
             }
         }
+        BuildingStyle::ApartmentBlock => {
+            let block_footprint = main_footprint.scale(1.4);
+            let height = within_height_limit(lot, stories(lot) as N * STORY_HEIGHT);
+            let entrance_height = within_height_limit(lot, STORY_HEIGHT);
+
+            BuildingMesh {
+                wall: block_footprint.wall_mesh(height)
+                    + entrance_footprint.wall_mesh(entrance_height),
+                brick_roof: Mesh::empty(),
+                flat_roof: block_footprint.flat_roof_mesh(height)
+                    + entrance_footprint.flat_roof_mesh(entrance_height),
+                field: Mesh::empty(),
+            }
+        }
+        BuildingStyle::ShopWithFlats => {
+            let height = within_height_limit(lot, stories(lot) as N * STORY_HEIGHT);
+            // the shop front sticks out from the ground floor
+            let shop_height = within_height_limit(lot, STORY_HEIGHT + 0.5 * rng.next_f32());
+
+            let (roof_brick_mesh, roof_wall_mesh) =
+                main_footprint.open_gable_roof_mesh(height, 0.3);
+
+            BuildingMesh {
+                wall: main_footprint.wall_mesh(height)
+                    + entrance_footprint.wall_mesh(shop_height)
+                    + roof_wall_mesh,
+                brick_roof: roof_brick_mesh,
+                flat_roof: entrance_footprint.flat_roof_mesh(shop_height),
+                field: Mesh::empty(),
+            }
+        }
         BuildingStyle::Field => BuildingMesh {
             wall: Mesh::empty(),
             brick_roof: Mesh::empty(),
@@ -73,8 +143,8 @@ pub fn build_building<R: Rng>(
             field: Mesh::from_area(&lot.area),
         },
         BuildingStyle::Mill => {
-            let height = 3.0 + rng.next_f32();
-            let tower_height = 5.0 + rng.next_f32();
+            let height = within_height_limit(lot, 3.0 + rng.next_f32());
+            let tower_height = within_height_limit(lot, 5.0 + rng.next_f32());
 
             let (roof_brick_mesh, roof_wall_mesh) =
                 main_footprint.open_gable_roof_mesh(height, 0.3);
@@ -92,7 +162,7 @@ pub fn build_building<R: Rng>(
             }
         }
         BuildingStyle::Bakery => {
-            let height = 3.0 + rng.next_f32();
+            let height = within_height_limit(lot, 3.0 + rng.next_f32());
             let entrance_height = height;
 
             let (entrance_roof_brick_mesh, entrance_roof_wall_mesh) =
@@ -252,13 +322,23 @@ impl Footprint {
 }
 
 pub fn generate_house_footprint<R: Rng>(lot: &Lot, rng: &mut R) -> (Footprint, Footprint) {
-    let building_position = lot.center_point();
-    let building_orientation = lot.connection_points[0].1;
+    let (connection_point, building_orientation) = lot.connection_points[0];
     let building_orientation_orth = building_orientation.orthogonal();
 
     let footprint_width = 10.0 + rng.next_f32() * 7.0;
     let footprint_depth = 7.0 + rng.next_f32() * 5.0;
 
+    // moves the building back from the road if it would be closer than the set back
+    let center = lot.center_point();
+    let away_from_road = if (center - connection_point).dot(&building_orientation_orth) > 0.0 {
+        building_orientation_orth
+    } else {
+        -building_orientation_orth
+    };
+    let distance_to_road = (center - connection_point).dot(&away_from_road) - footprint_depth / 2.0;
+    let building_position =
+        center + (f32::from(lot.set_back) - distance_to_road).max(0.0) * away_from_road;
+
     let entrance_position = building_position
         + building_orientation * (0.5 - 1.0 * rng.next_f32()) * footprint_width
         - building_orientation_orth * (rng.next_f32() * 0.3 + 0.1) * footprint_depth;
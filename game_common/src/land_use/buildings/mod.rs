@@ -1,9 +1,10 @@
 use kay::{ActorSystem, World, Actor, Fate};
 use compact::{CVec, COption};
-use descartes::P2;
+use descartes::{P2, PointContainer};
 use stagemaster::UserInterfaceID;
 
 use transport::lane::{Lane, LaneID};
+use transport::pedestrians::Sidewalk;
 use simulation::Ticks;
 use construction::{ConstructionID, Constructable, ConstructableID};
 use planning::{Prototype, PrototypeKind};
@@ -11,9 +12,11 @@ use planning::{Prototype, PrototypeKind};
 pub mod rendering;
 pub mod architecture;
 
-use economy::households::HouseholdID;
+use economy::households::{HouseholdID, HouseholdSnapshot};
 use transport::pathfinding::PreciseLocation;
-use economy::immigration_and_development::ImmigrationManagerID;
+use transport::parking::{ParkingCapacity, ParkingSpot, lot_parking_for_units};
+use economy::immigration_and_development::{ImmigrationManagerID, unit_type_for, spawn_household};
+use persistence::PersistenceID;
 use land_use::zone_planning::Lot;
 
 #[derive(Copy, Clone)]
@@ -36,6 +39,10 @@ pub struct UnitIdx(usize);
 pub enum BuildingStyle {
     FamilyHouse,
     GroceryShop,
+    // dwellings on every story
+    ApartmentBlock,
+    // a shop on the ground floor and dwellings above
+    ShopWithFlats,
     Field,
     Mill,
     Bakery,
@@ -48,6 +55,8 @@ pub struct Building {
     units: CVec<Unit>,
     lot: Lot,
     pub location: Option<PreciseLocation>,
+    pub sidewalk_location: Option<PreciseLocation>,
+    pub parking: ParkingCapacity,
     style: BuildingStyle,
     being_destroyed_for: COption<ConstructionID>,
     started_reconnect: bool,
@@ -67,11 +76,16 @@ impl Building {
             world,
         );
 
+        let units = units_for_style(style, lot);
+        let parking = lot_parking_for_units(units.iter().map(|&Unit(_, unit_type)| unit_type));
+
         Building {
             id,
-            units: units_for_style(style),
+            units,
             lot: lot.clone(),
             location: None,
+            sidewalk_location: None,
+            parking,
             style,
             being_destroyed_for: COption(None),
             started_reconnect: false,
@@ -107,12 +121,14 @@ impl Building {
     }
 
     pub fn remove_household(&mut self, household: HouseholdID, world: &mut World) {
-        let position = self
+        // households evicted by restoring a snapshot already left their unit
+        if let Some(position) = self
             .units
             .iter()
             .position(|&Unit(user, _)| user == Some(household))
-            .expect("Tried to remove a household not in the building");
-        self.units[position].0 = None;
+        {
+            self.units[position].0 = None;
+        }
 
         if self.being_destroyed_for.is_some() && self.all_households().is_empty() {
             self.id.finally_destroy(world);
@@ -126,11 +142,87 @@ impl Building {
             .collect()
     }
 
+    pub fn report_demand_to(
+        &mut self,
+        immigration_manager: ImmigrationManagerID,
+        world: &mut World,
+    ) {
+        if self.being_destroyed_for.is_some() {
+            return;
+        }
+
+        let vacant_dwellings = self
+            .units
+            .iter()
+            .filter(|&&Unit(household, unit_type)| {
+                household.is_none() && unit_type == UnitType::Dwelling
+            })
+            .count();
+        immigration_manager.on_vacant_dwellings(vacant_dwellings, world);
+
+        for household in &self.all_households() {
+            household.report_employment_to(immigration_manager, world);
+        }
+    }
+
+    pub fn save_households(&mut self, requester: PersistenceID, world: &mut World) {
+        for household in &self.all_households() {
+            household.save_snapshot(self.lot.center_point(), requester, world);
+        }
+    }
+
+    pub fn restore_households(
+        &mut self,
+        snapshots: &CVec<HouseholdSnapshot>,
+        requester: PersistenceID,
+        world: &mut World,
+    ) {
+        if self.being_destroyed_for.is_some() {
+            return;
+        }
+
+        // whoever lived here before loading doesn't belong to the restored city
+        for unit in self.units.iter_mut() {
+            if let Some(household) = unit.0 {
+                household.destroy(world);
+                unit.0 = None;
+            }
+        }
+
+        // the lot is rebuilt from the same master result, so its center is where it was saved
+        let home = self.lot.center_point();
+
+        for (snapshot_idx, snapshot) in snapshots.iter().enumerate() {
+            if (snapshot.home - home).norm() > RESTORED_HOME_TOLERANCE {
+                continue;
+            }
+
+            let required_unit_type = unit_type_for(snapshot.household_type);
+
+            if let Some(idx) = self.units.iter().position(|&Unit(household, unit_type)| {
+                household.is_none() && unit_type == required_unit_type
+            }) {
+                let household = spawn_household(
+                    snapshot.household_type,
+                    self.id,
+                    Simulation::local_first(world),
+                    world,
+                );
+                household.restore_snapshot(snapshot.clone(), world);
+                self.units[idx].0 = Some(household);
+                requester.on_household_restored(snapshot_idx as u32, world);
+            }
+        }
+    }
+
     pub fn finally_destroy(&mut self, world: &mut World) -> Fate {
         rendering::on_destroy(self.id, world);
         if let Some(location) = self.location {
             location.node.remove_attachee(self.id_as(), world);
         }
+        if let Some(sidewalk_location) = self.sidewalk_location {
+            sidewalk_location.node.remove_attachee(self.id_as(), world);
+        }
         self.being_destroyed_for
             .unwrap()
             .action_done(self.id.into(), world);
@@ -171,11 +263,29 @@ use simulation::{Simulation, Sleeper, SleeperID, Duration};
 impl Attachee for Building {
     fn location_changed(
         &mut self,
-        _old: Option<Location>,
+        old: Option<Location>,
         maybe_new: Option<Location>,
         world: &mut World,
     ) {
-        if let Some(new) = maybe_new {
+        let sidewalk_changed = old.is_some()
+            && self.sidewalk_location.map(|sidewalk| sidewalk.location) == old
+            && self.location.map(|lane| lane.location) != old;
+
+        if sidewalk_changed {
+            if let Some(new) = maybe_new {
+                self.sidewalk_location
+                    .as_mut()
+                    .expect("Only an existing location can change")
+                    .location = new;
+            } else {
+                self.sidewalk_location = None;
+                Simulation::local_first(world).wake_up_in(
+                    Ticks::from(Duration::from_minutes(10)),
+                    self.id_as(),
+                    world,
+                );
+            }
+        } else if let Some(new) = maybe_new {
             self.location
                 .as_mut()
                 .expect("Only an existing location can change")
@@ -200,11 +310,20 @@ impl Sleeper for Building {
                 self.started_reconnect = false;
             }
         } else {
-            Lane::global_broadcast(world).try_reconnect_building(
-                self.id,
-                self.lot.center_point(),
-                world,
-            );
+            if self.location.is_none() {
+                Lane::global_broadcast(world).try_reconnect_building(
+                    self.id,
+                    self.lot.center_point(),
+                    world,
+                );
+            }
+            if self.sidewalk_location.is_none() {
+                Sidewalk::global_broadcast(world).try_reconnect_building_sidewalk(
+                    self.id,
+                    self.lot.center_point(),
+                    world,
+                );
+            }
             Simulation::local_first(world).wake_up_in(
                 Ticks::from(Duration::from_minutes(10)),
                 self.id_as(),
@@ -227,8 +346,29 @@ impl Building {
             new_location.node.add_attachee(self.id_as(), world);
         }
     }
+
+    pub fn reconnect_sidewalk(&mut self, new_location: PreciseLocation, world: &mut World) {
+        if self.sidewalk_location.is_none() {
+            self.sidewalk_location = Some(new_location);
+            new_location.node.add_attachee(self.id_as(), world);
+        }
+    }
+
+    pub fn report_as_neighbor(
+        &mut self,
+        estimator: LandValueEstimatorID,
+        lot_position: P2,
+        world: &mut World,
+    ) {
+        let distance = (self.lot.center_point() - lot_position).norm();
+        if distance < ACCESSIBILITY_RADIUS {
+            estimator.on_neighbor(self.style, self.location, distance, world);
+        }
+    }
 }
 
+use land_use::land_value::{LandValueEstimatorID, ACCESSIBILITY_RADIUS};
+
 use transport::pathfinding::{RoughLocation, RoughLocationID, RoughLocationResolve};
 use simulation::Instant;
 
@@ -236,14 +376,52 @@ impl RoughLocation for Building {
     fn resolve(&self) -> RoughLocationResolve {
         RoughLocationResolve::Done(self.location, self.lot.center_point())
     }
+
+    fn walking_location(&self) -> Option<PreciseLocation> {
+        self.sidewalk_location
+    }
+
+    fn try_reserve_parking(&mut self) -> Option<ParkingSpot> {
+        if self.parking.try_occupy() {
+            Some(ParkingSpot::Lot(self.id))
+        } else {
+            None
+        }
+    }
 }
 
 const FAMILIES_PER_NEIGHBORING_TOWN: usize = 5;
 
-pub fn units_for_style(style: BuildingStyle) -> CVec<Unit> {
+// each dwelling of a multi-unit building takes up this much of the lot's width on every story
+const DWELLING_WIDTH: f32 = 8.0;
+
+fn dwellings_per_story(lot: &Lot) -> usize {
+    let width = lot
+        .width_depth_per_connection_point()
+        .first()
+        .map(|&(_, _, width, _)| width)
+        .unwrap_or(0.0);
+    ((width / DWELLING_WIDTH) as usize).max(1)
+}
+
+pub fn units_for_style(style: BuildingStyle, lot: &Lot) -> CVec<Unit> {
     match style {
         BuildingStyle::FamilyHouse => vec![Unit(None, UnitType::Dwelling)],
         BuildingStyle::GroceryShop => vec![Unit(None, UnitType::Retail)],
+        BuildingStyle::ApartmentBlock => vec![
+            Unit(None, UnitType::Dwelling);
+            architecture::stories(lot) * dwellings_per_story(lot)
+        ],
+        BuildingStyle::ShopWithFlats => {
+            let upper_stories = architecture::stories(lot) - 1;
+            Some(Unit(None, UnitType::Retail))
+                .into_iter()
+                .chain(vec![
+                    Unit(None, UnitType::Dwelling);
+                    upper_stories * dwellings_per_story(lot)
+                ])
+                .collect()
+        }
         BuildingStyle::Bakery => vec![Unit(None, UnitType::Bakery)],
         BuildingStyle::Mill => vec![Unit(None, UnitType::Mill)],
         BuildingStyle::Field => vec![Unit(None, UnitType::Agriculture)],
@@ -259,6 +437,9 @@ pub fn units_for_style(style: BuildingStyle) -> CVec<Unit> {
     }.into()
 }
 
+// restored households move back into the building whose lot center is this close to their home
+const RESTORED_HOME_TOLERANCE: f32 = 1.0;
+
 pub const MIN_ROAD_LENGTH_TO_TOWN: f32 = 4000.0;
 const MIN_NEIGHBORING_TOWN_DISTANCE: f32 = 2000.0;
 
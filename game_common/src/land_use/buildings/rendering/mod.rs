@@ -8,6 +8,7 @@ use imgui::ImGuiSetCond_FirstUseEver;
 
 use super::{Building, Lot, BuildingID, BuildingPlanResultDelta, BuildingStyle};
 use economy::households::HouseholdID;
+use transport::parking::ParkingCapacity;
 use style::colors;
 use render_layers::RenderLayers;
 
@@ -15,7 +16,7 @@ use super::architecture::{BuildingMesh, build_building};
 
 #[cfg(feature = "server")]
 type UI = External<::imgui::Ui<'static>>;
-#[cfg(feature = "browser")]
+#[cfg(not(feature = "server"))]
 type UI = ();
 
 #[derive(Compact, Clone)]
@@ -24,6 +25,7 @@ pub struct BuildingInspector {
     user_interface: UserInterfaceID,
     current_building: Option<BuildingID>,
     current_households: CVec<HouseholdID>,
+    current_parking: ParkingCapacity,
     households_todo: CVec<HouseholdID>,
     return_ui_to: Option<UserInterfaceID>,
 }
@@ -39,6 +41,7 @@ impl BuildingInspector {
             user_interface,
             current_building: None,
             current_households: CVec::new(),
+            current_parking: ParkingCapacity::default(),
             households_todo: CVec::new(),
             return_ui_to: None,
         }
@@ -48,10 +51,12 @@ impl BuildingInspector {
         &mut self,
         building: BuildingID,
         households: &CVec<HouseholdID>,
+        parking: ParkingCapacity,
         world: &mut World,
     ) {
         self.current_building = Some(building);
         self.current_households = households.clone();
+        self.current_parking = parking;
         self.households_todo.clear();
         self.user_interface.add_2d(self.id_as(), world);
     }
@@ -92,6 +97,11 @@ impl Interactable2d for BuildingInspector {
                         ui.text(im_str!(
                             "# of households: {}",
                             self.current_households.len()
+                        ));
+                        ui.text(im_str!(
+                            "Parking: {} of {} spaces used",
+                            self.current_parking.occupied,
+                            self.current_parking.spaces
                         ))
                     });
 
@@ -120,6 +130,7 @@ impl Interactable3d for Building {
             BuildingInspector::local_first(world).set_inspected_building(
                 self.id,
                 self.all_households().into(),
+                self.parking,
                 world,
             );
         };
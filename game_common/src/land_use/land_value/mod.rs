@@ -0,0 +1,275 @@
+use kay::{ActorSystem, World, Fate, Actor, TypedID};
+use compact::CVec;
+use descartes::{N, P2, Area};
+use monet::{GrouperID, GrouperIndividualID, Mesh};
+
+use simulation::{Simulation, Sleeper, Instant, Duration, Ticks};
+use transport::lane::Lane;
+use transport::pathfinding::{Location, PreciseLocation, DistanceRequester};
+use land_use::buildings::{Building, BuildingStyle};
+use land_use::vacant_lots::{VacantLot, VacantLotID};
+use land_use::zone_planning::Lot;
+use style::colors;
+use render_layers::RenderLayers;
+
+// of lots that nothing is known about yet
+pub const BASE_LAND_VALUE: f32 = 0.3;
+// shops and jobs further away than this don't make a lot more accessible
+pub const ACCESSIBILITY_RADIUS: N = 2000.0;
+// buildings closer than this count as neighboring uses
+const NEIGHBORHOOD_RADIUS: N = 200.0;
+// lanes this close to the connection point of a lot give access to it
+pub const LOT_ACCESS_MAX_DISTANCE: N = 10.0;
+// a destination this many seconds away counts half as much as one right next door
+const ACCESSIBILITY_HALF_SECS: f32 = 300.0;
+// accessibility counts logarithmically, so each further destination adds a bit less,
+// but lots with a hundred destinations around still rank above those with a dozen
+const ACCESSIBILITY_WEIGHT: f32 = 0.12;
+const CONGESTION_WEIGHT: f32 = 0.3;
+// how long to wait for all buildings and pathfinding answers
+const ESTIMATION_MINUTES: usize = 10;
+
+// shops and workplaces that people want to reach from a lot
+fn is_destination(style: BuildingStyle) -> bool {
+    match style {
+        BuildingStyle::GroceryShop
+        | BuildingStyle::ShopWithFlats
+        | BuildingStyle::Bakery
+        | BuildingStyle::Mill
+        | BuildingStyle::Field => true,
+        BuildingStyle::FamilyHouse
+        | BuildingStyle::ApartmentBlock
+        | BuildingStyle::NeighboringTownConnection => false,
+    }
+}
+
+// how much a building right next to a lot changes its value
+fn neighbor_influence(style: BuildingStyle) -> f32 {
+    match style {
+        BuildingStyle::FamilyHouse | BuildingStyle::ApartmentBlock => 0.02,
+        BuildingStyle::GroceryShop | BuildingStyle::ShopWithFlats => 0.05,
+        BuildingStyle::Bakery => 0.02,
+        BuildingStyle::Mill => -0.1,
+        BuildingStyle::Field | BuildingStyle::NeighboringTownConnection => 0.0,
+    }
+}
+
+// accessibility is the sum of all reachable destinations, each weighted by how close it is
+fn land_value(accessibility: f32, neighborhood: f32, congestion: f32) -> f32 {
+    (BASE_LAND_VALUE + ACCESSIBILITY_WEIGHT * accessibility.ln_1p() + neighborhood
+        - CONGESTION_WEIGHT * congestion)
+        .max(0.0)
+        .min(1.0)
+}
+
+// Collects accessibility, neighboring uses and congestion around a lot
+// for a while and then reports the resulting land value to the lot
+#[derive(Compact, Clone)]
+pub struct LandValueEstimator {
+    id: LandValueEstimatorID,
+    lot: VacantLotID,
+    access: Option<Location>,
+    congestion: f32,
+    // destinations found before the lot got access
+    destinations: CVec<Location>,
+    accessibility: f32,
+    neighborhood: f32,
+}
+
+impl LandValueEstimator {
+    pub fn spawn(
+        id: LandValueEstimatorID,
+        lot_id: VacantLotID,
+        lot: &Lot,
+        world: &mut World,
+    ) -> LandValueEstimator {
+        if let Some(&(connection_point, _)) = lot.connection_points.first() {
+            Lane::global_broadcast(world).report_lot_access(id, connection_point, world);
+        }
+        Building::global_broadcast(world).report_as_neighbor(id, lot.center_point(), world);
+
+        Simulation::local_first(world).wake_up_in(
+            Ticks::from(Duration::from_minutes(ESTIMATION_MINUTES)),
+            id.into(),
+            world,
+        );
+
+        LandValueEstimator {
+            id,
+            lot: lot_id,
+            access: None,
+            congestion: 0.0,
+            destinations: CVec::new(),
+            accessibility: 0.0,
+            neighborhood: 0.0,
+        }
+    }
+
+    pub fn on_access(&mut self, location: Location, congestion: f32, world: &mut World) {
+        if self.access.is_none() {
+            self.access = Some(location);
+            self.congestion = congestion;
+
+            for destination in &self.destinations {
+                location
+                    .node
+                    .get_distance_to(*destination, self.id_as(), world);
+            }
+            self.destinations.clear();
+        }
+    }
+
+    pub fn on_neighbor(
+        &mut self,
+        style: BuildingStyle,
+        maybe_location: Option<PreciseLocation>,
+        distance: N,
+        world: &mut World,
+    ) {
+        if distance < NEIGHBORHOOD_RADIUS {
+            self.neighborhood += neighbor_influence(style) * (1.0 - distance / NEIGHBORHOOD_RADIUS);
+        }
+
+        if !is_destination(style) {
+            return;
+        }
+
+        if let Some(destination) = maybe_location {
+            if let Some(access) = self.access {
+                access
+                    .node
+                    .get_distance_to(destination.location, self.id_as(), world);
+            } else {
+                self.destinations.push(destination.location);
+            }
+        }
+    }
+
+    pub fn land_value(&self) -> f32 {
+        land_value(self.accessibility, self.neighborhood, self.congestion)
+    }
+
+    pub fn done(&mut self, _: &mut World) -> Fate {
+        Fate::Die
+    }
+}
+
+impl DistanceRequester for LandValueEstimator {
+    fn on_distance(&mut self, maybe_distance_and_time: Option<(f32, f32)>, _: &mut World) {
+        if let Some((_distance, travel_time)) = maybe_distance_and_time {
+            self.accessibility += 1.0 / (1.0 + travel_time / ACCESSIBILITY_HALF_SECS);
+        }
+    }
+}
+
+impl Sleeper for LandValueEstimator {
+    fn wake(&mut self, _: Instant, world: &mut World) {
+        let land_value = self.land_value();
+        self.lot.on_land_value(land_value, world);
+        self.id.done(world);
+    }
+}
+
+const N_HEATMAP_BUCKETS: usize = 5;
+
+// Shows the land value of all vacant lots as a heatmap, only while enabled
+#[derive(Compact, Clone)]
+pub struct LandValueRenderer {
+    id: LandValueRendererID,
+    groupers: CVec<GrouperID>,
+    visible: bool,
+}
+
+impl LandValueRenderer {
+    pub fn spawn(id: LandValueRendererID, world: &mut World) -> LandValueRenderer {
+        LandValueRenderer {
+            id,
+            groupers: (0..N_HEATMAP_BUCKETS)
+                .map(|bucket| {
+                    GrouperID::spawn(
+                        colors::LAND_VALUE_HEATMAP[bucket],
+                        RenderLayers::LandValueHeatmap as u32 + bucket as u32 * 1_000,
+                        true,
+                        world,
+                    )
+                })
+                .collect(),
+            visible: false,
+        }
+    }
+
+    pub fn set_visible(&mut self, visible: bool, world: &mut World) {
+        if visible && !self.visible {
+            VacantLot::global_broadcast(world).report_land_value(self.id, world);
+        } else if !visible && self.visible {
+            for grouper in &self.groupers {
+                grouper.clear(world);
+            }
+        }
+        self.visible = visible;
+    }
+
+    pub fn update_lot(
+        &mut self,
+        lot_id: VacantLotID,
+        area: &Area,
+        land_value: f32,
+        world: &mut World,
+    ) {
+        if self.visible {
+            self.remove_lot(lot_id, world);
+
+            let bucket =
+                ((land_value * N_HEATMAP_BUCKETS as f32) as usize).min(N_HEATMAP_BUCKETS - 1);
+            // TODO: ugly: VacantLot is not really a GrouperIndividual
+            self.groupers[bucket].add_frozen(
+                unsafe { GrouperIndividualID::from_raw(lot_id.as_raw()) },
+                Mesh::from_area(area),
+                world,
+            );
+        }
+    }
+
+    pub fn remove_lot(&mut self, lot_id: VacantLotID, world: &mut World) {
+        for grouper in &self.groupers {
+            grouper.remove(
+                unsafe { GrouperIndividualID::from_raw(lot_id.as_raw()) },
+                world,
+            );
+        }
+    }
+}
+
+pub fn setup(system: &mut ActorSystem) {
+    system.register::<LandValueEstimator>();
+    system.register::<LandValueRenderer>();
+    auto_setup(system);
+}
+
+pub fn spawn(world: &mut World) -> LandValueRendererID {
+    LandValueRendererID::spawn(world)
+}
+
+mod kay_auto;
+pub use self::kay_auto::*;
+
+#[test]
+fn test_land_value_ranks_lots() {
+    let next_door = |n_destinations: usize| n_destinations as f32;
+
+    let nothing_around = land_value(0.0, 0.0, 0.0);
+    let few_shops = land_value(next_door(3), 0.0, 0.0);
+    let many_shops = land_value(next_door(20), 0.0, 0.0);
+    let town_center = land_value(next_door(100), 0.0, 0.0);
+    assert_eq!(nothing_around, BASE_LAND_VALUE);
+    assert!(nothing_around < few_shops);
+    assert!(few_shops < many_shops);
+    assert!(many_shops < town_center);
+    assert!(town_center < 1.0);
+
+    let next_to_mill = land_value(next_door(20), neighbor_influence(BuildingStyle::Mill), 0.0);
+    assert!(next_to_mill < many_shops);
+
+    let congested = land_value(next_door(20), 0.0, 0.5);
+    assert!(congested < many_shops);
+}
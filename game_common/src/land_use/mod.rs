@@ -3,14 +3,18 @@ use stagemaster::UserInterfaceID;
 
 pub mod buildings;
 pub mod vacant_lots;
+pub mod land_value;
 pub mod construction;
 pub mod zone_planning;
 
 pub fn setup(system: &mut ActorSystem) {
     buildings::setup(system);
     vacant_lots::setup(system);
+    land_value::setup(system);
+    zone_planning::interaction::setup(system);
 }
 
 pub fn spawn(world: &mut World, user_interface: UserInterfaceID) {
-    buildings::spawn(world, user_interface)
+    buildings::spawn(world, user_interface);
+    land_value::spawn(world);
 }
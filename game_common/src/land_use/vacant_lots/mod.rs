@@ -1,11 +1,14 @@
-use kay::{World, Fate, ActorSystem};
+use kay::{World, Fate, ActorSystem, Actor, TypedID};
 use descartes::{P2, V2, Area, WithUniqueOrthogonal, ClosedLinePath, LinePath,
 PointContainer, AreaError};
 use ordered_float::OrderedFloat;
 
 use land_use::zone_planning::{Lot, BuildingIntent};
 use land_use::buildings::BuildingStyle;
-use land_use::buildings::architecture::ideal_lot_shape;
+use land_use::buildings::architecture::{ideal_lot_shape, min_height};
+use land_use::land_value::{LandValueEstimatorID, LandValueRenderer, LandValueRendererID,
+BASE_LAND_VALUE};
+use simulation::{Simulation, Sleeper, Instant, Duration, Ticks};
 use economy::immigration_and_development::DevelopmentManagerID;
 use itertools::{Itertools, MinMaxResult};
 
@@ -17,6 +20,25 @@ pub struct VacantLot {
     pub id: VacantLotID,
     pub lot: Lot,
     based_on: StepID,
+    pub land_value: f32,
+    // grows while the land value stays the same, to estimate stable lots less often
+    update_hours: usize,
+    estimating: bool,
+}
+
+const MIN_LAND_VALUE_UPDATE_HOURS: usize = 6;
+const MAX_LAND_VALUE_UPDATE_HOURS: usize = 48;
+// changes smaller than this don't make a lot update sooner again
+const LAND_VALUE_CHANGE_THRESHOLD: f32 = 0.02;
+// spreads out the first estimation of lots that appear at the same time
+const FIRST_ESTIMATION_SPREAD_MINUTES: usize = 60;
+
+fn next_update_hours(update_hours: usize, old_land_value: f32, new_land_value: f32) -> usize {
+    if (new_land_value - old_land_value).abs() < LAND_VALUE_CHANGE_THRESHOLD {
+        (update_hours * 2).min(MAX_LAND_VALUE_UPDATE_HOURS)
+    } else {
+        MIN_LAND_VALUE_UPDATE_HOURS
+    }
 }
 
 impl Lot {
@@ -71,7 +93,13 @@ impl Lot {
         allow_left_split: bool,
         allow_right_split: bool,
     ) -> Result<Option<Lot>, AreaError> {
-        let needed_shape = ideal_lot_shape(building_style);
+        if self.max_height > 0 && f32::from(self.max_height) < min_height(building_style) {
+            return Ok(None);
+        }
+
+        let (ideal_width, ideal_depth) = ideal_lot_shape(building_style);
+        // the building has to fit behind the set back
+        let needed_shape = (ideal_width, ideal_depth + f32::from(self.set_back));
         let width_depth_per_connection_point = self.width_depth_per_connection_point();
 
         let maybe_suitable_connection_point = width_depth_per_connection_point.iter().find(
@@ -179,20 +207,49 @@ impl Lot {
 }
 
 impl VacantLot {
-    pub fn spawn(id: VacantLotID, lot: &Lot, based_on: StepID, _world: &mut World) -> VacantLot {
+    pub fn spawn(id: VacantLotID, lot: &Lot, based_on: StepID, world: &mut World) -> VacantLot {
+        let spread_minutes = id.as_raw().instance_id as usize % FIRST_ESTIMATION_SPREAD_MINUTES;
+        Simulation::local_first(world).wake_up_in(
+            Ticks::from(Duration::from_minutes(spread_minutes)),
+            id.into(),
+            world,
+        );
+
         VacantLot {
             id,
             based_on,
             lot: lot.clone(),
+            land_value: BASE_LAND_VALUE,
+            update_hours: MIN_LAND_VALUE_UPDATE_HOURS,
+            estimating: false,
         }
     }
 
+    pub fn on_land_value(&mut self, land_value: f32, world: &mut World) {
+        self.estimating = false;
+        self.update_hours = next_update_hours(self.update_hours, self.land_value, land_value);
+        self.land_value = land_value;
+        LandValueRenderer::local_first(world).update_lot(
+            self.id,
+            self.lot.area.clone(),
+            land_value,
+            world,
+        );
+    }
+
+    pub fn report_land_value(&mut self, renderer: LandValueRendererID, world: &mut World) {
+        renderer.update_lot(self.id, self.lot.area.clone(), self.land_value, world);
+    }
+
     pub fn suggest_lot(
         &mut self,
         building_style: BuildingStyle,
         requester: DevelopmentManagerID,
         world: &mut World,
     ) {
+        if !self.lot.permits(building_style) {
+            return;
+        }
         println!("Trying suggest");
         match self.lot.split_for(building_style, true, true) {
             Ok(Some(suitable_lot)) => requester.on_suggested_lot(
@@ -201,6 +258,7 @@ impl VacantLot {
                     building_style,
                 },
                 self.based_on,
+                self.land_value,
                 world,
             ),
             Ok(None) => {}
@@ -215,11 +273,28 @@ impl Constructable for VacantLot {
     }
 
     fn destruct(&mut self, report_to: ConstructionID, world: &mut World) -> Fate {
+        LandValueRenderer::local_first(world).remove_lot(self.id, world);
         report_to.action_done(self.id.into(), world);
+        // pending wakes and land value estimations for a dead lot are just dropped
         Fate::Die
     }
 }
 
+impl Sleeper for VacantLot {
+    fn wake(&mut self, _: Instant, world: &mut World) {
+        if !self.estimating {
+            LandValueEstimatorID::spawn(self.id, self.lot.clone(), world);
+            self.estimating = true;
+        }
+
+        Simulation::local_first(world).wake_up_in(
+            Ticks::from(Duration::from_hours(self.update_hours)),
+            self.id_as(),
+            world,
+        );
+    }
+}
+
 pub fn setup(system: &mut ActorSystem) {
     system.register::<VacantLot>();
     auto_setup(system);
@@ -227,3 +302,15 @@ pub fn setup(system: &mut ActorSystem) {
 
 mod kay_auto;
 pub use self::kay_auto::*;
+
+#[test]
+fn test_stable_lots_are_estimated_less_often() {
+    let mut update_hours = MIN_LAND_VALUE_UPDATE_HOURS;
+    for _ in 0..10 {
+        update_hours = next_update_hours(update_hours, 0.5, 0.505);
+    }
+    assert_eq!(update_hours, MAX_LAND_VALUE_UPDATE_HOURS);
+
+    update_hours = next_update_hours(update_hours, 0.5, 0.6);
+    assert_eq!(update_hours, MIN_LAND_VALUE_UPDATE_HOURS);
+}
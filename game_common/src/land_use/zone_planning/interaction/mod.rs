@@ -1,13 +1,52 @@
-use kay::World;
+use kay::{World, Fate, TypedID, ActorSystem};
 use compact::CVec;
 use monet::{RendererID, Mesh, Instance};
-use planning::{PlanResult, PrototypeKind};
+use stagemaster::user_interface::UserInterfaceID;
+use stagemaster::{Interactable2d, Interactable2dID};
+#[cfg(feature = "server")]
+use imgui::ImGuiSetCond_FirstUseEver;
+use planning::{PlanHistory, PlanResult, PrototypeKind, GestureIntent, GestureID, ProposalID,
+PlanManagerID, VersionedGesture};
+use planning::interaction::{GestureInteractable, GestureInteractableID};
 use construction::Action;
 use style::colors;
 use style::dimensions::LOT_OUTLINE_WIDTH;
 use render_layers::RenderLayers;
 
-use super::{LotPrototype, Lot, LotOccupancy, LandUse};
+use super::{LotPrototype, Lot, LotOccupancy, LandUse, ZoneIntent, MAX_HEIGHT_LIMIT, MAX_SET_BACK};
+
+const LAND_USE_LAYERS: [(LandUse, RenderLayers, [f32; 3]); 6] = [
+    (
+        LandUse::Residential,
+        RenderLayers::PlanningLotResidentialArea,
+        colors::RESIDENTIAL,
+    ),
+    (
+        LandUse::Commercial,
+        RenderLayers::PlanningLotCommercialArea,
+        colors::COMMERCIAL,
+    ),
+    (
+        LandUse::Industrial,
+        RenderLayers::PlanningLotIndustrialArea,
+        colors::INDUSTRIAL,
+    ),
+    (
+        LandUse::Agricultural,
+        RenderLayers::PlanningLotAgriculturalArea,
+        colors::AGRICULTURAL,
+    ),
+    (
+        LandUse::Recreational,
+        RenderLayers::PlanningLotRecreationalArea,
+        colors::RECREATIONAL,
+    ),
+    (
+        LandUse::Official,
+        RenderLayers::PlanningLotOfficialArea,
+        colors::OFFICIAL,
+    ),
+];
 
 pub fn render_preview(
     result_preview: &PlanResult,
@@ -16,7 +55,10 @@ pub fn render_preview(
     _frame: usize,
     world: &mut World,
 ) {
-    let mut lot_residential_mesh = Mesh::empty();
+    let mut land_use_meshes = LAND_USE_LAYERS
+        .iter()
+        .map(|_| Mesh::empty())
+        .collect::<Vec<_>>();
     let mut lot_occupied_outline_mesh = Mesh::empty();
     let mut lot_vacant_outline_mesh = Mesh::empty();
 
@@ -45,8 +87,8 @@ pub fn render_preview(
             }
 
             for land_use in land_uses {
-                if *land_use == LandUse::Residential {
-                    lot_residential_mesh += Mesh::from_area(area);
+                if let Some(idx) = LAND_USE_LAYERS.iter().position(|&(l, ..)| l == *land_use) {
+                    land_use_meshes[idx] += Mesh::from_area(area);
                 }
             }
         }
@@ -68,11 +110,134 @@ pub fn render_preview(
         world,
     );
 
-    renderer_id.update_individual(
-        RenderLayers::PlanningLotResidentialArea as u32,
-        lot_residential_mesh,
-        Instance::with_color(colors::RESIDENTIAL),
-        true,
-        world,
-    );
+    for (&(_, layer, color), mesh) in LAND_USE_LAYERS.iter().zip(land_use_meshes) {
+        renderer_id.update_individual(layer as u32, mesh, Instance::with_color(color), true, world);
+    }
+}
+
+// Lets the value of a height limit or set back be changed while its plan is open
+#[derive(Compact, Clone)]
+pub struct ZoneRestrictionInteractable {
+    id: ZoneRestrictionInteractableID,
+    plan_manager: PlanManagerID,
+    proposal_id: ProposalID,
+    gesture_id: GestureID,
+    restriction: ZoneIntent,
+}
+
+impl ZoneRestrictionInteractable {
+    pub fn spawn(
+        id: ZoneRestrictionInteractableID,
+        user_interface: UserInterfaceID,
+        plan_manager: PlanManagerID,
+        proposal_id: ProposalID,
+        gesture_id: GestureID,
+        restriction: ZoneIntent,
+        world: &mut World,
+    ) -> Self {
+        user_interface.add_2d(id.into(), world);
+
+        ZoneRestrictionInteractable {
+            id,
+            plan_manager,
+            proposal_id,
+            gesture_id,
+            restriction,
+        }
+    }
+}
+
+impl GestureInteractable for ZoneRestrictionInteractable {
+    fn remove(&self, user_interface: UserInterfaceID, world: &mut World) -> Fate {
+        user_interface.remove_2d(self.id.into(), world);
+        Fate::Die
+    }
 }
+
+impl Interactable2d for ZoneRestrictionInteractable {
+    #[cfg(feature = "server")]
+    fn draw(&mut self, world: &mut World, ui: &::imgui::Ui<'static>) {
+        let mut maybe_new_restriction = None;
+
+        let (name, label, value, max_value) = match self.restriction {
+            ZoneIntent::MaxHeight(max_height) => (
+                "Height Limit",
+                im_str!("Max. height (m)"),
+                max_height,
+                MAX_HEIGHT_LIMIT,
+            ),
+            ZoneIntent::SetBack(set_back) => {
+                ("Set Back", im_str!("Set back (m)"), set_back, MAX_SET_BACK)
+            }
+            ZoneIntent::LandUse(_) => return,
+        };
+        let title = ::imgui::ImString::new(format!("{}##{}", name, self.gesture_id.0));
+
+        ui.window(&title)
+            .size((250.0, 60.0), ImGuiSetCond_FirstUseEver)
+            .collapsible(true)
+            .build(|| {
+                let mut new_value = i32::from(value);
+                if ui
+                    .slider_int(label, &mut new_value, 1, i32::from(max_value))
+                    .build()
+                {
+                    maybe_new_restriction = Some(match self.restriction {
+                        ZoneIntent::MaxHeight(_) => ZoneIntent::MaxHeight(new_value as u8),
+                        _ => ZoneIntent::SetBack(new_value as u8),
+                    });
+                }
+            });
+
+        if let Some(new_restriction) = maybe_new_restriction {
+            self.restriction = new_restriction.clone();
+            self.plan_manager.set_intent(
+                self.proposal_id,
+                self.gesture_id,
+                GestureIntent::Zone(new_restriction),
+                true,
+                world,
+            );
+        }
+    }
+
+    #[cfg(not(feature = "server"))]
+    fn draw(&mut self, world: &mut World, ui: &()) {}
+}
+
+pub fn spawn_gesture_interactables(
+    history: &PlanHistory,
+    user_interface: UserInterfaceID,
+    plan_manager: PlanManagerID,
+    proposal_id: ProposalID,
+    world: &mut World,
+) -> Vec<GestureInteractableID> {
+    history
+        .gestures
+        .pairs()
+        .filter_map(
+            |(gesture_id, &VersionedGesture(ref gesture, _))| match gesture.intent {
+                GestureIntent::Zone(ZoneIntent::LandUse(_)) => None,
+                GestureIntent::Zone(ref restriction) => Some(
+                    ZoneRestrictionInteractableID::spawn(
+                        user_interface,
+                        plan_manager,
+                        proposal_id,
+                        *gesture_id,
+                        restriction.clone(),
+                        world,
+                    ).into(),
+                ),
+                _ => None,
+            },
+        )
+        .collect()
+}
+
+pub fn setup(system: &mut ActorSystem) {
+    system.register::<ZoneRestrictionInteractable>();
+    auto_setup(system);
+}
+
+pub mod kay_auto;
+pub use self::kay_auto::*;
@@ -9,6 +9,8 @@ PrototypeKind, GestureIntent, StepID};
 
 pub mod interaction;
 
+// Height limits and set backs apply to all land use zones they overlap,
+// their values can be changed while their plan is open
 #[derive(Compact, Clone, Serialize, Deserialize)]
 pub enum ZoneIntent {
     LandUse(LandUse),
@@ -16,6 +18,11 @@ pub enum ZoneIntent {
     SetBack(u8),
 }
 
+pub const DEFAULT_MAX_HEIGHT: u8 = 6;
+pub const DEFAULT_SET_BACK: u8 = 5;
+pub const MAX_HEIGHT_LIMIT: u8 = 100;
+pub const MAX_SET_BACK: u8 = 50;
+
 #[derive(Copy, Clone, PartialEq, Serialize, Deserialize, Debug)]
 pub enum LandUse {
     Residential,
@@ -26,16 +33,40 @@ pub enum LandUse {
     Official,
 }
 
+impl LandUse {
+    pub fn permits(&self, building_style: BuildingStyle) -> bool {
+        match (*self, building_style) {
+            (LandUse::Residential, BuildingStyle::FamilyHouse)
+            | (LandUse::Residential, BuildingStyle::ApartmentBlock)
+            | (LandUse::Commercial, BuildingStyle::GroceryShop)
+            | (LandUse::Commercial, BuildingStyle::ShopWithFlats)
+            | (LandUse::Commercial, BuildingStyle::Bakery)
+            | (LandUse::Industrial, BuildingStyle::Mill)
+            | (LandUse::Industrial, BuildingStyle::Bakery)
+            | (LandUse::Agricultural, BuildingStyle::Field) => true,
+            _ => false,
+        }
+    }
+}
+
 #[derive(Compact, Clone, Serialize, Deserialize, Debug)]
 pub struct Lot {
     pub area: Area,
     pub land_uses: CVec<LandUse>,
+    // in meters, 0 means unrestricted
     pub max_height: u8,
+    // in meters from the road, 0 means unrestricted
     pub set_back: u8,
     pub connection_points: CVec<(P2, V2)>,
 }
 
 impl Lot {
+    pub fn permits(&self, building_style: BuildingStyle) -> bool {
+        self.land_uses
+            .iter()
+            .any(|land_use| land_use.permits(building_style))
+    }
+
     pub fn center_point(&self) -> P2 {
         let outline = &self.area.primitives[0].boundary.path();
         P2::from_coordinates(
@@ -65,6 +96,40 @@ pub enum LotOccupancy {
     Occupied(BuildingStyle),
 }
 
+// overlapping height limits take the lowest, overlapping set backs the biggest
+fn restrict(max_height: u8, set_back: u8, restriction: &ZoneIntent) -> (u8, u8) {
+    match *restriction {
+        ZoneIntent::MaxHeight(limit) if max_height == 0 => (limit, set_back),
+        ZoneIntent::MaxHeight(limit) => (max_height.min(limit), set_back),
+        ZoneIntent::SetBack(distance) => (max_height, set_back.max(distance)),
+        ZoneIntent::LandUse(_) => unreachable!(),
+    }
+}
+
+const N_OUTLINE_SAMPLES: usize = 10;
+
+fn outline_samples(area: &Area) -> Vec<P2> {
+    let outline = area.primitives[0].boundary.path();
+    (0..N_OUTLINE_SAMPLES)
+        .map(|i| outline.along(i as f32 * (outline.length() / N_OUTLINE_SAMPLES as f32)))
+        .collect()
+}
+
+// for areas whose boundaries don't cross: whether one lies within the other.
+// Most of the outline has to be inside, so areas that only touch don't count
+fn lies_within(shape: &Area, other: &Area) -> bool {
+    let samples = outline_samples(shape);
+    let n_inside = samples
+        .iter()
+        .filter(|&&point| other.contains(point))
+        .count();
+    n_inside * 2 > samples.len()
+}
+
+fn overlaps_without_crossing(shape: &Area, restriction_area: &Area) -> bool {
+    lies_within(shape, restriction_area) || lies_within(restriction_area, shape)
+}
+
 pub fn calculate_prototypes(
     history: &PlanHistory,
     current_result: &PlanResult,
@@ -181,6 +246,27 @@ pub fn calculate_prototypes(
             })
             .collect::<Vec<_>>();
 
+        let restriction_areas = history
+            .gestures
+            .values()
+            .filter_map(|VersionedGesture(gesture, step_id)| match gesture.intent {
+                GestureIntent::Zone(ZoneIntent::LandUse(_)) => None,
+                GestureIntent::Zone(ref restriction) => {
+                    Some((restriction.clone(), &gesture.points, *step_id))
+                }
+                _ => None,
+            })
+            .filter_map(|(restriction, points, step_id)| {
+                Some((
+                    restriction,
+                    Area::new_simple(ClosedLinePath::new(LinePath::new(
+                        points.iter().chain(points.first()).cloned().collect(),
+                    )?)?),
+                    step_id,
+                ))
+            })
+            .collect::<Vec<_>>();
+
         let paved_or_built_areas = || paved_area_areas.iter().chain(building_areas.iter());
 
         let land_use_areas_influenced: Vec<(LandUse, Area, PrototypeID)> = land_use_areas
@@ -223,9 +309,83 @@ pub fn calculate_prototypes(
             })
             .collect();
 
-        land_use_areas_influenced
+        // parts of land use areas with different restrictions become separate lots
+        let land_use_areas_restricted: Vec<(LandUse, u8, u8, Area, PrototypeID)> =
+            land_use_areas_influenced
+                .into_iter()
+                .flat_map(|(land_use, shape, id)| {
+                    let mut shapes = vec![(0, 0, shape, id)];
+
+                    for &(ref restriction, ref restriction_area, restriction_step_id) in
+                        &restriction_areas
+                    {
+                        shapes = shapes
+                            .into_iter()
+                            .flat_map(|(max_height, set_back, shape, current_id)| {
+                                if let Some(split) = shape.split_if_intersects(restriction_area) {
+                                    let (restricted_height, restricted_set_back) =
+                                        restrict(max_height, set_back, restriction);
+                                    let inside = split
+                                        .intersection()
+                                        .into_iter() // this just flattens a result
+                                        .flat_map(|cut_shapes| cut_shapes.disjoint())
+                                        .enumerate()
+                                        .map(|(i, cut_shape)| {
+                                            (
+                                                restricted_height,
+                                                restricted_set_back,
+                                                cut_shape,
+                                                current_id
+                                                    .add_influences((restriction_step_id, i)),
+                                            )
+                                        });
+                                    let outside = split
+                                        .a_minus_b()
+                                        .into_iter()
+                                        .flat_map(|cut_shapes| cut_shapes.disjoint())
+                                        .enumerate()
+                                        .map(|(i, cut_shape)| {
+                                            (
+                                                max_height,
+                                                set_back,
+                                                cut_shape,
+                                                current_id.add_influences((
+                                                    restriction_step_id,
+                                                    i,
+                                                    "outside",
+                                                )),
+                                            )
+                                        });
+                                    inside.chain(outside).collect()
+                                } else if overlaps_without_crossing(&shape, restriction_area) {
+                                    // a restriction inside of a lot applies to the whole lot
+                                    let (restricted_height, restricted_set_back) =
+                                        restrict(max_height, set_back, restriction);
+                                    vec![(
+                                        restricted_height,
+                                        restricted_set_back,
+                                        shape,
+                                        current_id.add_influences(restriction_step_id),
+                                    )]
+                                } else {
+                                    vec![(max_height, set_back, shape, current_id)]
+                                }
+                            })
+                            .collect();
+                    }
+
+                    shapes
+                        .into_iter()
+                        .map(|(max_height, set_back, shape, id)| {
+                            (land_use, max_height, set_back, shape, id)
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect();
+
+        land_use_areas_restricted
             .into_iter()
-            .filter_map(|(land_use, area, id)| {
+            .filter_map(|(land_use, max_height, set_back, area, id)| {
                 let connection_points = area.primitives[0]
                     .boundary
                     .path()
@@ -253,8 +413,8 @@ pub fn calculate_prototypes(
                         kind: PrototypeKind::Lot(LotPrototype {
                             lot: Lot {
                                 land_uses: vec![land_use].into(),
-                                max_height: 0,
-                                set_back: 0,
+                                max_height,
+                                set_back,
                                 connection_points,
                                 area,
                             },
@@ -273,3 +433,38 @@ pub fn calculate_prototypes(
         .chain(building_prototypes)
         .collect())
 }
+
+#[cfg(test)]
+fn square(min: (f32, f32), size: f32) -> Area {
+    let (x, y) = min;
+    Area::new_simple(
+        ClosedLinePath::new(
+            LinePath::new(
+                vec![
+                    P2::new(x, y),
+                    P2::new(x + size, y),
+                    P2::new(x + size, y + size),
+                    P2::new(x, y + size),
+                    P2::new(x, y),
+                ]
+                .into(),
+            )
+            .unwrap(),
+        )
+        .unwrap(),
+    )
+}
+
+#[test]
+fn test_restrictions_apply_to_contained_and_containing_lots() {
+    let lot = square((0.0, 0.0), 100.0);
+    let inside_lot = square((40.0, 40.0), 10.0);
+    let around_lot = square((-50.0, -50.0), 200.0);
+    let elsewhere = square((300.0, 0.0), 50.0);
+    let touching_lot = square((100.0, 0.0), 100.0);
+
+    assert!(overlaps_without_crossing(&lot, &inside_lot));
+    assert!(overlaps_without_crossing(&lot, &around_lot));
+    assert!(!overlaps_without_crossing(&lot, &elsewhere));
+    assert!(!overlaps_without_crossing(&lot, &touching_lot));
+}
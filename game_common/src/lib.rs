@@ -20,22 +20,23 @@ extern crate compact_macros;
 pub extern crate kay;
 #[cfg(feature = "server")]
 pub extern crate monet;
-#[cfg(feature = "browser")]
+#[cfg(not(feature = "server"))]
 pub extern crate browser_monet;
-#[cfg(feature = "browser")]
+#[cfg(not(feature = "server"))]
 pub use browser_monet as monet;
 pub extern crate descartes;
 #[cfg(feature = "server")]
 pub extern crate stagemaster;
-#[cfg(feature = "browser")]
+#[cfg(not(feature = "server"))]
 pub extern crate browser_stagemaster;
-#[cfg(feature = "browser")]
+#[cfg(not(feature = "server"))]
 pub use browser_stagemaster as stagemaster;
 #[cfg(feature = "server")]
 #[macro_use]
 extern crate imgui;
 #[macro_use]
 extern crate serde_derive;
+extern crate serde_json;
 #[cfg(feature = "browser")]
 #[macro_use]
 extern crate stdweb;
@@ -59,6 +60,11 @@ pub mod ui_layers;
 pub mod render_layers;
 pub mod style;
 pub mod browser_ui;
+pub mod persistence;
+pub mod replay;
+pub mod scenario;
+pub mod statistics;
+pub mod governance;
 
 pub fn setup_all(system: &mut kay::ActorSystem) {
     for setup_fn in &[
@@ -71,6 +77,11 @@ pub fn setup_all(system: &mut kay::ActorSystem) {
         economy::setup,
         land_use::setup,
         browser_ui::setup,
+        persistence::setup,
+        replay::setup,
+        scenario::setup,
+        statistics::setup,
+        governance::setup,
     ] {
         setup_fn(system)
     }
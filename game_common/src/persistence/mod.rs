@@ -0,0 +1,409 @@
+use kay::{ActorSystem, World, Actor};
+use compact::{CVec, COption, CString};
+use stagemaster::{UserInterfaceID, Interactable2d, Interactable2dID};
+#[cfg(feature = "server")]
+use imgui::ImGuiSetCond_FirstUseEver;
+use std::fs::File;
+use std::io::{Read, Write};
+
+use simulation::{SimulationID, Instant};
+use planning::{PlanManagerID, PlanHistory, PlanResult};
+use land_use::buildings::Building;
+use economy::households::HouseholdSnapshot;
+use economy::resources::ResourceAmount;
+use governance::Treasury;
+
+pub const SNAPSHOT_VERSION: u32 = 1;
+pub const DEFAULT_SNAPSHOT_PATH: &str = "citybound_snapshot.json";
+
+// Lanes and buildings are not stored themselves, they are constructed again
+// from the master result and reconnect through the usual construction logic.
+// A snapshot resumes the city, not every moving part of it: cars on the road,
+// ongoing trips, household tasks and used offers all refer to actors that don't
+// survive the process, so they are not kept. Restored households start idle at
+// home and find new offers and tasks within their first decisions.
+#[derive(Serialize, Deserialize)]
+pub struct WorldSnapshot {
+    pub version: u32,
+    pub ticks: usize,
+    pub master_plan: PlanHistory,
+    pub master_result: PlanResult,
+    pub households: Vec<HouseholdSnapshot>,
+    #[serde(default)]
+    pub treasury_balance: Option<ResourceAmount>,
+}
+
+#[derive(Compact, Clone)]
+struct PendingSave {
+    instant: COption<Instant>,
+    master_plan: COption<PlanHistory>,
+    master_result: COption<PlanResult>,
+    households: CVec<HouseholdSnapshot>,
+    treasury_balance: COption<ResourceAmount>,
+    waited_a_frame: bool,
+}
+
+#[derive(Compact, Clone)]
+struct PendingRestore {
+    households: CVec<HouseholdSnapshot>,
+    placed: CVec<bool>,
+    // households are only moved back in once construction rebuilt the city
+    construction_done: bool,
+    waited_a_frame: bool,
+}
+
+#[derive(Compact, Clone)]
+pub struct Persistence {
+    id: PersistenceID,
+    simulation: SimulationID,
+    plan_manager: PlanManagerID,
+    snapshot_path: CString,
+    pending_save: COption<PendingSave>,
+    pending_restore: COption<PendingRestore>,
+}
+
+impl Persistence {
+    pub fn spawn(
+        id: PersistenceID,
+        simulation: SimulationID,
+        plan_manager: PlanManagerID,
+        snapshot_path: &CString,
+        _: &mut World,
+    ) -> Persistence {
+        Persistence {
+            id,
+            simulation,
+            plan_manager,
+            snapshot_path: snapshot_path.clone(),
+            pending_save: COption(None),
+            pending_restore: COption(None),
+        }
+    }
+
+    pub fn save(&mut self, world: &mut World) {
+        if self.pending_save.is_some() {
+            println!("Already saving a snapshot");
+            return;
+        }
+
+        self.pending_save = COption(Some(PendingSave {
+            instant: COption(None),
+            master_plan: COption(None),
+            master_result: COption(None),
+            households: CVec::new(),
+            treasury_balance: COption(None),
+            waited_a_frame: false,
+        }));
+
+        self.simulation.report_instant_to(self.id, world);
+        self.plan_manager.report_master_to(self.id, world);
+        Building::global_broadcast(world).save_households(self.id, world);
+        Treasury::global_first(world).report_balance_to(self.id, world);
+    }
+
+    pub fn on_instant(&mut self, instant: Instant, _: &mut World) {
+        if let Some(ref mut pending_save) = self.pending_save.0 {
+            pending_save.instant = COption(Some(instant));
+        }
+    }
+
+    pub fn on_master(
+        &mut self,
+        master_plan: &PlanHistory,
+        master_result: &PlanResult,
+        _: &mut World,
+    ) {
+        if let Some(ref mut pending_save) = self.pending_save.0 {
+            pending_save.master_plan = COption(Some(master_plan.clone()));
+            pending_save.master_result = COption(Some(master_result.clone()));
+        }
+    }
+
+    pub fn on_treasury_balance(&mut self, balance: ResourceAmount, _: &mut World) {
+        if let Some(ref mut pending_save) = self.pending_save.0 {
+            pending_save.treasury_balance = COption(Some(balance));
+        }
+    }
+
+    pub fn on_household_snapshot(&mut self, snapshot: &HouseholdSnapshot, _: &mut World) {
+        if let Some(ref mut pending_save) = self.pending_save.0 {
+            pending_save.households.push(snapshot.clone());
+        }
+    }
+
+    // Household snapshots arrive through buildings, so we can't know how many
+    // to expect - instead we write the snapshot once a whole frame has passed
+    pub fn finish_pending_save(&mut self, _: &mut World) {
+        let snapshot = match self.pending_save.0 {
+            Some(ref mut pending_save) => {
+                if !pending_save.waited_a_frame {
+                    pending_save.waited_a_frame = true;
+                    return;
+                }
+
+                match (
+                    *pending_save.instant,
+                    &*pending_save.master_plan,
+                    &*pending_save.master_result,
+                ) {
+                    (Some(instant), &Some(ref master_plan), &Some(ref master_result)) => {
+                        WorldSnapshot {
+                            version: SNAPSHOT_VERSION,
+                            ticks: instant.ticks(),
+                            master_plan: master_plan.clone(),
+                            master_result: master_result.clone(),
+                            households: pending_save.households.to_vec(),
+                            treasury_balance: *pending_save.treasury_balance,
+                        }
+                    }
+                    _ => return,
+                }
+            }
+            None => return,
+        };
+
+        self.pending_save = COption(None);
+
+        if let Err(err) = File::create(&*self.snapshot_path)
+            .map_err(|err| format!("{}", err))
+            .and_then(|file| write_snapshot(&snapshot, file))
+        {
+            println!(
+                "Error writing snapshot to {}: {}",
+                &*self.snapshot_path, err
+            );
+        } else {
+            println!(
+                "Saved snapshot with {} households to {}",
+                snapshot.households.len(),
+                &*self.snapshot_path
+            );
+        }
+    }
+
+    pub fn load(&mut self, world: &mut World) {
+        let snapshot = match File::open(&*self.snapshot_path)
+            .map_err(|err| format!("{}", err))
+            .and_then(read_snapshot)
+        {
+            Ok(snapshot) => snapshot,
+            Err(err) => {
+                println!(
+                    "Error loading snapshot from {}: {}",
+                    &*self.snapshot_path, err
+                );
+                return;
+            }
+        };
+
+        println!("Loaded snapshot, cars and ongoing trips start over");
+
+        self.simulation
+            .restore_instant(Instant::new(snapshot.ticks), world);
+        self.plan_manager.restore_master(
+            snapshot.master_plan,
+            snapshot.master_result,
+            self.id,
+            world,
+        );
+        self.pending_restore = COption(Some(PendingRestore {
+            placed: vec![false; snapshot.households.len()].into(),
+            households: snapshot.households.into(),
+            construction_done: false,
+            waited_a_frame: false,
+        }));
+        if let Some(balance) = snapshot.treasury_balance {
+            Treasury::global_first(world).restore_balance(balance, world);
+        }
+    }
+
+    pub fn on_construction_done(&mut self, world: &mut World) {
+        if let Some(ref mut pending_restore) = self.pending_restore.0 {
+            if !pending_restore.construction_done {
+                pending_restore.construction_done = true;
+                println!("Restoring {} households", pending_restore.households.len());
+                Building::global_broadcast(world).restore_households(
+                    pending_restore.households.clone(),
+                    self.id,
+                    world,
+                );
+            }
+        }
+    }
+
+    pub fn on_household_restored(&mut self, snapshot_idx: u32, _: &mut World) {
+        if let Some(ref mut pending_restore) = self.pending_restore.0 {
+            pending_restore.placed[snapshot_idx as usize] = true;
+        }
+    }
+
+    // like saving, waits a whole frame for all buildings to report back,
+    // then tells about households that found no home in the rebuilt city
+    pub fn finish_pending_restore(&mut self, _: &mut World) {
+        match self.pending_restore.0 {
+            Some(ref mut pending_restore) => {
+                if !pending_restore.construction_done {
+                    return;
+                }
+                if !pending_restore.waited_a_frame {
+                    pending_restore.waited_a_frame = true;
+                    return;
+                }
+
+                let mut n_unplaced = 0;
+                for (snapshot, &placed) in pending_restore
+                    .households
+                    .iter()
+                    .zip(pending_restore.placed.iter())
+                {
+                    if !placed {
+                        n_unplaced += 1;
+                        println!(
+                            "Couldn't find the home of a {:?} household at {:?}",
+                            snapshot.household_type, snapshot.home
+                        );
+                    }
+                }
+                println!(
+                    "Restored {} of {} households, {} couldn't be placed",
+                    pending_restore.households.len() - n_unplaced,
+                    pending_restore.households.len(),
+                    n_unplaced
+                );
+            }
+            None => return,
+        }
+
+        self.pending_restore = COption(None);
+    }
+}
+
+pub fn write_snapshot<W: Write>(snapshot: &WorldSnapshot, writer: W) -> Result<(), String> {
+    ::serde_json::to_writer(writer, snapshot).map_err(|err| format!("{}", err))
+}
+
+pub fn read_snapshot<R: Read>(reader: R) -> Result<WorldSnapshot, String> {
+    let snapshot: WorldSnapshot =
+        ::serde_json::from_reader(reader).map_err(|err| format!("{}", err))?;
+
+    if snapshot.version == SNAPSHOT_VERSION {
+        Ok(snapshot)
+    } else {
+        Err(format!(
+            "Can't load snapshot version {}, expected {}",
+            snapshot.version, SNAPSHOT_VERSION
+        ))
+    }
+}
+
+impl Interactable2d for Persistence {
+    #[cfg(feature = "server")]
+    fn draw(&mut self, world: &mut World, ui: &::imgui::Ui<'static>) {
+        let saving = self.pending_save.is_some();
+
+        ui.window(im_str!("Snapshot"))
+            .size((200.0, 50.0), ImGuiSetCond_FirstUseEver)
+            .collapsible(false)
+            .build(|| {
+                if saving {
+                    ui.text(im_str!("Saving..."));
+                } else {
+                    if ui.small_button(im_str!("Save")) {
+                        self.id.save(world);
+                    }
+                    ui.same_line(0.0);
+                    if ui.small_button(im_str!("Load")) {
+                        self.id.load(world);
+                    }
+                }
+            });
+    }
+
+    #[cfg(not(feature = "server"))]
+    fn draw(&mut self, world: &mut World, ui: &()) {}
+}
+
+impl Persistence {
+    pub fn add_to_ui(&mut self, user_interface: UserInterfaceID, world: &mut World) {
+        user_interface.add_2d(self.id_as(), world);
+    }
+}
+
+pub fn setup(system: &mut ActorSystem) {
+    system.register::<Persistence>();
+    auto_setup(system);
+}
+
+pub fn spawn(
+    world: &mut World,
+    simulation: SimulationID,
+    plan_manager: PlanManagerID,
+    snapshot_path: &str,
+) -> PersistenceID {
+    PersistenceID::spawn(
+        simulation,
+        plan_manager,
+        snapshot_path.to_owned().into(),
+        world,
+    )
+}
+
+mod kay_auto;
+pub use self::kay_auto::*;
+
+#[test]
+fn test_snapshot_round_trip() {
+    use economy::resources::Resource;
+    use economy::immigration_and_development::HouseholdTypeToSpawn;
+    use descartes::P2;
+
+    let snapshot = WorldSnapshot {
+        version: SNAPSHOT_VERSION,
+        ticks: 123_456,
+        master_plan: PlanHistory::new(),
+        master_result: PlanResult::new(),
+        households: vec![HouseholdSnapshot {
+            household_type: HouseholdTypeToSpawn::Family,
+            home: P2::new(10.0, -20.0),
+            resources: vec![(Resource::Money, 42.5)].into(),
+            member_resources: vec![vec![(Resource::Awakeness, 0.5)].into()].into(),
+        }],
+        treasury_balance: Some(1000.0),
+    };
+
+    let mut buffer = Vec::new();
+    write_snapshot(&snapshot, &mut buffer).unwrap();
+    let loaded = read_snapshot(&buffer[..]).unwrap();
+
+    assert_eq!(loaded.ticks, 123_456);
+    assert_eq!(loaded.treasury_balance, Some(1000.0));
+    assert_eq!(loaded.households.len(), 1);
+    let household = &loaded.households[0];
+    assert_eq!(household.household_type, HouseholdTypeToSpawn::Family);
+    assert_eq!(household.home, P2::new(10.0, -20.0));
+    assert_eq!(household.resources.to_vec(), vec![(Resource::Money, 42.5)]);
+    assert_eq!(
+        household.member_resources[0].to_vec(),
+        vec![(Resource::Awakeness, 0.5)]
+    );
+    assert_eq!(
+        loaded.master_plan.gestures.pairs().count(),
+        snapshot.master_plan.gestures.pairs().count()
+    );
+}
+
+#[test]
+fn test_snapshot_version_mismatch_is_rejected() {
+    let snapshot = WorldSnapshot {
+        version: SNAPSHOT_VERSION + 1,
+        ticks: 0,
+        master_plan: PlanHistory::new(),
+        master_result: PlanResult::new(),
+        households: Vec::new(),
+        treasury_balance: None,
+    };
+
+    let mut buffer = Vec::new();
+    write_snapshot(&snapshot, &mut buffer).unwrap();
+    assert!(read_snapshot(&buffer[..]).is_err());
+}
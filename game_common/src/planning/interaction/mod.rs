@@ -230,6 +230,15 @@ impl PlanManager {
                             world,
                         ),
                     )
+                    .chain(
+                        ::land_use::zone_planning::interaction::spawn_gesture_interactables(
+                            preview,
+                            user_interface,
+                            self.id,
+                            proposal_id,
+                            world,
+                        ),
+                    )
                     .collect()
             }
         };
@@ -627,7 +636,7 @@ impl GestureCanvas {
                 settings: External::new(::ENV.load_settings("Planning")),
             }
         }
-        #[cfg(feature = "browser")]
+        #[cfg(not(feature = "server"))]
         {
             GestureCanvas {
                 id,
@@ -750,9 +759,42 @@ impl Interactable2d for GestureCanvas {
                 if ui.small_button(im_str!("Road")) {
                     self.current_intent = GestureIntent::Road(RoadIntent::new(2, 2));
                 }
-                if ui.small_button(im_str!("Zone")) {
+                for &(label, land_use) in &[
+                    (im_str!("Residential Zone"), LandUse::Residential),
+                    (im_str!("Commercial Zone"), LandUse::Commercial),
+                    (im_str!("Industrial Zone"), LandUse::Industrial),
+                    (im_str!("Agricultural Zone"), LandUse::Agricultural),
+                ] {
+                    if ui.small_button(label) {
+                        self.current_intent = GestureIntent::Zone(ZoneIntent::LandUse(land_use));
+                    }
+                }
+                if ui.small_button(im_str!("Height Limit")) {
+                    use land_use::zone_planning::DEFAULT_MAX_HEIGHT;
+                    self.current_intent =
+                        GestureIntent::Zone(ZoneIntent::MaxHeight(DEFAULT_MAX_HEIGHT));
+                }
+                if ui.small_button(im_str!("Set Back")) {
+                    use land_use::zone_planning::DEFAULT_SET_BACK;
+                    self.current_intent =
+                        GestureIntent::Zone(ZoneIntent::SetBack(DEFAULT_SET_BACK));
+                }
+                if ui.small_button(im_str!("Bus Line")) {
+                    use transport::transit::{TransitLineIntent, DEFAULT_HEADWAY_MINUTES};
                     self.current_intent =
-                        GestureIntent::Zone(ZoneIntent::LandUse(LandUse::Residential));
+                        GestureIntent::TransitLine(TransitLineIntent::new(DEFAULT_HEADWAY_MINUTES));
+                }
+                if ui.small_button(im_str!("Signal Program")) {
+                    use transport::transport_planning::SignalProgramIntent;
+                    self.current_intent = GestureIntent::SignalProgram(SignalProgramIntent::new());
+                }
+                if ui.small_button(im_str!("Right of Way")) {
+                    use transport::transport_planning::RightOfWay;
+                    self.current_intent = GestureIntent::RightOfWay(RightOfWay::Yield);
+                }
+                if ui.small_button(im_str!("Roundabout")) {
+                    use transport::transport_planning::RoundaboutIntent;
+                    self.current_intent = GestureIntent::Roundabout(RoundaboutIntent::new(1));
                 }
                 if ui.small_button(im_str!("Implement")) {
                     self.plan_manager.implement(self.proposal_id, world);
@@ -830,7 +872,7 @@ impl Interactable2d for GestureCanvas {
         });
     }
 
-    #[cfg(feature = "browser")]
+    #[cfg(not(feature = "server"))]
     fn draw(&mut self, world: &mut World, ui: &()) {}
 }
 
@@ -6,9 +6,15 @@ use uuid::Uuid;
 use util::random::{seed, Rng};
 use std::hash::Hash;
 
-use transport::transport_planning::{RoadIntent, RoadPrototype};
+use transport::transport_planning::{RoadIntent, RoadPrototype, SignalProgramIntent, RightOfWay,
+RoundaboutIntent};
+use transport::transit::{TransitLineIntent, TransitLinePrototype};
 use land_use::zone_planning::{ZoneIntent, BuildingIntent, LotPrototype};
 use construction::Construction;
+use persistence::PersistenceID;
+use replay::{Journal, JournalInput};
+use governance::{Treasury, construction_cost};
+use economy::resources::ResourceAmount;
 
 pub mod rendering;
 pub mod interaction;
@@ -39,6 +45,10 @@ pub enum GestureIntent {
     Road(RoadIntent),
     Zone(ZoneIntent),
     Building(BuildingIntent),
+    TransitLine(TransitLineIntent),
+    SignalProgram(SignalProgramIntent),
+    RightOfWay(RightOfWay),
+    Roundabout(RoundaboutIntent),
 }
 
 #[derive(Copy, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
@@ -281,6 +291,7 @@ impl Prototype {
 pub enum PrototypeKind {
     Road(RoadPrototype),
     Lot(LotPrototype),
+    TransitLine(TransitLinePrototype),
 }
 
 #[derive(Copy, Clone, Hash, PartialEq, Eq, Serialize, Deserialize, Debug)]
@@ -305,6 +316,7 @@ impl PlanHistory {
         for prototype_fn in &[
             ::transport::transport_planning::calculate_prototypes,
             ::land_use::zone_planning::calculate_prototypes,
+            ::transport::transit::calculate_prototypes,
         ] {
             let new_prototypes = prototype_fn(self, &result)?;
 
@@ -339,6 +351,7 @@ pub struct PlanManager {
     proposals: CHashMap<ProposalID, Proposal>,
     implemented_proposals: CHashMap<ProposalID, Proposal>,
     ui_state: CHashMap<MachineID, PlanManagerUIState>,
+    replaying: bool,
 }
 
 impl PlanManager {
@@ -352,6 +365,7 @@ impl PlanManager {
                 .collect(),
             implemented_proposals: CHashMap::new(),
             ui_state: CHashMap::new(),
+            replaying: false,
         }
     }
 
@@ -379,17 +393,71 @@ impl PlanManager {
             .expect("Expected gesture (that point should be added to) to exist!")
     }
 
-    pub fn implement(&mut self, proposal_id: ProposalID, world: &mut World) {
+    fn cost_of(&self, proposal_id: ProposalID) -> ResourceAmount {
         let proposal = self
             .proposals
-            .remove(proposal_id)
+            .get(proposal_id)
             .expect("Proposal should exist");
 
+        match proposal.apply_to(&self.master_plan).calculate_result() {
+            Ok(result) => {
+                construction_cost(&self.master_result, &self.master_result.actions_to(&result))
+            }
+            // the error is reported once we actually try to implement it
+            Err(_) => 0.0,
+        }
+    }
+
+    pub fn implement(&mut self, proposal_id: ProposalID, world: &mut World) {
+        // while replaying, implemented proposals come from the journal instead
+        if self.replaying {
+            return;
+        }
+
+        let cost = self.cost_of(proposal_id);
+
+        if cost > 0.0 {
+            Treasury::global_first(world).request_construction_funds(
+                self.id,
+                proposal_id,
+                cost,
+                world,
+            );
+        } else {
+            self.implement_funded(proposal_id, 0.0, world);
+        }
+    }
+
+    // The funds were granted for the cost estimated when the proposal was submitted,
+    // but the master plan might have changed since, so the actual cost is settled here
+    pub fn implement_funded(
+        &mut self,
+        proposal_id: ProposalID,
+        funds: ResourceAmount,
+        world: &mut World,
+    ) {
+        let proposal = match self.proposals.remove(proposal_id) {
+            Some(proposal) => proposal,
+            None => {
+                // already implemented while we were waiting for funds
+                Treasury::global_first(world).refund(funds, world);
+                return;
+            }
+        };
+
+        Journal::global_first(world).record(JournalInput::Implement(proposal.clone()), world);
+
         self.master_plan = proposal.apply_to(&self.master_plan);
 
         match self.master_plan.calculate_result() {
             Ok(result) => {
                 let actions = self.master_result.actions_to(&result);
+                let cost = construction_cost(&self.master_result, &actions);
+                if cost >= funds {
+                    Treasury::global_first(world).charge(cost - funds, world);
+                } else {
+                    Treasury::global_first(world).refund(funds - cost, world);
+                }
                 Construction::global_first(world).implement(actions, world);
                 self.implemented_proposals.insert(proposal_id, proposal);
                 self.master_result = result;
@@ -418,14 +486,42 @@ impl PlanManager {
                     }
                 }
             }
-            Err(err) => match err {
-                ::descartes::AreaError::LeftOver(string) => {
-                    println!("Implement Plan Error: {}", string);
-                }
-                _ => {
-                    println!("Implement Plan Error: {:?}", err);
+            Err(err) => {
+                Treasury::global_first(world).refund(funds, world);
+
+                match err {
+                    ::descartes::AreaError::LeftOver(string) => {
+                        println!("Implement Plan Error: {}", string);
+                    }
+                    _ => {
+                        println!("Implement Plan Error: {:?}", err);
+                    }
                 }
-            },
+            }
+        }
+    }
+
+    pub fn report_master_to(&mut self, requester: PersistenceID, world: &mut World) {
+        requester.on_master(self.master_plan.clone(), self.master_result.clone(), world);
+    }
+
+    pub fn restore_master(
+        &mut self,
+        master_plan: &PlanHistory,
+        master_result: &PlanResult,
+        requester: PersistenceID,
+        world: &mut World,
+    ) {
+        let actions = self.master_result.actions_to(master_result);
+        Construction::global_first(world).implement(actions, world);
+        Construction::global_first(world).report_done_to(requester, world);
+        self.master_plan = master_plan.clone();
+        self.master_result = master_result.clone();
+
+        let all_proposal_ids = self.proposals.keys().cloned().collect::<Vec<_>>();
+        for proposal_id in all_proposal_ids {
+            self.clear_previews(proposal_id);
+            self.recreate_gesture_interactables(proposal_id, world);
         }
     }
 
@@ -435,12 +531,30 @@ impl PlanManager {
         based_on: StepID,
         world: &mut World,
     ) {
+        // while replaying, implemented proposals come from the journal instead
+        if self.replaying {
+            return;
+        }
+
         if based_on == self.master_plan.latest_step_id() {
             let proposal_id = ProposalID::new();
             self.proposals.insert(proposal_id, proposal.clone());
             self.implement(proposal_id, world);
         }
     }
+
+    pub fn set_replaying(&mut self, replaying: bool, _: &mut World) {
+        self.replaying = replaying;
+    }
+
+    // for proposals that come from outside the city, like replayed journals or
+    // scenario files, so they don't have to be based on the latest step.
+    // These are paid for even if the treasury can't afford them.
+    pub fn implement_proposal(&mut self, proposal: &Proposal, world: &mut World) {
+        let proposal_id = ProposalID::new();
+        self.proposals.insert(proposal_id, proposal.clone());
+        self.implement_funded(proposal_id, 0.0, world);
+    }
 }
 
 pub fn setup(system: &mut ActorSystem) {
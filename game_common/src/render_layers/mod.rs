@@ -1,9 +1,18 @@
+#[derive(Copy, Clone)]
 #[repr(u32)]
 pub enum RenderLayers {
     PlanningLotResidentialArea = 100_000_000,
+    PlanningLotCommercialArea,
+    PlanningLotIndustrialArea,
+    PlanningLotAgriculturalArea,
+    PlanningLotRecreationalArea,
+    PlanningLotOfficialArea,
+    LandValueHeatmap = 150_000_000,
     PlanningLotVacantOutline = 180_000_000,
     PlanningLotOccupiedOutline = 190_000_000,
 
+    Sidewalk = 195_000_000,
+
     LaneAsphalt = 200_000_000,
     LaneMarker = 210_000_000,
     LaneMarkerGaps = 220_000_000,
@@ -13,6 +22,8 @@ pub enum RenderLayers {
     PlanningIntersection = 320_000_000,
 
     Car = 400_000_000,
+    DeliveryTruck,
+    Bus,
     TrafficLightBox = 450_000_000,
     TrafficLightLight,
     TrafficLightLightLeft,
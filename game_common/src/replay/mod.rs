@@ -0,0 +1,341 @@
+use kay::{ActorSystem, World, Actor};
+use compact::{CVec, CString};
+use fnv::FnvHasher;
+use std::hash::{Hash, Hasher};
+use std::fs::File;
+
+use simulation::{SimulationID, Simulatable, SimulatableID, Instant, Ticks};
+use planning::{PlanManagerID, Proposal};
+use transport::lane::{Lane, SwitchLane};
+use transport::microtraffic::LaneCar;
+use land_use::buildings::Building;
+
+pub const JOURNAL_VERSION: u32 = 1;
+pub const DEFAULT_JOURNAL_PATH: &str = "citybound_journal.json";
+const CHECKSUM_EVERY_N_TICKS: usize = 300;
+
+#[derive(Compact, Clone, Serialize, Deserialize)]
+pub enum JournalInput {
+    Implement(Proposal),
+    SetSpeed(i32),
+}
+
+// Inputs are stamped with the instant at which the next simulation
+// progress starts, since that is when they take effect
+#[derive(Compact, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub ticks: usize,
+    pub input: JournalInput,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct JournalFile {
+    pub version: u32,
+    pub entries: Vec<JournalEntry>,
+    pub checksums: Vec<(usize, u64)>,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum JournalMode {
+    Off,
+    Recording,
+    Replaying,
+}
+
+impl JournalMode {
+    pub fn from_args<I: Iterator<Item = String>>(mut args: I) -> (JournalMode, String) {
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--record" => {
+                    let path = args
+                        .next()
+                        .unwrap_or_else(|| DEFAULT_JOURNAL_PATH.to_owned());
+                    return (JournalMode::Recording, path);
+                }
+                "--replay" => {
+                    let path = args
+                        .next()
+                        .unwrap_or_else(|| DEFAULT_JOURNAL_PATH.to_owned());
+                    return (JournalMode::Replaying, path);
+                }
+                _ => {}
+            }
+        }
+        (JournalMode::Off, DEFAULT_JOURNAL_PATH.to_owned())
+    }
+}
+
+#[derive(Compact, Clone)]
+pub struct Journal {
+    id: JournalID,
+    simulation: SimulationID,
+    plan_manager: PlanManagerID,
+    mode: JournalMode,
+    path: CString,
+    next_instant: Instant,
+    entries: CVec<JournalEntry>,
+    next_entry_idx: usize,
+    checksums: CVec<(Instant, u64)>,
+    pending_checksum: Option<(Instant, u64)>,
+    dirty: bool,
+}
+
+impl Journal {
+    pub fn spawn(
+        id: JournalID,
+        simulation: SimulationID,
+        plan_manager: PlanManagerID,
+        mode: JournalMode,
+        path: &CString,
+        world: &mut World,
+    ) -> Journal {
+        let (entries, checksums) = if mode == JournalMode::Replaying {
+            match File::open(&**path)
+                .map_err(|err| format!("{}", err))
+                .and_then(|file| {
+                    ::serde_json::from_reader::<_, JournalFile>(file)
+                        .map_err(|err| format!("{}", err))
+                })
+                .and_then(|journal_file| {
+                    if journal_file.version == JOURNAL_VERSION {
+                        Ok(journal_file)
+                    } else {
+                        Err(format!(
+                            "version {}, expected {}",
+                            journal_file.version, JOURNAL_VERSION
+                        ))
+                    }
+                }) {
+                Ok(journal_file) => {
+                    println!(
+                        "Replaying {} inputs from {}",
+                        journal_file.entries.len(),
+                        &**path
+                    );
+                    plan_manager.set_replaying(true, world);
+                    (
+                        journal_file.entries.into(),
+                        journal_file
+                            .checksums
+                            .into_iter()
+                            .map(|(ticks, checksum)| (Instant::new(ticks), checksum))
+                            .collect(),
+                    )
+                }
+                Err(err) => {
+                    println!("Error loading journal from {}: {}", &**path, err);
+                    (CVec::new(), CVec::new())
+                }
+            }
+        } else {
+            (CVec::new(), CVec::new())
+        };
+
+        let mut journal = Journal {
+            id,
+            simulation,
+            plan_manager,
+            mode,
+            path: path.clone(),
+            next_instant: Instant::new(0),
+            entries,
+            next_entry_idx: 0,
+            checksums,
+            pending_checksum: None,
+            dirty: false,
+        };
+
+        if mode == JournalMode::Replaying {
+            // inputs that happened before the very first tick
+            journal.replay_inputs_up_to(Instant::new(0), world);
+        }
+
+        journal
+    }
+
+    pub fn record(&mut self, input: &JournalInput, _: &mut World) {
+        if self.mode == JournalMode::Recording {
+            self.entries.push(JournalEntry {
+                ticks: self.next_instant.ticks(),
+                input: input.clone(),
+            });
+            // inputs are rare, but can't be recomputed, so they are written right away
+            self.write();
+        }
+    }
+
+    pub fn on_checksum_part(&mut self, instant: Instant, part: u64, _: &mut World) {
+        if let Some(ref mut pending) = self.pending_checksum {
+            if pending.0 == instant {
+                pending.1 = pending.1.wrapping_add(part);
+            }
+        }
+    }
+
+    fn replay_inputs_up_to(&mut self, next_instant: Instant, world: &mut World) {
+        while let Some(entry) = self.entries.get(self.next_entry_idx).cloned() {
+            if entry.ticks > next_instant.ticks() {
+                break;
+            }
+
+            match entry.input {
+                JournalInput::Implement(ref proposal) => {
+                    self.plan_manager.implement_proposal(proposal.clone(), world);
+                }
+                JournalInput::SetSpeed(speed) => {
+                    self.simulation.set_speed(speed, world);
+                }
+            }
+
+            self.next_entry_idx += 1;
+        }
+    }
+
+    fn finish_checksum(&mut self) {
+        if let Some((instant, checksum)) = self.pending_checksum.take() {
+            match self.mode {
+                JournalMode::Recording => {
+                    self.checksums.push((instant, checksum));
+                    self.dirty = true;
+                }
+                JournalMode::Replaying => {
+                    if let Some(&(_, recorded)) =
+                        self.checksums.iter().find(|&&(at, _)| at == instant)
+                    {
+                        if recorded != checksum {
+                            println!(
+                                "Replay diverged at tick {}: {:x} vs. recorded {:x}",
+                                instant.ticks(),
+                                checksum,
+                                recorded
+                            );
+                        }
+                    }
+                }
+                JournalMode::Off => {}
+            }
+        }
+    }
+
+    fn write(&mut self) {
+        let journal_file = JournalFile {
+            version: JOURNAL_VERSION,
+            entries: self.entries.to_vec(),
+            checksums: self
+                .checksums
+                .iter()
+                .map(|&(instant, checksum)| (instant.ticks(), checksum))
+                .collect(),
+        };
+
+        if let Err(err) = File::create(&*self.path)
+            .map_err(|err| format!("{}", err))
+            .and_then(|file| {
+                ::serde_json::to_writer(file, &journal_file).map_err(|err| format!("{}", err))
+            })
+        {
+            println!("Error writing journal to {}: {}", &*self.path, err);
+        }
+
+        self.dirty = false;
+    }
+}
+
+impl Simulatable for Journal {
+    fn tick(&mut self, _dt: f32, current_instant: Instant, world: &mut World) {
+        if self.mode == JournalMode::Off {
+            return;
+        }
+
+        self.next_instant = current_instant + Ticks(1);
+
+        if self.mode == JournalMode::Replaying {
+            // sent now, these are handled before the next progress, like live inputs
+            let next_instant = self.next_instant;
+            self.replay_inputs_up_to(next_instant, world);
+        }
+
+        if current_instant.ticks() % CHECKSUM_EVERY_N_TICKS == 0 {
+            // by now all parts of the previous checksum have arrived
+            self.finish_checksum();
+            self.pending_checksum = Some((current_instant, 0));
+            Lane::global_broadcast(world).add_to_checksum(current_instant, self.id, world);
+            SwitchLane::global_broadcast(world).add_to_checksum(current_instant, self.id, world);
+            Building::global_broadcast(world).add_households_to_checksum(
+                current_instant,
+                self.id,
+                world,
+            );
+
+            if self.dirty && self.mode == JournalMode::Recording {
+                self.write();
+            }
+        }
+    }
+}
+
+fn car_checksum(car: &LaneCar, hasher: &mut FnvHasher) {
+    car.position.to_bits().hash(hasher);
+    car.velocity.to_bits().hash(hasher);
+    car.acceleration.to_bits().hash(hasher);
+}
+
+impl Lane {
+    pub fn add_to_checksum(&mut self, instant: Instant, journal: JournalID, world: &mut World) {
+        let mut hasher = FnvHasher::default();
+        self.microtraffic.cars.len().hash(&mut hasher);
+        for car in &self.microtraffic.cars {
+            car_checksum(car, &mut hasher);
+        }
+        journal.on_checksum_part(instant, hasher.finish(), world);
+    }
+}
+
+impl SwitchLane {
+    pub fn add_to_checksum(&mut self, instant: Instant, journal: JournalID, world: &mut World) {
+        let mut hasher = FnvHasher::default();
+        self.microtraffic.cars.len().hash(&mut hasher);
+        for car in &self.microtraffic.cars {
+            car_checksum(car, &mut hasher);
+            car.switch_position.to_bits().hash(&mut hasher);
+        }
+        journal.on_checksum_part(instant, hasher.finish(), world);
+    }
+}
+
+impl Building {
+    pub fn add_households_to_checksum(
+        &mut self,
+        instant: Instant,
+        journal: JournalID,
+        world: &mut World,
+    ) {
+        for household in &self.all_households() {
+            household.add_to_checksum(instant, journal, world);
+        }
+    }
+}
+
+pub fn setup(system: &mut ActorSystem) {
+    system.register::<Journal>();
+    auto_setup(system);
+}
+
+pub fn spawn(
+    world: &mut World,
+    simulation: SimulationID,
+    plan_manager: PlanManagerID,
+    mode: JournalMode,
+    path: &str,
+) -> JournalID {
+    JournalID::spawn(
+        simulation,
+        plan_manager,
+        mode,
+        path.to_owned().into(),
+        world,
+    )
+}
+
+mod kay_auto;
+pub use self::kay_auto::*;
@@ -0,0 +1,199 @@
+use kay::{ActorSystem, World, Actor};
+use compact::{CVec, CString};
+use descartes::P2;
+use std::fs::File;
+
+use simulation::{Duration, Ticks};
+use planning::{PlanManagerID, Proposal, Plan, GestureID, Gesture, GestureIntent};
+use transport::transport_planning::RoadIntent;
+use transport::lane::{Lane, SwitchLane};
+use land_use::zone_planning::{ZoneIntent, LandUse};
+use land_use::buildings::{Building, BuildingStyle};
+use economy::immigration_and_development::{HouseholdTypeToSpawn, ImmigrationManager,
+DevelopmentManager};
+
+#[derive(Serialize, Deserialize)]
+pub struct ScenarioRoad {
+    pub points: Vec<P2>,
+    pub intent: RoadIntent,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ScenarioZone {
+    pub points: Vec<P2>,
+    pub land_use: LandUse,
+}
+
+// Buildings and households are given as counts - buildings are developed
+// on fitting vacant lots and households immigrate into fitting units,
+// developing more buildings if needed. No random households immigrate.
+#[derive(Serialize, Deserialize)]
+pub struct Scenario {
+    pub roads: Vec<ScenarioRoad>,
+    #[serde(default)]
+    pub zones: Vec<ScenarioZone>,
+    #[serde(default)]
+    pub buildings: Vec<(BuildingStyle, usize)>,
+    #[serde(default)]
+    pub households: Vec<(HouseholdTypeToSpawn, usize)>,
+    pub duration_minutes: usize,
+}
+
+impl Scenario {
+    pub fn load(path: &str) -> Result<Scenario, String> {
+        File::open(path)
+            .map_err(|err| format!("{}", err))
+            .and_then(|file| ::serde_json::from_reader(file).map_err(|err| format!("{}", err)))
+    }
+
+    pub fn duration_ticks(&self) -> usize {
+        Ticks::from(Duration::from_minutes(self.duration_minutes)).0
+    }
+
+    pub fn to_proposal(&self) -> Proposal {
+        let road_gestures = self
+            .roads
+            .iter()
+            .map(|road| Gesture::new(road.points.clone().into(), GestureIntent::Road(road.intent)));
+
+        let zone_gestures = self.zones.iter().map(|zone| {
+            Gesture::new(
+                zone.points.clone().into(),
+                GestureIntent::Zone(ZoneIntent::LandUse(zone.land_use)),
+            )
+        });
+
+        Proposal::from_plan(Plan::from_gestures(
+            road_gestures
+                .chain(zone_gestures)
+                .map(|gesture| (GestureID::new(), gesture)),
+        ))
+    }
+
+    pub fn load_into(&self, plan_manager: PlanManagerID, world: &mut World) {
+        plan_manager.implement_proposal(self.to_proposal(), world);
+
+        let building_styles: CVec<BuildingStyle> = self
+            .buildings
+            .iter()
+            .flat_map(|&(building_style, count)| (0..count).map(move |_| building_style))
+            .collect();
+        DevelopmentManager::global_first(world).queue_development(building_styles, world);
+
+        let household_types: CVec<HouseholdTypeToSpawn> = self
+            .households
+            .iter()
+            .flat_map(|&(household_type, count)| (0..count).map(move |_| household_type))
+            .collect();
+        ImmigrationManager::global_first(world).request_households(household_types, world);
+    }
+}
+
+#[derive(Copy, Clone, Default, Serialize, Deserialize)]
+pub struct ScenarioMetrics {
+    pub ticks: usize,
+    pub lanes: usize,
+    pub cars: usize,
+    pub mean_car_velocity: f32,
+    pub buildings: usize,
+    pub households: usize,
+}
+
+#[derive(Compact, Clone)]
+pub struct ScenarioRunner {
+    id: ScenarioRunnerID,
+    metrics_path: CString,
+    metrics: ScenarioMetrics,
+    total_car_velocity: f32,
+}
+
+impl ScenarioRunner {
+    pub fn spawn(id: ScenarioRunnerID, metrics_path: &CString, _: &mut World) -> ScenarioRunner {
+        ScenarioRunner {
+            id,
+            metrics_path: metrics_path.clone(),
+            metrics: ScenarioMetrics::default(),
+            total_car_velocity: 0.0,
+        }
+    }
+
+    pub fn collect_metrics(&mut self, ticks: usize, world: &mut World) {
+        self.metrics = ScenarioMetrics {
+            ticks,
+            ..ScenarioMetrics::default()
+        };
+        self.total_car_velocity = 0.0;
+
+        Lane::global_broadcast(world).add_to_metrics(self.id, world);
+        SwitchLane::global_broadcast(world).add_to_metrics(self.id, world);
+        Building::global_broadcast(world).add_to_metrics(self.id, world);
+    }
+
+    pub fn on_lane_metrics(&mut self, cars: usize, total_velocity: f32, _: &mut World) {
+        self.metrics.lanes += 1;
+        self.metrics.cars += cars;
+        self.total_car_velocity += total_velocity;
+    }
+
+    pub fn on_building_metrics(&mut self, households: usize, _: &mut World) {
+        self.metrics.buildings += 1;
+        self.metrics.households += households;
+    }
+
+    // Metrics arrive through broadcasts, so this should only be called
+    // once all messages sent by `collect_metrics` have been handled
+    pub fn write_metrics(&mut self, _: &mut World) {
+        if self.metrics.cars > 0 {
+            self.metrics.mean_car_velocity = self.total_car_velocity / self.metrics.cars as f32;
+        }
+
+        let result = if self.metrics_path.is_empty() {
+            ::serde_json::to_string_pretty(&self.metrics)
+                .map(|json| println!("{}", json))
+                .map_err(|err| format!("{}", err))
+        } else {
+            File::create(&*self.metrics_path)
+                .map_err(|err| format!("{}", err))
+                .and_then(|file| {
+                    ::serde_json::to_writer_pretty(file, &self.metrics)
+                        .map_err(|err| format!("{}", err))
+                })
+        };
+
+        if let Err(err) = result {
+            println!("Error writing scenario metrics: {}", err);
+        }
+    }
+}
+
+impl Lane {
+    pub fn add_to_metrics(&mut self, runner: ScenarioRunnerID, world: &mut World) {
+        let total_velocity: f32 = self.microtraffic.cars.iter().map(|car| car.velocity).sum();
+        runner.on_lane_metrics(self.microtraffic.cars.len(), total_velocity, world);
+    }
+}
+
+impl SwitchLane {
+    pub fn add_to_metrics(&mut self, runner: ScenarioRunnerID, world: &mut World) {
+        let total_velocity: f32 = self.microtraffic.cars.iter().map(|car| car.velocity).sum();
+        runner.on_lane_metrics(self.microtraffic.cars.len(), total_velocity, world);
+    }
+}
+
+impl Building {
+    pub fn add_to_metrics(&mut self, runner: ScenarioRunnerID, world: &mut World) {
+        runner.on_building_metrics(self.all_households().len(), world);
+    }
+}
+
+pub fn setup(system: &mut ActorSystem) {
+    system.register::<ScenarioRunner>();
+    auto_setup(system);
+}
+
+pub fn spawn(world: &mut World, metrics_path: &str) -> ScenarioRunnerID {
+    ScenarioRunnerID::spawn(metrics_path.to_owned().into(), world)
+}
+
+mod kay_auto;
+pub use self::kay_auto::*;
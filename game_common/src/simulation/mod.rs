@@ -1,6 +1,8 @@
 use kay::{ActorSystem, World, Actor};
 use compact::CVec;
 use stagemaster::UserInterfaceID;
+use persistence::PersistenceID;
+use replay::{Journal, JournalInput};
 
 mod time;
 
@@ -75,6 +77,23 @@ impl Simulation {
         self.sleepers.insert(insert_idx, (wake_up_at, sleeper_id));
     }
 
+    pub fn report_instant_to(&mut self, requester: PersistenceID, world: &mut World) {
+        requester.on_instant(self.current_instant, world);
+    }
+
+    pub fn restore_instant(&mut self, instant: Instant, _: &mut World) {
+        // keep already scheduled sleepers at the same distance from now
+        let offset = instant.ticks() as isize - self.current_instant.iticks();
+        for sleeper in self.sleepers.iter_mut() {
+            sleeper.0 = Instant::new((sleeper.0.iticks() + offset).max(0) as usize);
+        }
+        self.current_instant = instant;
+    }
+
+    pub fn set_speed(&mut self, speed: i32, _: &mut World) {
+        self.speed = speed;
+    }
+
     pub fn add_to_ui(&mut self, ui_id: &UserInterfaceID, world: &mut World) {
         ui_id.add_2d(self.id_as(), world);
     }
@@ -84,7 +103,7 @@ use stagemaster::{Interactable2d, Interactable2dID};
 
 impl Interactable2d for Simulation {
     #[cfg(feature = "server")]
-    fn draw(&mut self, _world: &mut World, ui: &::imgui::Ui<'static>) {
+    fn draw(&mut self, world: &mut World, ui: &::imgui::Ui<'static>) {
         let time = TimeOfDay::from(self.current_instant).hours_minutes();
 
         ui.window(im_str!("Simulation")).build(|| {
@@ -92,14 +111,17 @@ impl Interactable2d for Simulation {
             ui.spacing();
             ui.text(im_str!("Simulation Speed"));
             ui.same_line(130.0);
-            let _ = ui
+            if ui
                 .slider_int(im_str!("##simulation-speed"), &mut self.speed, 0, 30)
-                .build();
+                .build()
+            {
+                Journal::global_first(world).record(JournalInput::SetSpeed(self.speed), world);
+            }
             ui.spacing();
         });
     }
 
-    #[cfg(feature = "browser")]
+    #[cfg(not(feature = "server"))]
     fn draw(&mut self, _world: &mut World, ui: &()) {}
 }
 
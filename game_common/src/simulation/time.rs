@@ -221,6 +221,12 @@ impl TimeOfDayRange {
         }
     }
 
+    pub fn length(&self) -> Duration {
+        let start = self.start.minutes_of_day as usize;
+        let end = self.end.minutes_of_day as usize;
+        Duration::from_minutes((end + MINUTES_PER_DAY - start) % MINUTES_PER_DAY)
+    }
+
     pub fn end_after_on_same_day(&self, time: TimeOfDay) -> bool {
         if self.end > self.start {
             time < self.end
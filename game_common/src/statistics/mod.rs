@@ -0,0 +1,423 @@
+use kay::{ActorSystem, World, Actor};
+use compact::{CVec, CString};
+use stagemaster::{UserInterfaceID, Interactable2d, Interactable2dID};
+#[cfg(feature = "server")]
+use imgui::ImGuiSetCond_FirstUseEver;
+use std::fs::File;
+use std::io::Write;
+
+use simulation::{Simulatable, SimulatableID, Instant, TICKS_PER_SIM_MINUTE};
+use transport::lane::{Lane, SwitchLane};
+use transport::pathfinding::trip::TripFate;
+use land_use::buildings::Building;
+use construction::Construction;
+use economy::resources::{Resource, ResourceAmount};
+use economy::market::Market;
+
+pub const DEFAULT_STATISTICS_PATH: &str = "citybound_statistics.csv";
+const SAMPLE_EVERY_N_TICKS: usize = TICKS_PER_SIM_MINUTE;
+const EXPORT_EVERY_N_SAMPLES: usize = 60;
+
+#[derive(Copy, Clone, Default, Serialize, Deserialize)]
+pub struct TripOutcomes {
+    pub success: usize,
+    pub source_or_destination_not_resolvable: usize,
+    pub no_route: usize,
+    pub route_forgotten: usize,
+    pub hop_disconnected: usize,
+    pub lane_unbuilt: usize,
+    pub force_stopped: usize,
+    pub no_parking: usize,
+}
+
+impl TripOutcomes {
+    pub fn count(&mut self, fate: TripFate) {
+        match fate {
+            TripFate::Success(_) => self.success += 1,
+            TripFate::SourceOrDestinationNotResolvable => {
+                self.source_or_destination_not_resolvable += 1
+            }
+            TripFate::NoRoute => self.no_route += 1,
+            TripFate::RouteForgotten => self.route_forgotten += 1,
+            TripFate::HopDisconnected => self.hop_disconnected += 1,
+            TripFate::LaneUnbuilt => self.lane_unbuilt += 1,
+            TripFate::ForceStopped => self.force_stopped += 1,
+            TripFate::NoParking => self.no_parking += 1,
+        }
+    }
+}
+
+// Lanes are identified by their instance id, switch lanes separately from normal ones
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct LaneCars {
+    pub lane: u32,
+    pub switch_lane: bool,
+    pub cars: usize,
+}
+
+// Trip outcomes are counted since the previous sample,
+// everything else is the state at the time of the sample
+#[derive(Compact, Clone, Serialize, Deserialize)]
+pub struct StatisticsSample {
+    pub ticks: usize,
+    pub lanes: usize,
+    pub cars: usize,
+    pub max_cars_per_lane: usize,
+    // only lanes that had cars on them
+    pub cars_per_lane: CVec<LaneCars>,
+    pub trips: TripOutcomes,
+    pub households: usize,
+    pub employed: usize,
+    pub unemployed: usize,
+    pub vacancies: usize,
+    pub resources: CVec<(Resource, ResourceAmount)>,
+    pub queued_construction_groups: usize,
+    pub pending_constructables: usize,
+    pub prices: CVec<(Resource, ResourceAmount)>,
+}
+
+impl StatisticsSample {
+    pub fn new(ticks: usize) -> StatisticsSample {
+        StatisticsSample {
+            ticks,
+            lanes: 0,
+            cars: 0,
+            max_cars_per_lane: 0,
+            cars_per_lane: CVec::new(),
+            trips: TripOutcomes::default(),
+            households: 0,
+            employed: 0,
+            unemployed: 0,
+            vacancies: 0,
+            resources: CVec::new(),
+            queued_construction_groups: 0,
+            pending_constructables: 0,
+            prices: CVec::new(),
+        }
+    }
+}
+
+#[derive(Compact, Clone)]
+pub struct Statistics {
+    id: StatisticsID,
+    export_path: CString,
+    current: StatisticsSample,
+    samples: CVec<StatisticsSample>,
+}
+
+impl Statistics {
+    pub fn spawn(id: StatisticsID, export_path: &CString, _: &mut World) -> Statistics {
+        Statistics {
+            id,
+            export_path: export_path.clone(),
+            current: StatisticsSample::new(0),
+            samples: CVec::new(),
+        }
+    }
+
+    pub fn on_trip_finished(&mut self, fate: TripFate, _: &mut World) {
+        self.current.trips.count(fate);
+    }
+
+    pub fn on_lane_cars(&mut self, lane_cars: LaneCars, _: &mut World) {
+        self.current.lanes += 1;
+        self.current.cars += lane_cars.cars;
+        self.current.max_cars_per_lane = self.current.max_cars_per_lane.max(lane_cars.cars);
+        if lane_cars.cars > 0 {
+            self.current.cars_per_lane.push(lane_cars);
+        }
+    }
+
+    pub fn on_household_resources(
+        &mut self,
+        resources: &CVec<(Resource, ResourceAmount)>,
+        _: &mut World,
+    ) {
+        self.current.households += 1;
+        for &(resource, amount) in resources {
+            if let Some(total) = self
+                .current
+                .resources
+                .iter_mut()
+                .find(|total| total.0 == resource)
+            {
+                total.1 += amount;
+                continue;
+            }
+            self.current.resources.push((resource, amount));
+        }
+    }
+
+    pub fn on_household_employment(
+        &mut self,
+        employed: usize,
+        unemployed: usize,
+        vacancies: usize,
+        _: &mut World,
+    ) {
+        self.current.employed += employed;
+        self.current.unemployed += unemployed;
+        self.current.vacancies += vacancies;
+    }
+
+    pub fn on_construction_queue(
+        &mut self,
+        queued_groups: usize,
+        pending_constructables: usize,
+        _: &mut World,
+    ) {
+        self.current.queued_construction_groups = queued_groups;
+        self.current.pending_constructables = pending_constructables;
+    }
+
+    pub fn on_market_prices(&mut self, prices: &CVec<(Resource, ResourceAmount)>, _: &mut World) {
+        self.current.prices = prices.clone();
+    }
+
+    pub fn export(&mut self, _: &mut World) {
+        if self.export_path.is_empty() {
+            return;
+        }
+
+        let result = if self.export_path.ends_with(".csv") {
+            File::create(&*self.export_path)
+                .and_then(|mut file| write_csv(&mut file, &self.samples))
+                .map_err(|err| format!("{}", err))
+        } else {
+            File::create(&*self.export_path)
+                .map_err(|err| format!("{}", err))
+                .and_then(|file| {
+                    ::serde_json::to_writer(file, &self.samples).map_err(|err| format!("{}", err))
+                })
+        };
+
+        if let Err(err) = result {
+            println!(
+                "Error exporting statistics to {}: {}",
+                &*self.export_path, err
+            );
+        }
+    }
+}
+
+fn write_csv<W: Write>(out: &mut W, samples: &[StatisticsSample]) -> ::std::io::Result<()> {
+    let mut resources: Vec<Resource> = samples
+        .iter()
+        .flat_map(|sample| sample.resources.iter().map(|&(resource, _)| resource))
+        .collect();
+    resources.sort();
+    resources.dedup();
+
+    let mut priced_resources: Vec<Resource> = samples
+        .iter()
+        .flat_map(|sample| sample.prices.iter().map(|&(resource, _)| resource))
+        .collect();
+    priced_resources.sort();
+    priced_resources.dedup();
+
+    let mut lanes: Vec<(bool, u32)> = samples
+        .iter()
+        .flat_map(|sample| {
+            sample
+                .cars_per_lane
+                .iter()
+                .map(|lane_cars| (lane_cars.switch_lane, lane_cars.lane))
+        })
+        .collect();
+    lanes.sort();
+    lanes.dedup();
+
+    write!(
+        out,
+        "ticks,lanes,cars,max_cars_per_lane,trips_success,\
+         trips_source_or_destination_not_resolvable,trips_no_route,trips_route_forgotten,\
+         trips_hop_disconnected,trips_lane_unbuilt,trips_force_stopped,trips_no_parking,\
+         households,employed,unemployed,vacancies,queued_construction_groups,\
+         pending_constructables"
+    )?;
+    for resource in &resources {
+        write!(out, ",{}", resource)?;
+    }
+    for resource in &priced_resources {
+        write!(out, ",price_{}", resource)?;
+    }
+    for &(switch_lane, lane) in &lanes {
+        if switch_lane {
+            write!(out, ",cars_switch_lane_{}", lane)?;
+        } else {
+            write!(out, ",cars_lane_{}", lane)?;
+        }
+    }
+    writeln!(out)?;
+
+    for sample in samples {
+        write!(
+            out,
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            sample.ticks,
+            sample.lanes,
+            sample.cars,
+            sample.max_cars_per_lane,
+            sample.trips.success,
+            sample.trips.source_or_destination_not_resolvable,
+            sample.trips.no_route,
+            sample.trips.route_forgotten,
+            sample.trips.hop_disconnected,
+            sample.trips.lane_unbuilt,
+            sample.trips.force_stopped,
+            sample.trips.no_parking,
+            sample.households,
+            sample.employed,
+            sample.unemployed,
+            sample.vacancies,
+            sample.queued_construction_groups,
+            sample.pending_constructables
+        )?;
+        for resource in &resources {
+            let amount = sample
+                .resources
+                .iter()
+                .find(|&&(r, _)| r == *resource)
+                .map(|&(_, amount)| amount)
+                .unwrap_or(0.0);
+            write!(out, ",{}", amount)?;
+        }
+        for resource in &priced_resources {
+            match sample.prices.iter().find(|&&(r, _)| r == *resource) {
+                Some(&(_, price)) => write!(out, ",{}", price)?,
+                None => write!(out, ",")?,
+            }
+        }
+        for &(switch_lane, lane) in &lanes {
+            let cars = sample
+                .cars_per_lane
+                .iter()
+                .find(|lane_cars| lane_cars.switch_lane == switch_lane && lane_cars.lane == lane)
+                .map(|lane_cars| lane_cars.cars)
+                .unwrap_or(0);
+            write!(out, ",{}", cars)?;
+        }
+        writeln!(out)?;
+    }
+
+    Ok(())
+}
+
+impl Simulatable for Statistics {
+    fn tick(&mut self, _dt: f32, current_instant: Instant, world: &mut World) {
+        if current_instant.ticks() % SAMPLE_EVERY_N_TICKS == 0 {
+            // by now all reports for the previous sample have arrived
+            if current_instant.ticks() > 0 {
+                self.samples.push(self.current.clone());
+
+                if self.samples.len() % EXPORT_EVERY_N_SAMPLES == 0 {
+                    self.export(world);
+                }
+            }
+
+            self.current = StatisticsSample::new(current_instant.ticks());
+
+            Lane::global_broadcast(world).add_to_statistics(self.id, world);
+            SwitchLane::global_broadcast(world).add_to_statistics(self.id, world);
+            Building::global_broadcast(world).add_households_to_statistics(self.id, world);
+            Construction::global_first(world).report_queue_to(self.id, world);
+            Market::global_first(world).report_prices_to(self.id, world);
+        }
+    }
+}
+
+impl Lane {
+    pub fn add_to_statistics(&mut self, statistics: StatisticsID, world: &mut World) {
+        let lane_cars = LaneCars {
+            lane: self.id.as_raw().instance_id as u32,
+            switch_lane: false,
+            cars: self.microtraffic.cars.len(),
+        };
+        statistics.on_lane_cars(lane_cars, world);
+    }
+}
+
+impl SwitchLane {
+    pub fn add_to_statistics(&mut self, statistics: StatisticsID, world: &mut World) {
+        let lane_cars = LaneCars {
+            lane: self.id.as_raw().instance_id as u32,
+            switch_lane: true,
+            cars: self.microtraffic.cars.len(),
+        };
+        statistics.on_lane_cars(lane_cars, world);
+    }
+}
+
+impl Building {
+    pub fn add_households_to_statistics(&mut self, statistics: StatisticsID, world: &mut World) {
+        for household in &self.all_households() {
+            household.add_to_statistics(statistics, world);
+        }
+    }
+}
+
+impl Interactable2d for Statistics {
+    #[cfg(feature = "server")]
+    fn draw(&mut self, world: &mut World, ui: &::imgui::Ui<'static>) {
+        let can_export = !self.export_path.is_empty();
+
+        ui.window(im_str!("Statistics"))
+            .size((250.0, 200.0), ImGuiSetCond_FirstUseEver)
+            .collapsible(true)
+            .build(|| {
+                if let Some(sample) = self.samples.last() {
+                    ui.text(im_str!("Cars: {} on {} lanes", sample.cars, sample.lanes));
+                    ui.text(im_str!("Busiest lane: {} cars", sample.max_cars_per_lane));
+                    ui.text(im_str!(
+                        "Trips: {} ok, {} failed",
+                        sample.trips.success,
+                        sample.trips.source_or_destination_not_resolvable
+                            + sample.trips.no_route
+                            + sample.trips.route_forgotten
+                            + sample.trips.hop_disconnected
+                            + sample.trips.lane_unbuilt
+                            + sample.trips.no_parking
+                    ));
+                    ui.text(im_str!("Households: {}", sample.households));
+                    ui.text(im_str!(
+                        "Jobs: {} employed, {} unemployed, {} vacancies",
+                        sample.employed,
+                        sample.unemployed,
+                        sample.vacancies
+                    ));
+                    ui.text(im_str!(
+                        "Construction: {} queued, {} pending",
+                        sample.queued_construction_groups,
+                        sample.pending_constructables
+                    ));
+                } else {
+                    ui.text(im_str!("No samples yet"));
+                }
+
+                if can_export && ui.small_button(im_str!("Export")) {
+                    self.id.export(world);
+                }
+            });
+    }
+
+    #[cfg(not(feature = "server"))]
+    fn draw(&mut self, world: &mut World, ui: &()) {}
+}
+
+impl Statistics {
+    pub fn add_to_ui(&mut self, user_interface: UserInterfaceID, world: &mut World) {
+        user_interface.add_2d(self.id_as(), world);
+    }
+}
+
+pub fn setup(system: &mut ActorSystem) {
+    system.register::<Statistics>();
+    auto_setup(system);
+}
+
+pub fn spawn(world: &mut World, export_path: &str) -> StatisticsID {
+    StatisticsID::spawn(export_path.to_owned().into(), world)
+}
+
+mod kay_auto;
+pub use self::kay_auto::*;
@@ -21,6 +21,7 @@ pub const GESTURE_LINES: [f32; 3] = [0.3, 0.3, 0.3];
 
 pub const GRASS: (f32, f32, f32, f32) = (0.6, 0.75, 0.4, 1.0);
 pub const ASPHALT: [f32; 3] = [0.7, 0.7, 0.7];
+pub const SIDEWALK: [f32; 3] = [0.85, 0.84, 0.8];
 pub const ROAD_MARKER: [f32; 3] = [1.0, 1.0, 1.0];
 pub const WALL: [f32; 3] = [0.95, 0.95, 0.95];
 pub const FLAT_ROOF: [f32; 3] = [0.5, 0.5, 0.5];
@@ -36,6 +37,15 @@ pub const AGRICULTURAL: [f32; 3] = [0.93, 0.68, 0.14];
 pub const RECREATIONAL: [f32; 3] = [0.17, 0.26, 0.14];
 pub const OFFICIAL: [f32; 3] = [0.15, 0.11, 0.72];
 
+// from low to high land value
+pub const LAND_VALUE_HEATMAP: [[f32; 3]; 5] = [
+    [0.20, 0.30, 0.80],
+    [0.20, 0.65, 0.80],
+    [0.30, 0.80, 0.30],
+    [0.95, 0.80, 0.20],
+    [0.90, 0.25, 0.15],
+];
+
 pub const LOT_VACANT: [f32; 3] = [0.3, 0.3, 0.3];
 pub const LOT_OCCUPIED: [f32; 3] = [0.1, 0.1, 0.1];
 
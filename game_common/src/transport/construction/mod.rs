@@ -5,13 +5,15 @@ use itertools::Itertools;
 use ordered_float::OrderedFloat;
 
 use super::lane::{Lane, LaneID, SwitchLane, SwitchLaneID};
-use super::lane::connectivity::{Interaction, InteractionKind, OverlapKind};
+use super::lane::connectivity::{Interaction, InteractionKind, OverlapKind, LanePriority};
 use super::microtraffic::LaneLikeID;
 
-use planning::Prototype;
+use planning::{Prototype, PrototypeKind};
 use construction::{ConstructionID, Constructable, ConstructableID};
 use super::transport_planning::{RoadPrototype, LanePrototype, SwitchLanePrototype,
-IntersectionPrototype};
+IntersectionPrototype, SidewalkPrototype, RightOfWay};
+use super::pedestrians::SidewalkID;
+use super::signal_control::{SignalControllerID, ControlledLane};
 
 use style::dimensions::{LANE_CONNECTION_TOLERANCE, MAX_SWITCHING_LANE_DISTANCE,
 MIN_SWITCHING_LANE_LENGTH};
@@ -19,33 +21,49 @@ MIN_SWITCHING_LANE_LENGTH};
 impl RoadPrototype {
     pub fn construct(&self, report_to: ConstructionID, world: &mut World) -> CVec<ConstructableID> {
         match *self {
-            RoadPrototype::Lane(LanePrototype(ref path, _)) => vec![
-                LaneID::spawn_and_connect(path.clone(), false, CVec::new(), report_to, world)
-                    .into(),
+            RoadPrototype::Lane(LanePrototype(ref path, _, curbside, speed_limit)) => vec![
+                LaneID::spawn_and_connect(
+                    path.clone(),
+                    false,
+                    curbside,
+                    LanePriority::Major,
+                    speed_limit,
+                    report_to,
+                    world,
+                ).into(),
             ].into(),
             RoadPrototype::SwitchLane(SwitchLanePrototype(ref path)) => {
                 vec![SwitchLaneID::spawn_and_connect(path.clone(), report_to, world).into()].into()
             }
-            RoadPrototype::Intersection(IntersectionPrototype {
-                ref connecting_lanes,
-                ..
-            }) => {
-                let ids = connecting_lanes
-                    .values()
-                    .flat_map(|group| {
+            RoadPrototype::Intersection(ref intersection) => {
+                let controlled_lanes = intersection
+                    .connecting_lanes
+                    .pairs()
+                    .flat_map(|(&(incoming, _), group)| {
+                        let priority = intersection.lane_priority(incoming);
                         group
                             .iter()
-                            .map(|&LanePrototype(ref path, ref timings)| {
-                                LaneID::spawn_and_connect(
-                                    path.clone(),
-                                    true,
+                            .map(|&LanePrototype(ref path, ref timings, _, speed_limit)| {
+                                ControlledLane::new(
+                                    LaneID::spawn_and_connect(
+                                        path.clone(),
+                                        true,
+                                        false,
+                                        priority,
+                                        speed_limit,
+                                        report_to,
+                                        world,
+                                    ),
                                     timings.clone(),
-                                    report_to,
-                                    world,
                                 )
                             })
                             .collect::<Vec<_>>()
                     })
+                    .collect::<CVec<_>>();
+
+                let ids = controlled_lanes
+                    .iter()
+                    .map(|controlled| controlled.lane)
                     .collect::<Vec<_>>();
 
                 for id in &ids {
@@ -55,7 +73,26 @@ impl RoadPrototype {
                     )
                 }
 
-                ids.into_iter().map(|lane_id| lane_id.into()).collect()
+                let maybe_signal_controller = if intersection.right_of_way == RightOfWay::Signals {
+                    let signal_controller = SignalControllerID::spawn(
+                        intersection.area.clone(),
+                        intersection.signal_program.clone(),
+                        controlled_lanes,
+                        world,
+                    ).into();
+                    report_to.action_done(signal_controller, world);
+                    Some(signal_controller)
+                } else {
+                    None
+                };
+
+                ids.into_iter()
+                    .map(|lane_id| lane_id.into())
+                    .chain(maybe_signal_controller)
+                    .collect()
+            }
+            RoadPrototype::Sidewalk(SidewalkPrototype(ref path)) => {
+                vec![SidewalkID::spawn_and_connect(path.clone(), report_to, world).into()].into()
             }
             RoadPrototype::PavedArea(_) => CVec::new(),
         }
@@ -63,7 +100,30 @@ impl RoadPrototype {
 }
 
 impl Constructable for Lane {
-    fn morph(&mut self, _new_prototype: &Prototype, report_to: ConstructionID, world: &mut World) {
+    fn morph(&mut self, new_prototype: &Prototype, report_to: ConstructionID, world: &mut World) {
+        match new_prototype.kind {
+            PrototypeKind::Road(RoadPrototype::Lane(LanePrototype(_, _, _, speed_limit))) => {
+                self.microtraffic.speed_limit = speed_limit;
+            }
+            // intersection lanes let their signal controller know in which phases they are green now
+            PrototypeKind::Road(RoadPrototype::Intersection(IntersectionPrototype {
+                ref connecting_lanes,
+                ..
+            })) => {
+                if let Some(&LanePrototype(_, ref timings, _, speed_limit)) = connecting_lanes
+                    .values()
+                    .flat_map(|lanes| lanes.iter())
+                    .find(|&&LanePrototype(ref path, ..)| {
+                        path.rough_eq_by(&self.construction.path, 0.05)
+                    }) {
+                    self.microtraffic.speed_limit = speed_limit;
+                    if let Some(signal_controller) = self.microtraffic.signal_controller {
+                        signal_controller.update_lane_timings(self.id, timings.clone(), world);
+                    }
+                }
+            }
+            _ => {}
+        }
         report_to.action_done(self.id_as(), world);
     }
     fn destruct(&mut self, report_to: ConstructionID, world: &mut World) -> Fate {
@@ -117,11 +177,14 @@ thread_local!(
 );
 
 impl Lane {
+    #[cfg_attr(feature = "cargo-clippy", allow(too_many_arguments))]
     pub fn spawn_and_connect(
         id: LaneID,
         path: &LinePath,
         on_intersection: bool,
-        timings: &CVec<bool>,
+        curbside: bool,
+        priority: LanePriority,
+        speed_limit: N,
         report_to: ConstructionID,
         world: &mut World,
     ) -> Lane {
@@ -137,12 +200,26 @@ impl Lane {
             SwitchLane::global_broadcast(world).connect_switch_to_normal(id, path.clone(), world);
         }
         report_to.action_done(id.into(), world);
-        Lane::spawn(id, path, on_intersection, timings, world)
+        Lane::spawn(
+            id,
+            path,
+            on_intersection,
+            curbside,
+            priority,
+            speed_limit,
+            world,
+        )
     }
 
     pub fn start_connecting_overlaps(&mut self, lanes: &CVec<LaneID>, world: &mut World) {
         for &lane_id in lanes.iter() {
-            lane_id.connect_overlaps(self.id, self.construction.path.clone(), true, world);
+            lane_id.connect_overlaps(
+                self.id,
+                self.construction.path.clone(),
+                self.connectivity.priority,
+                true,
+                world,
+            );
         }
     }
 
@@ -179,7 +256,10 @@ impl Lane {
                     partner_lane: other_id.into(),
                     start: self.construction.length,
                     partner_start: 0.0,
-                    kind: InteractionKind::Next { green: false },
+                    kind: InteractionKind::Next {
+                        green: false,
+                        stop: false,
+                    },
                 });
             }
 
@@ -228,6 +308,7 @@ impl Lane {
         &mut self,
         other_id: LaneID,
         other_path: &LinePath,
+        other_priority: LanePriority,
         reply_needed: bool,
         world: &mut World,
     ) {
@@ -292,7 +373,9 @@ impl Lane {
                         //     [1.0, 0.0, 0.0],
                         //     0.3
                         // );
-                        OverlapKind::Conflicting
+                        OverlapKind::Conflicting {
+                            partner_priority: other_priority,
+                        }
                     };
 
                     self.connectivity.interactions.push(Interaction {
@@ -311,7 +394,13 @@ impl Lane {
             }
 
             if reply_needed {
-                other_id.connect_overlaps(self.id, self.construction.path.clone(), false, world);
+                other_id.connect_overlaps(
+                    self.id,
+                    self.construction.path.clone(),
+                    self.connectivity.priority,
+                    false,
+                    world,
+                );
             }
         });
     }
@@ -445,6 +534,7 @@ impl Lane {
 }
 
 use land_use::buildings::{BuildingID, MIN_LANE_BUILDING_DISTANCE};
+use land_use::land_value::{LandValueEstimatorID, LOT_ACCESS_MAX_DISTANCE};
 use transport::pathfinding::PreciseLocation;
 
 impl Lane {
@@ -477,6 +567,23 @@ impl Lane {
             }
         }
     }
+
+    pub fn report_lot_access(
+        &mut self,
+        estimator: LandValueEstimatorID,
+        connection_point: P2,
+        world: &mut World,
+    ) {
+        if let Some(location) = self.pathfinding.location {
+            if !self.connectivity.on_intersection
+                && self.construction.path.distance_to(connection_point) < LOT_ACCESS_MAX_DISTANCE
+            {
+                let congestion = 1.0
+                    - (self.microtraffic.average_speed / self.microtraffic.speed_limit).min(1.0);
+                estimator.on_access(location, congestion, world);
+            }
+        }
+    }
 }
 
 impl SwitchLane {
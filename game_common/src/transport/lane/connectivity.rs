@@ -6,17 +6,30 @@ use super::LaneID;
 pub struct ConnectivityInfo {
     pub interactions: CVec<Interaction>,
     pub on_intersection: bool,
+    pub priority: LanePriority,
 }
 
 impl ConnectivityInfo {
-    pub fn new(on_intersection: bool) -> Self {
+    pub fn new(on_intersection: bool, priority: LanePriority) -> Self {
         ConnectivityInfo {
             interactions: CVec::new(),
             on_intersection,
+            priority,
         }
     }
 }
 
+// Who goes first where two lanes cross, ordered from least to most important.
+// Lanes with the same priority treat each other first come, first serve.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum LanePriority {
+    Stop,
+    Yield,
+    Major,
+    // crossings are resolved by the signals instead
+    Signalized,
+}
+
 use super::super::microtraffic::LaneLikeID;
 
 #[derive(Compact, Clone, Default)]
@@ -44,6 +57,8 @@ pub enum InteractionKind {
     },
     Next {
         green: bool,
+        // the next lane has a stop sign
+        stop: bool,
     },
     Previous,
 }
@@ -52,5 +67,5 @@ pub enum InteractionKind {
 pub enum OverlapKind {
     Parallel,
     Transfer,
-    Conflicting,
+    Conflicting { partner_priority: LanePriority },
 }
@@ -1,12 +1,13 @@
-use compact::{CVec, COption};
+use compact::COption;
 use kay::{ActorSystem, World, Actor};
 use descartes::{N, Band, LinePath};
 
 use super::construction::ConstructionInfo;
 pub mod connectivity;
-use self::connectivity::{ConnectivityInfo, TransferConnectivityInfo};
+use self::connectivity::{ConnectivityInfo, TransferConnectivityInfo, LanePriority};
 use super::microtraffic::{Microtraffic, TransferringMicrotraffic};
 use super::pathfinding::PathfindingInfo;
+use super::parking::{ParkingCapacity, curb_parking_for_lane};
 use stagemaster::{UserInterface, Event3d, Interactable3d, Interactable3dID};
 
 #[derive(Compact, Clone)]
@@ -16,6 +17,7 @@ pub struct Lane {
     pub connectivity: ConnectivityInfo,
     pub microtraffic: Microtraffic,
     pub pathfinding: PathfindingInfo,
+    pub parking: ParkingCapacity,
 }
 
 impl Lane {
@@ -24,15 +26,18 @@ impl Lane {
         id: LaneID,
         path: &LinePath,
         on_intersection: bool,
-        timings: &CVec<bool>,
+        curbside: bool,
+        priority: LanePriority,
+        speed_limit: N,
         world: &mut World,
     ) -> Self {
         let lane = Lane {
             id,
             construction: ConstructionInfo::from_path(path.clone()),
-            connectivity: ConnectivityInfo::new(on_intersection),
-            microtraffic: Microtraffic::new(timings.clone()),
+            connectivity: ConnectivityInfo::new(on_intersection, priority),
+            microtraffic: Microtraffic::new(priority == LanePriority::Signalized, speed_limit),
             pathfinding: PathfindingInfo::default(),
+            parking: curb_parking_for_lane(path.length(), on_intersection, curbside),
         };
 
         super::rendering::on_build(&lane, world);
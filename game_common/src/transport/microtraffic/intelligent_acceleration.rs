@@ -1,22 +1,39 @@
-use super::Obstacle;
+use super::{LaneCar, Obstacle, VehicleType};
 
 pub const COMFORTABLE_BREAKING_DECELERATION: f32 = 0.4;
 
 pub fn intelligent_acceleration(
+    car: &LaneCar,
+    obstacle: &Obstacle,
+    safe_time_headway: f32,
+    speed_limit: f32,
+) -> f32 {
+    vehicle_acceleration(
+        car.vehicle,
+        &car.as_obstacle,
+        obstacle,
+        safe_time_headway,
+        speed_limit,
+    )
+}
+
+pub fn vehicle_acceleration(
+    vehicle: VehicleType,
     car: &Obstacle,
     obstacle: &Obstacle,
     safe_time_headway: f32,
+    speed_limit: f32,
 ) -> f32 {
     // http://en.wikipedia.org/wiki/Intelligent_driver_model
 
-    let car_length = 4.0;
-    let acceleration = 0.4;
+    let acceleration = vehicle.acceleration();
     let max_deceleration: f32 = 5.0;
-    let desired_velocity = car.max_velocity;
+    let desired_velocity = car.max_velocity.min(speed_limit);
     let acceleration_exponent = 4.0;
     let minimum_spacing = 4.0;
 
-    let net_distance = *obstacle.position - *car.position - car_length;
+    // positions are in the middle of vehicles
+    let net_distance = *obstacle.position - *car.position - (obstacle.length + car.length) / 2.0;
     let velocity_difference = car.velocity - obstacle.velocity;
 
     let s_star = minimum_spacing
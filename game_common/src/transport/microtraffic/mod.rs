@@ -5,11 +5,18 @@ use std::f32::INFINITY;
 use std::ops::{Deref, DerefMut};
 
 use super::lane::{Lane, LaneID, SwitchLane, SwitchLaneID};
-use super::lane::connectivity::{Interaction, InteractionKind, OverlapKind};
+use super::lane::connectivity::{Interaction, InteractionKind, OverlapKind, LanePriority};
 use super::pathfinding;
+use super::parking::{ParkingCapacity, ParkingSpot, MAX_PARKING_SEARCH_LANES};
+use super::signal_control::{SignalControllerID, DETECTOR_DISTANCE, WAITING_VELOCITY};
 
 mod intelligent_acceleration;
 use self::intelligent_acceleration::intelligent_acceleration;
+#[cfg(test)]
+use self::intelligent_acceleration::vehicle_acceleration;
+
+pub mod vehicle;
+pub use self::vehicle::VehicleType;
 
 // TODO: move all iteration, updates, etc into one huge retain loop (see identical TODO below)
 
@@ -17,25 +24,50 @@ use self::intelligent_acceleration::intelligent_acceleration;
 pub struct Microtraffic {
     pub obstacles: CVec<(Obstacle, LaneLikeID)>,
     pub cars: CVec<LaneCar>,
-    timings: CVec<bool>,
+    // set for lanes on signalized intersections
+    pub signal_controller: Option<SignalControllerID>,
     pub green: bool,
     pub yellow_to_green: bool,
     pub yellow_to_red: bool,
+    // in m/s, cars don't go faster even if they could
+    pub speed_limit: f32,
+    // of the cars on this lane, slowly follows changes in traffic
+    pub average_speed: f32,
+    // how long cars wait at the signal of this lane on average, set by its signal controller
+    pub signal_delay_secs: f32,
 }
 
 impl Microtraffic {
-    pub fn new(timings: CVec<bool>) -> Self {
+    // signalized lanes stay red until their signal controller decides otherwise
+    pub fn new(signalized: bool, speed_limit: f32) -> Self {
         Microtraffic {
             obstacles: CVec::new(),
             cars: CVec::new(),
-            timings,
-            green: false,
-            yellow_to_green: false,
-            yellow_to_red: false,
+            signal_controller: None,
+            green: !signalized,
+            yellow_to_green: !signalized,
+            yellow_to_red: true,
+            speed_limit,
+            average_speed: speed_limit,
+            signal_delay_secs: 0.0,
         }
     }
 }
 
+// cars stop in front of stop signs as if there was a standing car 2m behind the line,
+// which, with the minimum spacing kept, leaves their front about 2m before the line
+const STOP_OBSTACLE_OFFSET: f32 = 2.0;
+// cars standing with their front this close to the stop line have stopped at it
+const STOP_LINE_RELEASE_DISTANCE: f32 = 3.0;
+const STOPPED_VELOCITY: f32 = 0.5;
+// how long cars stand at the stop line before they go
+const STOP_SIGN_DWELL_SECS: usize = 2;
+// how far ahead cars with priority are seen by cars that have to yield to them
+const PRIORITY_GAP_SECS: f32 = 4.0;
+
+// how much each traffic update moves the average speed of a lane towards the current one
+const SPEED_AVERAGING_FACTOR: f32 = 0.05;
+
 // makes "time pass slower" for traffic, so we can still use realistic
 // unit values while traffic happening at a slower pace to be visible
 const MICROTRAFFIC_UNREALISTIC_SLOWDOWN: f32 = 1.0;
@@ -49,9 +81,12 @@ pub struct TransferringMicrotraffic {
 
 #[derive(Copy, Clone)]
 pub struct Obstacle {
+    // of the middle of the vehicle
     pub position: OrderedFloat<f32>,
     pub velocity: f32,
     pub max_velocity: f32,
+    // zero for obstacles that are just a line, like the end of a red light
+    pub length: f32,
 }
 
 impl Obstacle {
@@ -60,6 +95,7 @@ impl Obstacle {
             position: OrderedFloat(INFINITY),
             velocity: INFINITY,
             max_velocity: INFINITY,
+            length: 0.0,
         }
     }
     fn far_behind() -> Obstacle {
@@ -67,6 +103,7 @@ impl Obstacle {
             position: OrderedFloat(-INFINITY),
             velocity: 0.0,
             max_velocity: 20.0,
+            length: 0.0,
         }
     }
     fn offset_by(&self, delta: f32) -> Obstacle {
@@ -83,10 +120,22 @@ use super::pathfinding::Node;
 #[derive(Copy, Clone)]
 pub struct LaneCar {
     pub trip: TripID,
+    pub vehicle: VehicleType,
     pub as_obstacle: Obstacle,
     pub acceleration: f32,
     pub destination: pathfinding::PreciseLocation,
     pub next_hop_interaction: Option<u8>,
+    pub parking: CarParking,
+    // when the car came to a stop at the stop sign at the end of its current lane
+    pub stopped_at_line_since: Option<Instant>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum CarParking {
+    Reserved,
+    Needed,
+    // how many more lanes to try after the current one
+    Searching(u8),
 }
 
 impl LaneCar {
@@ -98,6 +147,35 @@ impl LaneCar {
     }
 }
 
+fn stop_obstacle(stop_line: f32) -> Obstacle {
+    Obstacle {
+        position: OrderedFloat(stop_line + STOP_OBSTACLE_OFFSET),
+        velocity: 0.0,
+        max_velocity: 0.0,
+        length: 0.0,
+    }
+}
+
+// Cars have to wait at a stop sign until they stood still right at the line
+// for a moment. Returns whether the car still has to wait and since when it stands there
+fn stop_sign_wait(
+    car: &Obstacle,
+    stop_line: f32,
+    stopped_since: Option<Instant>,
+    current_instant: Instant,
+) -> (bool, Option<Instant>) {
+    let at_line = *car.position + car.length / 2.0 >= stop_line - STOP_LINE_RELEASE_DISTANCE;
+    let stopped_since = match stopped_since {
+        None if at_line && car.velocity < STOPPED_VELOCITY => Some(current_instant),
+        stopped_since => stopped_since,
+    };
+    let released = stopped_since.map_or(false, |since| {
+        current_instant.ticks().saturating_sub(since.ticks())
+            >= STOP_SIGN_DWELL_SECS * TICKS_PER_SIM_SECOND
+    });
+    (!released, stopped_since)
+}
+
 impl Deref for LaneCar {
     type Target = Obstacle;
 
@@ -135,7 +213,7 @@ impl DerefMut for TransferringLaneCar {
     }
 }
 
-use simulation::Instant;
+use simulation::{Instant, TICKS_PER_SIM_SECOND};
 
 pub trait LaneLike {
     fn add_car(
@@ -163,17 +241,36 @@ impl LaneLike for Lane {
         instant: Instant,
         world: &mut World,
     ) {
+        if let CarParking::Searching(_) = car.parking {
+            if let Some(searching_car) = park_or_search(
+                car,
+                self.id,
+                &mut self.parking,
+                &self.connectivity.interactions,
+                self.connectivity.on_intersection,
+                instant,
+                world,
+            ) {
+                self.insert_car(searching_car);
+            }
+            return;
+        }
+
         if let Some(self_as_location) = self.pathfinding.location {
             if car.destination.location == self_as_location
                 && *car.position >= car.destination.offset
             {
-                car.trip.finish(
-                    TripResult {
-                        location_now: None,
-                        fate: TripFate::Success(instant),
-                    },
+                if let Some(searching_car) = park_or_search(
+                    car,
+                    self.id,
+                    &mut self.parking,
+                    &self.connectivity.interactions,
+                    self.connectivity.on_intersection,
+                    instant,
                     world,
-                );
+                ) {
+                    self.insert_car(searching_car);
+                }
 
                 return;
             }
@@ -211,19 +308,14 @@ impl LaneLike for Lane {
         if maybe_next_hop_interaction.is_some() || almost_there {
             let routed_car = LaneCar {
                 next_hop_interaction: maybe_next_hop_interaction.map(|hop| hop as u8),
+                stopped_at_line_since: None,
                 ..car
             };
 
-            // TODO: optimize using BinaryHeap?
-            let maybe_next_car_position =
-                self.microtraffic.cars.iter().position(|other_car| {
-                    other_car.as_obstacle.position > car.as_obstacle.position
-                });
-            match maybe_next_car_position {
-                Some(next_car_position) => {
-                    self.microtraffic.cars.insert(next_car_position, routed_car)
-                }
-                None => self.microtraffic.cars.push(routed_car),
+            self.insert_car(routed_car);
+
+            if let Some(signal_controller) = self.microtraffic.signal_controller {
+                signal_controller.car_passed(world);
             }
         } else {
             car.trip.finish(
@@ -247,12 +339,32 @@ impl LaneLike for Lane {
 }
 
 impl Lane {
-    pub fn on_signal_changed(&mut self, from: LaneLikeID, green: bool, _: &mut World) {
-        if let Some(interaction) = self
+    fn insert_car(&mut self, car: LaneCar) {
+        // TODO: optimize using BinaryHeap?
+        let maybe_next_car_position = self
+            .microtraffic
+            .cars
+            .iter()
+            .position(|other_car| other_car.as_obstacle.position > car.as_obstacle.position);
+        match maybe_next_car_position {
+            Some(next_car_position) => self.microtraffic.cars.insert(next_car_position, car),
+            None => self.microtraffic.cars.push(car),
+        }
+    }
+
+    pub fn on_signal_changed(
+        &mut self,
+        from: LaneLikeID,
+        green: bool,
+        stop: bool,
+        signal_controller: Option<SignalControllerID>,
+        world: &mut World,
+    ) {
+        if let Some(interaction_idx) = self
             .connectivity
             .interactions
-            .iter_mut()
-            .find(|interaction| match **interaction {
+            .iter()
+            .position(|interaction| match *interaction {
                 Interaction {
                     partner_lane,
                     kind: InteractionKind::Next { .. },
@@ -260,11 +372,73 @@ impl Lane {
                 } => partner_lane == from,
                 _ => false,
             }) {
-            interaction.kind = InteractionKind::Next { green }
+            self.connectivity.interactions[interaction_idx].kind =
+                InteractionKind::Next { green, stop };
+
+            if let Some(signal_controller) = signal_controller {
+                let detector_start = self.construction.length - DETECTOR_DISTANCE;
+                let (demand, waiting) = self.microtraffic
+                    .cars
+                    .iter()
+                    .filter(|car| {
+                        car.next_hop_interaction == Some(interaction_idx as u8)
+                            && *car.position > detector_start
+                    })
+                    .fold((0, 0), |(demand, waiting), car| {
+                        (
+                            demand + 1,
+                            waiting + if car.velocity < WAITING_VELOCITY { 1 } else { 0 },
+                        )
+                    });
+                signal_controller.report_approach(
+                    unsafe { LaneID::from_raw(from.as_raw()) },
+                    demand,
+                    waiting,
+                    world,
+                );
+            }
         } else {
             println!("Lane doesn't know about next lane yet");
         }
     }
+
+    pub fn set_signal_controller(&mut self, signal_controller: SignalControllerID, _: &mut World) {
+        self.microtraffic.signal_controller = Some(signal_controller);
+    }
+
+    pub fn set_signal_delay(&mut self, delay_secs: f32, _: &mut World) {
+        self.microtraffic.signal_delay_secs = delay_secs;
+    }
+
+    pub fn set_signal(&mut self, green: bool, green_soon: bool, world: &mut World) {
+        let changed = green != self.microtraffic.green;
+        self.microtraffic.green = green;
+        self.microtraffic.yellow_to_green = green_soon;
+        self.microtraffic.yellow_to_red = !green_soon;
+
+        if changed {
+            self.broadcast_signal(world);
+        }
+    }
+
+    fn broadcast_signal(&self, world: &mut World) {
+        for interaction in &self.connectivity.interactions {
+            if let Interaction {
+                kind: InteractionKind::Previous { .. },
+                partner_lane,
+                ..
+            } = *interaction
+            {
+                unsafe { LaneID::from_raw(partner_lane.as_raw()) }.on_signal_changed(
+                    self.id_as(),
+                    self.microtraffic.green,
+                    self.connectivity.priority == LanePriority::Stop,
+                    self.microtraffic.signal_controller,
+                    world,
+                );
+            }
+        }
+    }
 }
 
 impl Simulatable for Lane {
@@ -276,42 +450,10 @@ impl Simulatable for Lane {
         let do_traffic = current_instant.ticks() % TRAFFIC_LOGIC_THROTTLING
             == self.id.as_raw().instance_id as usize % TRAFFIC_LOGIC_THROTTLING;
 
-        let old_green = self.microtraffic.green;
-        self.microtraffic.yellow_to_red = if self.microtraffic.timings.is_empty() {
-            true
-        } else {
-            !self.microtraffic.timings
-                [((current_instant.ticks() + 100) / 30) % self.microtraffic.timings.len()]
-        };
-        self.microtraffic.yellow_to_green = if self.microtraffic.timings.is_empty() {
-            true
-        } else {
-            self.microtraffic.timings
-                [((current_instant.ticks() + 100) / 30) % self.microtraffic.timings.len()]
-        };
-        self.microtraffic.green = if self.microtraffic.timings.is_empty() {
-            true
-        } else {
-            self.microtraffic.timings
-                [(current_instant.ticks() / 30) % self.microtraffic.timings.len()]
-        };
-
         // TODO: this is just a hacky way to update new lanes about existing lane's green
-        if old_green != self.microtraffic.green || do_traffic {
-            for interaction in &self.connectivity.interactions {
-                if let Interaction {
-                    kind: InteractionKind::Previous { .. },
-                    partner_lane,
-                    ..
-                } = *interaction
-                {
-                    unsafe { LaneID::from_raw(partner_lane.as_raw()) }.on_signal_changed(
-                        self.id_as(),
-                        self.microtraffic.green,
-                        world,
-                    );
-                }
-            }
+        // (it also lets incoming lanes report their queues to the signal controller)
+        if do_traffic {
+            self.broadcast_signal(world);
         }
 
         if current_instant.ticks() % PATHFINDING_THROTTLING
@@ -321,6 +463,20 @@ impl Simulatable for Lane {
         }
 
         if do_traffic {
+            // empty lanes can be driven at the speed limit
+            let current_speed = if self.microtraffic.cars.is_empty() {
+                self.microtraffic.speed_limit
+            } else {
+                self.microtraffic
+                    .cars
+                    .iter()
+                    .map(|car| car.velocity)
+                    .sum::<f32>()
+                    / self.microtraffic.cars.len() as f32
+            };
+            self.microtraffic.average_speed +=
+                SPEED_AVERAGING_FACTOR * (current_speed - self.microtraffic.average_speed);
+
             // TODO: optimize using BinaryHeap?
             self.microtraffic
                 .obstacles
@@ -332,6 +488,7 @@ impl Simulatable for Lane {
                 .iter()
                 .map(|&(ref obstacle, _id)| obstacle);
             let mut maybe_next_obstacle = obstacles.next();
+            let speed_limit = self.microtraffic.speed_limit;
 
             for c in 0..self.microtraffic.cars.len() {
                 let next_obstacle = self
@@ -340,7 +497,8 @@ impl Simulatable for Lane {
                     .get(c + 1)
                     .map_or(Obstacle::far_ahead(), |car| car.as_obstacle);
                 let car = &mut self.microtraffic.cars[c];
-                let next_car_acceleration = intelligent_acceleration(car, &next_obstacle, 2.0);
+                let next_car_acceleration =
+                    intelligent_acceleration(car, &next_obstacle, 2.0, speed_limit);
 
                 maybe_next_obstacle = maybe_next_obstacle.and_then(|obstacle| {
                     let mut following_obstacle = Some(obstacle);
@@ -353,7 +511,7 @@ impl Simulatable for Lane {
                 });
 
                 let next_obstacle_acceleration = if let Some(next_obstacle) = maybe_next_obstacle {
-                    intelligent_acceleration(car, next_obstacle, 3.0)
+                    intelligent_acceleration(car, next_obstacle, 3.0, speed_limit)
                 } else {
                     INFINITY
                 };
@@ -363,19 +521,28 @@ impl Simulatable for Lane {
                 if let Some(next_hop_interaction) = car.next_hop_interaction {
                     if let Interaction {
                         start,
-                        kind: InteractionKind::Next { green },
+                        kind: InteractionKind::Next { green, stop },
                         ..
                     } = self.connectivity.interactions[next_hop_interaction as usize]
                     {
-                        if !green {
+                        let waiting_at_stop_sign = if stop {
+                            let (waiting, stopped_since) = stop_sign_wait(
+                                &car.as_obstacle,
+                                start,
+                                car.stopped_at_line_since,
+                                current_instant,
+                            );
+                            car.stopped_at_line_since = stopped_since;
+                            waiting
+                        } else {
+                            false
+                        };
+                        if !green || waiting_at_stop_sign {
                             car.acceleration = car.acceleration.min(intelligent_acceleration(
                                 car,
-                                &Obstacle {
-                                    position: OrderedFloat(start + 2.0),
-                                    velocity: 0.0,
-                                    max_velocity: 0.0,
-                                },
+                                &stop_obstacle(start),
                                 2.0,
+                                speed_limit,
                             ))
                         }
                     }
@@ -406,23 +573,47 @@ impl Simulatable for Lane {
         // TODO: move all iteration, updates, etc into one huge retain loop
 
         if let Some(self_as_location) = self.pathfinding.location {
-            self.microtraffic.cars.retain(|car| {
-                if car.destination.location == self_as_location
-                    && *car.position >= car.destination.offset
-                {
-                    car.trip.finish(
-                        TripResult {
-                            location_now: None,
-                            fate: TripFate::Success(current_instant),
-                        },
-                        world,
-                    );
-
-                    false
-                } else {
-                    true
-                }
-            });
+            let mut searching_cars = Vec::new();
+
+            {
+                let lane_id = self.id;
+                let parking = &mut self.parking;
+                let interactions = &self.connectivity.interactions;
+                let on_intersection = self.connectivity.on_intersection;
+
+                self.microtraffic.cars.retain(|car| {
+                    let searching = if let CarParking::Searching(_) = car.parking {
+                        true
+                    } else {
+                        false
+                    };
+
+                    if !searching
+                        && car.destination.location == self_as_location
+                        && *car.position >= car.destination.offset
+                    {
+                        if let Some(searching_car) = park_or_search(
+                            *car,
+                            lane_id,
+                            parking,
+                            interactions,
+                            on_intersection,
+                            current_instant,
+                            world,
+                        ) {
+                            searching_cars.push(searching_car);
+                        }
+
+                        false
+                    } else {
+                        true
+                    }
+                });
+            }
+
+            for searching_car in searching_cars {
+                self.insert_car(searching_car);
+            }
         }
 
         loop {
@@ -496,6 +687,7 @@ impl Simulatable for Lane {
             {
                 let maybe_obstacles = obstacles_for_interaction(
                     interaction,
+                    self.connectivity.priority,
                     cars,
                     self.microtraffic.obstacles.iter(),
                 );
@@ -510,6 +702,82 @@ impl Simulatable for Lane {
     }
 }
 
+// Cars with a reserved space just arrive, all others try to park at the curb
+// or continue on a pseudo-random next lane, until they give up
+fn park_or_search(
+    car: LaneCar,
+    lane: LaneID,
+    parking: &mut ParkingCapacity,
+    interactions: &CVec<Interaction>,
+    on_intersection: bool,
+    instant: Instant,
+    world: &mut World,
+) -> Option<LaneCar> {
+    let lanes_left = match car.parking {
+        CarParking::Reserved => {
+            car.trip.finish(
+                TripResult {
+                    location_now: None,
+                    fate: TripFate::Success(instant),
+                },
+                world,
+            );
+            return None;
+        }
+        CarParking::Needed => MAX_PARKING_SEARCH_LANES,
+        CarParking::Searching(lanes_left) => lanes_left,
+    };
+
+    if !on_intersection {
+        if parking.try_occupy() {
+            car.trip.parked(ParkingSpot::Curb(lane), instant, world);
+            return None;
+        } else if lanes_left == 0 {
+            car.trip.finish(
+                TripResult {
+                    location_now: Some(lane.into()),
+                    fate: TripFate::NoParking,
+                },
+                world,
+            );
+            return None;
+        }
+    }
+
+    let next_lanes = interactions
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, interaction)| match interaction.kind {
+            InteractionKind::Next { .. } => Some(idx as u8),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    if next_lanes.is_empty() {
+        car.trip.finish(
+            TripResult {
+                location_now: Some(lane.into()),
+                fate: TripFate::NoParking,
+            },
+            world,
+        );
+        None
+    } else {
+        let pseudo_random_idx =
+            (car.trip.as_raw().instance_id as usize + lanes_left as usize) % next_lanes.len();
+
+        Some(LaneCar {
+            next_hop_interaction: Some(next_lanes[pseudo_random_idx]),
+            parking: CarParking::Searching(if on_intersection {
+                lanes_left
+            } else {
+                lanes_left - 1
+            }),
+            ..car
+        })
+    }
+}
+
 impl LaneLike for SwitchLane {
     fn add_car(
         &mut self,
@@ -626,7 +894,10 @@ impl Simulatable for SwitchLane {
                                 dangerous = true;
                                 None
                             } else {
-                                Some(OrderedFloat(intelligent_acceleration(car, obstacle, 1.0)))
+                                // switch lanes have no speed limit of their own
+                                Some(OrderedFloat(intelligent_acceleration(
+                                    car, obstacle, 1.0, INFINITY,
+                                )))
                             }
                         })
                         .min()
@@ -781,6 +1052,7 @@ pub fn setup(system: &mut ActorSystem) {
 
 fn obstacles_for_interaction(
     interaction: &Interaction,
+    priority: LanePriority,
     mut cars: ::std::slice::Iter<LaneCar>,
     self_obstacles_iter: ::std::slice::Iter<(Obstacle, LaneLikeID)>,
 ) -> Option<CVec<Obstacle>> {
@@ -808,15 +1080,26 @@ fn obstacles_for_interaction(
                     }
                 }))
                 .collect(),
-            OverlapKind::Conflicting => {
+            OverlapKind::Conflicting { partner_priority } => {
+                // cars that entered the overlap always block it, but the partner
+                // also has to leave a big enough gap to cars that have priority
+                let lookahead_secs = if priority > partner_priority {
+                    PRIORITY_GAP_SECS
+                } else if priority == partner_priority {
+                    2.0
+                } else {
+                    0.0
+                };
                 let in_overlap = |car: &LaneCar| {
-                    *car.position + 2.0 * car.velocity > start && *car.position - 2.0 < end
+                    *car.position + lookahead_secs * car.velocity > start
+                        && *car.position - 2.0 < end
                 };
                 if cars.any(in_overlap) {
                     vec![Obstacle {
                         position: OrderedFloat(partner_start),
                         velocity: 0.0,
                         max_velocity: 0.0,
+                        length: 0.0,
                     }].into()
                 } else {
                     CVec::new()
@@ -848,3 +1131,74 @@ fn obstacles_for_interaction(
 
 mod kay_auto;
 pub use self::kay_auto::*;
+
+#[test]
+fn test_cars_get_through_stop_signs() {
+    let vehicle = VehicleType::Car;
+    let stop_line = 50.0;
+    let speed_limit = 15.0;
+    let dt = 1.0 / TICKS_PER_SIM_SECOND as f32;
+
+    let mut car = Obstacle {
+        position: OrderedFloat(0.0),
+        velocity: 10.0,
+        max_velocity: vehicle.max_velocity(),
+        length: vehicle.length(),
+    };
+    let mut stopped_at_line_since = None;
+    let mut acceleration = 0.0;
+
+    for tick in 0..TICKS_PER_SIM_SECOND * 120 {
+        if tick % TRAFFIC_LOGIC_THROTTLING == 0 {
+            let (waiting, stopped_since) =
+                stop_sign_wait(&car, stop_line, stopped_at_line_since, Instant::new(tick));
+            stopped_at_line_since = stopped_since;
+            let obstacle = if waiting {
+                stop_obstacle(stop_line)
+            } else {
+                Obstacle::far_ahead()
+            };
+            acceleration = vehicle_acceleration(vehicle, &car, &obstacle, 2.0, speed_limit);
+        }
+
+        *car.position += dt * car.velocity;
+        car.velocity = (car.velocity + dt * acceleration)
+            .min(car.max_velocity)
+            .max(0.0);
+
+        if *car.position > stop_line {
+            assert!(
+                stopped_at_line_since.is_some(),
+                "car didn't stop at the line"
+            );
+            return;
+        }
+    }
+
+    panic!("car never got through the stop sign");
+}
+
+#[test]
+fn test_cars_dont_pass_stop_signs_without_stopping() {
+    let car = Obstacle {
+        position: OrderedFloat(46.0),
+        velocity: 8.0,
+        max_velocity: 40.0,
+        length: 4.0,
+    };
+    let (waiting, stopped_since) = stop_sign_wait(&car, 50.0, None, Instant::new(0));
+    assert!(waiting);
+    assert!(stopped_since.is_none());
+
+    let stopped_car = Obstacle {
+        velocity: 0.0,
+        ..car
+    };
+    let (waiting, stopped_since) = stop_sign_wait(&stopped_car, 50.0, None, Instant::new(0));
+    assert!(waiting);
+    assert_eq!(stopped_since, Some(Instant::new(0)));
+
+    let dwell_ticks = STOP_SIGN_DWELL_SECS * TICKS_PER_SIM_SECOND;
+    let (waiting, _) = stop_sign_wait(&car, 50.0, stopped_since, Instant::new(dwell_ticks));
+    assert!(!waiting);
+}
@@ -0,0 +1,36 @@
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum VehicleType {
+    Car,
+    // carries goods between businesses
+    DeliveryTruck,
+    // serves transit lines
+    Bus,
+}
+
+impl VehicleType {
+    // bumper to bumper, the minimum distance kept to the vehicle in front comes on top
+    pub fn length(&self) -> f32 {
+        match *self {
+            VehicleType::Car => 4.0,
+            VehicleType::DeliveryTruck => 8.0,
+            VehicleType::Bus => 12.0,
+        }
+    }
+
+    pub fn acceleration(&self) -> f32 {
+        match *self {
+            VehicleType::Car => 0.4,
+            VehicleType::DeliveryTruck => 0.25,
+            VehicleType::Bus => 0.3,
+        }
+    }
+
+    // vehicles could go this fast, but usually keep to the speed limit of their lane
+    pub fn max_velocity(&self) -> f32 {
+        match *self {
+            VehicleType::Car => 40.0,
+            VehicleType::DeliveryTruck => 25.0,
+            VehicleType::Bus => 25.0,
+        }
+    }
+}
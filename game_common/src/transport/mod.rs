@@ -5,6 +5,10 @@ pub mod rendering;
 
 pub mod transport_planning;
 pub mod pathfinding;
+pub mod transit;
+pub mod pedestrians;
+pub mod parking;
+pub mod signal_control;
 
 use kay::{ActorSystem, World};
 use simulation::SimulationID;
@@ -16,9 +20,14 @@ pub fn setup(system: &mut ActorSystem) {
     self::pathfinding::setup(system);
     self::rendering::setup(system);
     self::transport_planning::setup(system);
+    self::transit::setup(system);
+    self::pedestrians::setup(system);
+    self::parking::setup(system);
+    self::signal_control::setup(system);
 }
 
 pub fn spawn(world: &mut World, simulation: SimulationID) {
     self::pathfinding::spawn(world, simulation);
     self::rendering::spawn(world);
+    self::transit::spawn(world);
 }
@@ -0,0 +1,131 @@
+use kay::{ActorSystem, World};
+use descartes::N;
+
+use land_use::buildings::{Building, BuildingID, UnitType};
+use super::lane::{Lane, LaneID};
+
+// including the gap to the next parked car
+const CURB_PARKING_SPACE_LENGTH: N = 6.0;
+// cars that found no space at their destination try this many more lanes
+pub const MAX_PARKING_SEARCH_LANES: u8 = 5;
+
+#[derive(Copy, Clone, Debug)]
+pub enum ParkingSpot {
+    // for destinations that don't have parking, like transit stops
+    NotNeeded,
+    Lot(BuildingID),
+    Curb(LaneID),
+}
+
+impl ParkingSpot {
+    pub fn release(&self, world: &mut World) {
+        match *self {
+            ParkingSpot::NotNeeded => {}
+            ParkingSpot::Lot(building) => building.release_parking(world),
+            ParkingSpot::Curb(lane) => lane.release_curb_parking(world),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Default, Debug)]
+pub struct ParkingCapacity {
+    pub spaces: u16,
+    pub occupied: u16,
+}
+
+impl ParkingCapacity {
+    pub fn new(spaces: u16) -> ParkingCapacity {
+        ParkingCapacity {
+            spaces,
+            occupied: 0,
+        }
+    }
+
+    pub fn try_occupy(&mut self) -> bool {
+        if self.occupied < self.spaces {
+            self.occupied += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn release(&mut self) {
+        self.occupied = self.occupied.saturating_sub(1);
+    }
+}
+
+pub fn curb_parking_for_lane(length: N, on_intersection: bool, curbside: bool) -> ParkingCapacity {
+    if curbside && !on_intersection {
+        ParkingCapacity::new((length / CURB_PARKING_SPACE_LENGTH) as u16)
+    } else {
+        ParkingCapacity::default()
+    }
+}
+
+fn parking_spaces_for_unit(unit_type: UnitType) -> u16 {
+    match unit_type {
+        UnitType::Dwelling => 2,
+        UnitType::Retail => 8,
+        UnitType::Agriculture => 2,
+        UnitType::Mill | UnitType::Bakery => 4,
+        // stands in for all the parking of the businesses of a whole town
+        UnitType::NeighboringTownTrade => 200,
+    }
+}
+
+// buildings have as much parking as their units need, so bigger buildings have more
+pub fn lot_parking_for_units<I: IntoIterator<Item = UnitType>>(unit_types: I) -> ParkingCapacity {
+    ParkingCapacity::new(unit_types.into_iter().map(parking_spaces_for_unit).sum())
+}
+
+impl Lane {
+    pub fn release_curb_parking(&mut self, _: &mut World) {
+        self.parking.release();
+    }
+}
+
+impl Building {
+    pub fn release_parking(&mut self, _: &mut World) {
+        self.parking.release();
+    }
+}
+
+pub fn setup(system: &mut ActorSystem) {
+    auto_setup(system);
+}
+
+mod kay_auto;
+pub use self::kay_auto::*;
+
+#[test]
+fn test_parking_capacity_frees_released_spaces() {
+    let mut capacity = ParkingCapacity::new(2);
+    assert!(capacity.try_occupy());
+    assert!(capacity.try_occupy());
+    assert!(!capacity.try_occupy());
+
+    capacity.release();
+    assert!(capacity.try_occupy());
+
+    capacity.release();
+    capacity.release();
+    capacity.release();
+    assert_eq!(capacity.occupied, 0);
+}
+
+#[test]
+fn test_lot_parking_grows_with_units() {
+    let family_house = lot_parking_for_units(vec![UnitType::Dwelling]);
+    let small_block = lot_parking_for_units(vec![UnitType::Dwelling; 4]);
+    let big_block = lot_parking_for_units(vec![UnitType::Dwelling; 20]);
+    assert_eq!(family_house.spaces, 2);
+    assert!(small_block.spaces < big_block.spaces);
+
+    let shop_with_flats = lot_parking_for_units(vec![
+        UnitType::Retail,
+        UnitType::Dwelling,
+        UnitType::Dwelling,
+    ]);
+    assert_eq!(shop_with_flats.spaces, 12);
+}
@@ -3,19 +3,22 @@ use kay::{ActorSystem, World, TypedID, Actor};
 use descartes::{P2};
 use super::lane::{Lane, LaneID, SwitchLane, SwitchLaneID};
 use super::lane::connectivity::{Interaction, InteractionKind, OverlapKind};
+use super::pedestrians::{Sidewalk, SidewalkID, WALKING_SPEED};
+use super::parking::ParkingSpot;
 use simulation::Instant;
 
 // TODO: MAKE TRANSFER LANE NOT PARTICIPATE AT ALL IN PATHFINDING -> MUCH SIMPLER
 
 pub mod trip;
-use self::trip::{TripResult, TripFate};
+use self::trip::{TripID, TripResult, TripFate};
 
 pub trait Node {
     fn update_routes(&mut self, world: &mut World);
     fn query_routes(&mut self, requester: NodeID, is_switch: bool, world: &mut World);
+    // distance, travel time and hops to each destination
     fn on_routes(
         &mut self,
-        new_routes: &CDict<Location, (f32, u8)>,
+        new_routes: &CDict<Location, (f32, f32, u8)>,
         from: NodeID,
         world: &mut World,
     );
@@ -47,6 +50,8 @@ pub struct PathfindingInfo {
     pub tell_to_forget_next_tick: CVec<Location>,
     pub query_routes_next_tick: bool,
     pub routing_timeout: u16,
+    // the own travel time that predecessors last heard about
+    pub advertised_travel_time: f32,
     attachees: CVec<AttacheeID>,
     pub debug_highlight_for: CHashMap<LaneID, ()>,
 }
@@ -105,6 +110,7 @@ pub trait Attachee {
 pub struct RoutingInfo {
     pub outgoing_idx: u8,
     pub distance: f32,
+    pub travel_time: f32,
     distance_hops: u8,
     learned_from: NodeID,
     fresh: bool,
@@ -137,8 +143,19 @@ pub fn on_disconnect(lane: &mut Lane, disconnected_id: LaneLikeID) {
 const IDEAL_LANDMARK_RADIUS: u8 = 3;
 const MIN_LANDMARK_INCOMING: usize = 3;
 const ROUTING_TIMEOUT_AFTER_CHANGE: u16 = 15;
-const LANE_CHANGE_COST_LEFT: f32 = 5.0;
-const LANE_CHANGE_COST_RIGHT: f32 = 3.0;
+// in seconds of travel time
+const LANE_CHANGE_COST_LEFT: f32 = 0.5;
+const LANE_CHANGE_COST_RIGHT: f32 = 0.3;
+// lanes only tell their predecessors about a relative change in travel time bigger than this
+const TRAVEL_TIME_CHANGE_THRESHOLD: f32 = 0.2;
+// keeps travel times of jammed lanes finite
+const MIN_AVERAGE_SPEED: f32 = 1.0;
+
+// how long it currently takes to drive along a lane, including waiting at its signal
+fn lane_travel_time(lane: &Lane) -> f32 {
+    lane.construction.length / lane.microtraffic.average_speed.max(MIN_AVERAGE_SPEED)
+        + lane.microtraffic.signal_delay_secs
+}
 
 impl Node for Lane {
     fn update_routes(&mut self, world: &mut World) {
@@ -166,6 +183,7 @@ impl Node for Lane {
                 query_routes_next_tick: false,
                 tell_to_forget_next_tick: CVec::new(),
                 routing_timeout: ROUTING_TIMEOUT_AFTER_CHANGE,
+                advertised_travel_time: self.pathfinding.advertised_travel_time,
                 attachees: self.pathfinding.attachees.clone(),
                 debug_highlight_for: self.pathfinding.debug_highlight_for.clone(),
             }
@@ -192,12 +210,20 @@ impl Node for Lane {
                 self.pathfinding.tell_to_forget_next_tick.clear();
             }
 
+            // so that predecessors route around jams
+            let travel_time = lane_travel_time(self);
+            let advertised = self.pathfinding.advertised_travel_time;
+            if (travel_time - advertised).abs() > TRAVEL_TIME_CHANGE_THRESHOLD * advertised {
+                self.pathfinding.advertised_travel_time = travel_time;
+                self.pathfinding.routes_changed = true;
+            }
+
             if self.pathfinding.routes_changed {
                 for (_, predecessor, is_switch) in predecessors(self) {
-                    let self_cost = if is_switch {
-                        0.0
+                    let (self_distance, self_travel_time) = if is_switch {
+                        (0.0, 0.0)
                     } else {
-                        self.construction.length
+                        (self.construction.length, lane_travel_time(self))
                     };
                     predecessor.on_routes(
                         self.pathfinding
@@ -208,6 +234,7 @@ impl Node for Lane {
                                     &destination,
                                     &RoutingInfo {
                                         distance,
+                                        travel_time,
                                         distance_hops,
                                         ..
                                     },
@@ -217,7 +244,11 @@ impl Node for Lane {
                                     {
                                         Some((
                                             destination,
-                                            (distance + self_cost, distance_hops + 1),
+                                            (
+                                                distance + self_distance,
+                                                travel_time + self_travel_time,
+                                                distance_hops + 1,
+                                            ),
                                         ))
                                     } else {
                                         None
@@ -227,9 +258,9 @@ impl Node for Lane {
                             .chain(if self.connectivity.on_intersection {
                                 None
                             } else {
-                                self.pathfinding
-                                    .location
-                                    .map(|destination| (destination, (self_cost, 0)))
+                                self.pathfinding.location.map(|destination| {
+                                    (destination, (self_distance, self_travel_time, 0))
+                                })
                             })
                             .collect(),
                         self.id_as(),
@@ -245,10 +276,10 @@ impl Node for Lane {
     }
 
     fn query_routes(&mut self, requester: NodeID, is_switch: bool, world: &mut World) {
-        let self_cost = if is_switch {
-            0.0
+        let (self_distance, self_travel_time) = if is_switch {
+            (0.0, 0.0)
         } else {
-            self.construction.length
+            (self.construction.length, lane_travel_time(self))
         };
         requester.on_routes(
             self.pathfinding
@@ -259,11 +290,19 @@ impl Node for Lane {
                         &destination,
                         &RoutingInfo {
                             distance,
+                            travel_time,
                             distance_hops,
                             ..
                         },
                     )| {
-                        (destination, (distance + self_cost, distance_hops + 1))
+                        (
+                            destination,
+                            (
+                                distance + self_distance,
+                                travel_time + self_travel_time,
+                                distance_hops + 1,
+                            ),
+                        )
                     },
                 )
                 .chain(if self.connectivity.on_intersection {
@@ -271,7 +310,7 @@ impl Node for Lane {
                 } else {
                     self.pathfinding
                         .location
-                        .map(|destination| (destination, (self_cost, 0)))
+                        .map(|destination| (destination, (self_distance, self_travel_time, 0)))
                 })
                 .collect(),
             self.id_as(),
@@ -279,14 +318,21 @@ impl Node for Lane {
         );
     }
 
-    fn on_routes(&mut self, new_routes: &CDict<Location, (f32, u8)>, from: NodeID, _: &mut World) {
+    fn on_routes(
+        &mut self,
+        new_routes: &CDict<Location, (f32, f32, u8)>,
+        from: NodeID,
+        _: &mut World,
+    ) {
         if let Some(from_interaction_idx) = self.connectivity.interactions.iter().position(
             |interaction| {
                 // TODO: ugly: untyped RawID shenanigans
                 interaction.partner_lane.as_raw() == from.as_raw()
             },
         ) {
-            for (&destination, &(new_distance, new_distance_hops)) in new_routes.pairs() {
+            for (&destination, &(new_distance, new_travel_time, new_distance_hops)) in
+                new_routes.pairs()
+            {
                 if destination.is_landmark() || new_distance_hops <= IDEAL_LANDMARK_RADIUS
                     || self
                         .pathfinding
@@ -294,17 +340,28 @@ impl Node for Lane {
                         .map(|self_dest| self_dest.landmark == destination.landmark)
                         .unwrap_or(false)
                 {
-                    let insert = self
-                        .pathfinding
-                        .routes
-                        .get(destination)
-                        .map(|&RoutingInfo { distance, .. }| new_distance < distance)
-                        .unwrap_or(true);
+                    // routes can also get slower, which only the successor they
+                    // were learned from can tell, so then alternatives are asked for again
+                    let (insert, got_slower) = match self.pathfinding.routes.get(destination) {
+                        Some(&RoutingInfo {
+                            travel_time,
+                            learned_from,
+                            ..
+                        }) => {
+                            let got_slower = learned_from == from && new_travel_time > travel_time;
+                            (new_travel_time < travel_time || got_slower, got_slower)
+                        }
+                        None => (true, false),
+                    };
+                    if got_slower {
+                        self.pathfinding.query_routes_next_tick = true;
+                    }
                     if insert {
                         self.pathfinding.routes.insert(
                             destination,
                             RoutingInfo {
                                 distance: new_distance,
+                                travel_time: new_travel_time,
                                 distance_hops: new_distance_hops,
                                 outgoing_idx: from_interaction_idx as u8,
                                 learned_from: from,
@@ -419,6 +476,7 @@ impl Node for Lane {
                 query_routes_next_tick: true,
                 tell_to_forget_next_tick,
                 routing_timeout: ROUTING_TIMEOUT_AFTER_CHANGE,
+                advertised_travel_time: self.pathfinding.advertised_travel_time,
                 attachees: self.pathfinding.attachees.clone(),
                 debug_highlight_for: self.pathfinding.debug_highlight_for.clone(),
             };
@@ -431,7 +489,7 @@ impl Node for Lane {
         requester: DistanceRequesterID,
         world: &mut World,
     ) {
-        let maybe_distance = self
+        let maybe_distance_and_time = self
             .pathfinding
             .routes
             .get(destination)
@@ -440,8 +498,8 @@ impl Node for Lane {
                     .routes
                     .get(destination.landmark_destination())
             })
-            .map(|routing_info| routing_info.distance);
-        requester.on_distance(maybe_distance, world);
+            .map(|routing_info| (routing_info.distance, routing_info.travel_time));
+        requester.on_distance(maybe_distance_and_time, world);
     }
 
     fn add_attachee(&mut self, attachee: AttacheeID, _: &mut World) {
@@ -543,7 +601,7 @@ impl Node for SwitchLane {
 
     fn on_routes(
         &mut self,
-        new_routes: &CDict<Location, (f32, u8)>,
+        new_routes: &CDict<Location, (f32, f32, u8)>,
         from: NodeID,
         world: &mut World,
     ) {
@@ -553,7 +611,7 @@ impl Node for SwitchLane {
             other_lane.on_routes(
                 new_routes
                     .pairs()
-                    .map(|(&destination, &(distance, hops))| {
+                    .map(|(&destination, &(distance, travel_time, hops))| {
                         // TODO: ugly: untyped RawID shenanigans
                         let change_cost = if from.as_raw()
                             == self.connectivity.left.expect("should have left").0.as_raw()
@@ -562,7 +620,7 @@ impl Node for SwitchLane {
                         } else {
                             LANE_CHANGE_COST_LEFT
                         };
-                        (destination, (distance + change_cost, hops))
+                        (destination, (distance, travel_time + change_cost, hops))
                     })
                     .collect(),
                 self.id_as(),
@@ -614,6 +672,339 @@ impl Node for SwitchLane {
     fn remove_attachee(&mut self, _attachee: AttacheeID, _: &mut World) {}
 }
 
+pub fn on_sidewalk_connect(sidewalk: &mut Sidewalk) {
+    sidewalk.pathfinding.routing_timeout = ROUTING_TIMEOUT_AFTER_CHANGE;
+    sidewalk.pathfinding.query_routes_next_tick = true;
+}
+
+pub fn on_sidewalk_disconnect(sidewalk: &mut Sidewalk, disconnected_id: SidewalkID) {
+    let disconnected: NodeID = disconnected_id.into();
+    // connections were removed, so the remaining routes need their outgoing indices updated
+    let new_routes = {
+        let connections = &sidewalk.connections;
+        sidewalk
+            .pathfinding
+            .routes
+            .pairs()
+            .filter_map(|(destination, route)| {
+                if route.learned_from == disconnected {
+                    None
+                } else {
+                    connections
+                        .iter()
+                        .position(|connection| {
+                            let partner: NodeID = connection.partner.into();
+                            partner == route.learned_from
+                        })
+                        .map(|idx| {
+                            (
+                                *destination,
+                                RoutingInfo {
+                                    outgoing_idx: idx as u8,
+                                    ..*route
+                                },
+                            )
+                        })
+                }
+            })
+            .collect()
+    };
+    sidewalk.pathfinding.routes = new_routes;
+    sidewalk.pathfinding.routes_changed = true;
+    sidewalk.pathfinding.query_routes_next_tick = true;
+}
+
+pub fn on_sidewalk_unbuild(sidewalk: &Sidewalk, world: &mut World) {
+    for attachee in &sidewalk.pathfinding.attachees {
+        attachee.location_changed(sidewalk.pathfinding.location, None, world);
+    }
+}
+
+// sidewalks can be walked in both directions, so every
+// connected sidewalk is both a successor and a predecessor
+#[cfg_attr(feature = "cargo-clippy", allow(needless_lifetimes))]
+fn sidewalk_neighbors<'a>(sidewalk: &'a Sidewalk) -> impl Iterator<Item = (u8, NodeID)> + 'a {
+    sidewalk
+        .connections
+        .iter()
+        .enumerate()
+        .filter(move |&(i, connection)| {
+            sidewalk
+                .connections
+                .iter()
+                .position(|other| other.partner == connection.partner)
+                == Some(i)
+        })
+        .map(|(i, connection)| (i as u8, connection.partner.into()))
+}
+
+impl Node for Sidewalk {
+    fn update_routes(&mut self, world: &mut World) {
+        if let Some(location) = self.pathfinding.location {
+            for (_, neighbor) in sidewalk_neighbors(self) {
+                neighbor.join_landmark(
+                    self.id_as(),
+                    Location {
+                        landmark: location.landmark,
+                        node: neighbor,
+                    },
+                    self.pathfinding.hops_from_landmark + 1,
+                    world,
+                );
+            }
+        } else {
+            // pedestrian networks are small, so every sidewalk starts out
+            // as a landmark and nearby landmarks merge into each other
+            self.pathfinding = PathfindingInfo {
+                location: Some(Location::landmark(self.id_as())),
+                hops_from_landmark: 0,
+                learned_landmark_from: Some(self.id_as()),
+                routes: CHashMap::new(),
+                routes_changed: true,
+                query_routes_next_tick: false,
+                tell_to_forget_next_tick: CVec::new(),
+                routing_timeout: ROUTING_TIMEOUT_AFTER_CHANGE,
+                advertised_travel_time: self.pathfinding.advertised_travel_time,
+                attachees: self.pathfinding.attachees.clone(),
+                debug_highlight_for: CHashMap::new(),
+            }
+        }
+
+        if self.pathfinding.routing_timeout > 0 {
+            self.pathfinding.routing_timeout -= 1;
+        } else {
+            if self.pathfinding.query_routes_next_tick {
+                for (_, neighbor) in sidewalk_neighbors(self) {
+                    neighbor.query_routes(self.id_as(), false, world);
+                }
+                self.pathfinding.query_routes_next_tick = false;
+            }
+
+            if !self.pathfinding.tell_to_forget_next_tick.is_empty() {
+                for (_, neighbor) in sidewalk_neighbors(self) {
+                    neighbor.forget_routes(
+                        self.pathfinding.tell_to_forget_next_tick.clone(),
+                        self.id_as(),
+                        world,
+                    );
+                }
+                self.pathfinding.tell_to_forget_next_tick.clear();
+            }
+
+            if self.pathfinding.routes_changed {
+                for (_, neighbor) in sidewalk_neighbors(self) {
+                    self.query_routes(neighbor, false, world);
+                }
+                self.pathfinding.routes_changed = false;
+            }
+        }
+    }
+
+    fn query_routes(&mut self, requester: NodeID, _is_switch: bool, world: &mut World) {
+        let self_distance = self.path.length();
+        let self_travel_time = self_distance / WALKING_SPEED;
+        requester.on_routes(
+            self.pathfinding
+                .routes
+                .pairs()
+                .map(
+                    |(
+                        &destination,
+                        &RoutingInfo {
+                            distance,
+                            travel_time,
+                            distance_hops,
+                            ..
+                        },
+                    )| {
+                        (
+                            destination,
+                            (
+                                distance + self_distance,
+                                travel_time + self_travel_time,
+                                distance_hops + 1,
+                            ),
+                        )
+                    },
+                )
+                .chain(
+                    self.pathfinding
+                        .location
+                        .map(|destination| (destination, (self_distance, self_travel_time, 0))),
+                )
+                .collect(),
+            self.id_as(),
+            world,
+        );
+    }
+
+    fn on_routes(
+        &mut self,
+        new_routes: &CDict<Location, (f32, f32, u8)>,
+        from: NodeID,
+        _: &mut World,
+    ) {
+        if let Some(from_connection_idx) = self.connections.iter().position(|connection| {
+            let partner: NodeID = connection.partner.into();
+            partner == from
+        }) {
+            for (&destination, &(new_distance, new_travel_time, new_distance_hops)) in
+                new_routes.pairs()
+            {
+                let self_as_node: NodeID = self.id_as();
+                if destination.node == self_as_node {
+                    continue;
+                }
+
+                if destination.is_landmark() || new_distance_hops <= IDEAL_LANDMARK_RADIUS
+                    || self
+                        .pathfinding
+                        .location
+                        .map(|self_dest| self_dest.landmark == destination.landmark)
+                        .unwrap_or(false)
+                {
+                    let insert = self
+                        .pathfinding
+                        .routes
+                        .get(destination)
+                        .map(|&RoutingInfo { travel_time, .. }| new_travel_time < travel_time)
+                        .unwrap_or(true);
+                    if insert {
+                        self.pathfinding.routes.insert(
+                            destination,
+                            RoutingInfo {
+                                distance: new_distance,
+                                travel_time: new_travel_time,
+                                distance_hops: new_distance_hops,
+                                outgoing_idx: from_connection_idx as u8,
+                                learned_from: from,
+                                fresh: true,
+                            },
+                        );
+                        self.pathfinding.routes_changed = true;
+                    }
+                }
+            }
+        }
+    }
+
+    fn forget_routes(&mut self, forget: &CVec<Location>, from: NodeID, world: &mut World) {
+        let mut forgotten = CVec::<Location>::new();
+        for destination_to_forget in forget.iter() {
+            let forget =
+                if let Some(routing_info) = self.pathfinding.routes.get(*destination_to_forget) {
+                    routing_info.learned_from == from
+                } else {
+                    false
+                };
+            if forget {
+                self.pathfinding.routes.remove(*destination_to_forget);
+                let destination_to_forget = *destination_to_forget;
+                self.pedestrians.retain(|pedestrian| {
+                    let lost = pedestrian.next_hop.is_some()
+                        && if destination_to_forget.is_landmark() {
+                            pedestrian.destination.landmark == destination_to_forget.landmark
+                        } else {
+                            pedestrian.destination.location == destination_to_forget
+                        };
+                    if lost {
+                        pedestrian.trip.finish(
+                            TripResult {
+                                location_now: None,
+                                fate: TripFate::RouteForgotten,
+                            },
+                            world,
+                        );
+                    }
+                    !lost
+                });
+                forgotten.push(destination_to_forget);
+            }
+        }
+        self.pathfinding.tell_to_forget_next_tick = forgotten;
+    }
+
+    fn join_landmark(
+        &mut self,
+        from: NodeID,
+        join_as: Location,
+        hops_from_landmark: u8,
+        world: &mut World,
+    ) {
+        let join = self
+            .pathfinding
+            .location
+            .map(|self_location| {
+                join_as != self_location && (if self_location.is_landmark() {
+                    hops_from_landmark < IDEAL_LANDMARK_RADIUS
+                        && join_as.landmark.as_raw().instance_id < self.id.as_raw().instance_id
+                } else {
+                    hops_from_landmark < self.pathfinding.hops_from_landmark
+                        || self
+                            .pathfinding
+                            .learned_landmark_from
+                            .map(|learned_from| learned_from == from)
+                            .unwrap_or(false)
+                })
+            })
+            .unwrap_or(true);
+        if join {
+            let tell_to_forget_next_tick = self
+                .pathfinding
+                .routes
+                .keys()
+                .cloned()
+                .chain(self.pathfinding.location.into_iter())
+                .collect();
+
+            for attachee in &self.pathfinding.attachees {
+                attachee.location_changed(self.pathfinding.location, Some(join_as), world);
+            }
+
+            self.pathfinding = PathfindingInfo {
+                location: Some(join_as),
+                learned_landmark_from: Some(from),
+                hops_from_landmark,
+                routes: CHashMap::new(),
+                routes_changed: true,
+                query_routes_next_tick: true,
+                tell_to_forget_next_tick,
+                routing_timeout: ROUTING_TIMEOUT_AFTER_CHANGE,
+                advertised_travel_time: self.pathfinding.advertised_travel_time,
+                attachees: self.pathfinding.attachees.clone(),
+                debug_highlight_for: CHashMap::new(),
+            };
+        }
+    }
+
+    fn get_distance_to(
+        &mut self,
+        destination: Location,
+        requester: DistanceRequesterID,
+        world: &mut World,
+    ) {
+        let maybe_distance_and_time = self
+            .pathfinding
+            .routes
+            .get(destination)
+            .or_else(|| {
+                self.pathfinding
+                    .routes
+                    .get(destination.landmark_destination())
+            })
+            .map(|routing_info| (routing_info.distance, routing_info.travel_time));
+        requester.on_distance(maybe_distance_and_time, world);
+    }
+
+    fn add_attachee(&mut self, attachee: AttacheeID, _: &mut World) {
+        self.pathfinding.attachees.push(attachee);
+    }
+
+    fn remove_attachee(&mut self, attachee: AttacheeID, _: &mut World) {
+        self.pathfinding.attachees.retain(|a| *a != attachee);
+    }
+}
+
 pub enum RoughLocationResolve {
     Done(Option<PreciseLocation>, P2),
     SameAs(RoughLocationID),
@@ -639,6 +1030,54 @@ pub trait RoughLocation {
         }
     }
 
+    // where pedestrians enter the sidewalk network, if at all
+    fn walking_location(&self) -> Option<PreciseLocation> {
+        None
+    }
+
+    fn resolve_as_walking_location(
+        &mut self,
+        requester: TripID,
+        rough_location: RoughLocationID,
+        instant: Instant,
+        world: &mut World,
+    ) {
+        match self.resolve() {
+            RoughLocationResolve::Done(..) => {
+                requester.walking_location_resolved(
+                    rough_location,
+                    self.walking_location(),
+                    instant,
+                    world,
+                );
+            }
+            RoughLocationResolve::SameAs(other_rough_location) => {
+                other_rough_location.resolve_as_walking_location(
+                    requester,
+                    rough_location,
+                    instant,
+                    world,
+                );
+            }
+        }
+    }
+
+    // only places with their own parking lot can run out of spaces
+    fn try_reserve_parking(&mut self) -> Option<ParkingSpot> {
+        Some(ParkingSpot::NotNeeded)
+    }
+
+    fn reserve_parking(&mut self, requester: TripID, world: &mut World) {
+        match self.resolve() {
+            RoughLocationResolve::Done(..) => {
+                requester.parking_reserved(self.try_reserve_parking(), world);
+            }
+            RoughLocationResolve::SameAs(other_rough_location) => {
+                other_rough_location.reserve_parking(requester, world);
+            }
+        }
+    }
+
     fn resolve_as_position(
         &mut self,
         requester: PositionRequesterID,
@@ -676,7 +1115,7 @@ pub trait PositionRequester {
 }
 
 pub trait DistanceRequester {
-    fn on_distance(&mut self, maybe_distance: Option<f32>, world: &mut World);
+    fn on_distance(&mut self, maybe_distance_and_time: Option<(f32, f32)>, world: &mut World);
 }
 
 pub const DEBUG_VIEW_CONNECTIVITY: bool = false;
@@ -8,6 +8,10 @@ use super::{PreciseLocation, RoughLocationID, LocationRequester, LocationRequest
 
 use itertools::Itertools;
 use super::super::lane::Lane;
+use super::super::transit::{TransitNetwork, TransitStopID};
+use super::super::pedestrians::SidewalkID;
+use super::super::parking::ParkingSpot;
+use statistics::Statistics;
 
 #[derive(Compact, Clone)]
 pub struct Trip {
@@ -17,6 +21,33 @@ pub struct Trip {
     source: Option<PreciseLocation>,
     destination: Option<PreciseLocation>,
     listener: Option<TripListenerID>,
+    mode: TripMode,
+    source_position: Option<P2>,
+    destination_position: Option<P2>,
+    last_instant: Instant,
+    started: bool,
+    parking: Option<ParkingSpot>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum TripMode {
+    Car,
+    CarOrTransit,
+    Walking,
+    // goods are driven in delivery trucks
+    Freight,
+    // the trip of a transit vehicle between two of its stops
+    Bus,
+}
+
+impl TripMode {
+    fn vehicle(&self) -> VehicleType {
+        match *self {
+            TripMode::Car | TripMode::CarOrTransit | TripMode::Walking => VehicleType::Car,
+            TripMode::Freight => VehicleType::DeliveryTruck,
+            TripMode::Bus => VehicleType::Bus,
+        }
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -35,6 +66,7 @@ pub enum TripFate {
     HopDisconnected,
     LaneUnbuilt,
     ForceStopped,
+    NoParking,
 }
 
 const DEBUG_FAILED_TRIPS_VISUALLY: bool = false;
@@ -45,10 +77,21 @@ impl Trip {
         rough_source: RoughLocationID,
         rough_destination: RoughLocationID,
         listener: Option<TripListenerID>,
+        mode: TripMode,
         instant: Instant,
         world: &mut World,
     ) -> Self {
-        rough_source.resolve_as_location(id.into(), rough_source, instant, world);
+        if mode == TripMode::Walking {
+            rough_source.resolve_as_walking_location(id, rough_source, instant, world);
+        } else {
+            rough_source.resolve_as_location(id.into(), rough_source, instant, world);
+        }
+
+        // positions are only needed to look for a transit connection
+        if mode == TripMode::CarOrTransit {
+            rough_source.resolve_as_position(id.into(), rough_source, world);
+            rough_destination.resolve_as_position(id.into(), rough_destination, world);
+        }
 
         if let Some(listener) = listener {
             listener.trip_created(id, world);
@@ -61,10 +104,169 @@ impl Trip {
             listener,
             source: None,
             destination: None,
+            mode,
+            source_position: None,
+            destination_position: None,
+            last_instant: instant,
+            started: false,
+            parking: None,
+        }
+    }
+
+    fn try_start(&mut self, world: &mut World) {
+        if self.started {
+            return;
+        }
+
+        if let (Some(source), Some(destination)) = (self.source, self.destination) {
+            match self.mode {
+                TripMode::Car | TripMode::Freight | TripMode::Bus => {
+                    self.started = true;
+                    self.start_by_car(world);
+                }
+                TripMode::Walking => {
+                    self.started = true;
+                    // TODO: ugly: untyped RawID shenanigans
+                    let source_sidewalk = unsafe { SidewalkID::from_raw(source.node.as_raw()) };
+                    source_sidewalk.add_pedestrian(
+                        self.id,
+                        destination,
+                        source.offset,
+                        self.last_instant,
+                        world,
+                    );
+                }
+                TripMode::CarOrTransit => {
+                    if let (Some(source_position), Some(destination_position)) =
+                        (self.source_position, self.destination_position)
+                    {
+                        self.started = true;
+                        TransitNetwork::global_first(world).find_connection(
+                            self.id,
+                            source_position,
+                            destination_position,
+                            world,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn walking_location_resolved(
+        &mut self,
+        rough_location: RoughLocationID,
+        location: Option<PreciseLocation>,
+        instant: Instant,
+        world: &mut World,
+    ) {
+        if let Some(precise) = location {
+            self.last_instant = instant;
+
+            if rough_location == self.rough_source {
+                self.source = Some(precise);
+
+                if self.rough_source == self.rough_destination {
+                    self.destination = Some(precise);
+                } else {
+                    self.rough_destination.resolve_as_walking_location(
+                        self.id,
+                        self.rough_destination,
+                        instant,
+                        world,
+                    );
+                }
+            } else if rough_location == self.rough_destination {
+                self.destination = Some(precise);
+            } else {
+                unreachable!();
+            }
+
+            self.try_start(world);
+        } else {
+            // not everything is next to a sidewalk, just drive instead
+            self.mode = TripMode::Car;
+            self.source = None;
+            self.destination = None;
+            self.rough_source
+                .resolve_as_location(self.id_as(), self.rough_source, instant, world);
+        }
+    }
+
+    fn start_by_car(&mut self, world: &mut World) {
+        self.rough_destination.reserve_parking(self.id, world);
+    }
+
+    pub fn parking_reserved(&mut self, spot: Option<ParkingSpot>, world: &mut World) {
+        self.parking = spot;
+
+        if let (Some(source), Some(destination)) = (self.source, self.destination) {
+            // TODO: ugly: untyped RawID shenanigans
+            let source_as_lane: LaneLikeID = unsafe { LaneLikeID::from_raw(source.node.as_raw()) };
+            source_as_lane.add_car(
+                LaneCar {
+                    trip: self.id,
+                    vehicle: self.mode.vehicle(),
+                    as_obstacle: Obstacle {
+                        position: OrderedFloat(source.offset),
+                        velocity: 0.0,
+                        max_velocity: self.mode.vehicle().max_velocity(),
+                        length: self.mode.vehicle().length(),
+                    },
+                    acceleration: 0.0,
+                    destination,
+                    next_hop_interaction: None,
+                    parking: if spot.is_some() {
+                        CarParking::Reserved
+                    } else {
+                        CarParking::Needed
+                    },
+                    stopped_at_line_since: None,
+                },
+                None,
+                self.last_instant,
+                world,
+            );
+        } else {
+            unreachable!()
         }
     }
 
+    pub fn on_transit_connection(
+        &mut self,
+        connection: Option<(TransitStopID, TransitStopID)>,
+        world: &mut World,
+    ) {
+        match (connection, self.source, self.destination) {
+            (Some((board_at, alight_at)), _, _) => board_at.wait_for(self.id, alight_at, world),
+            (None, Some(_), Some(_)) => self.start_by_car(world),
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn parked(&mut self, spot: ParkingSpot, instant: Instant, world: &mut World) -> Fate {
+        self.parking = Some(spot);
+        self.finish(
+            TripResult {
+                location_now: None,
+                fate: TripFate::Success(instant),
+            },
+            world,
+        )
+    }
+
     pub fn finish(&mut self, result: TripResult, world: &mut World) -> Fate {
+        Statistics::global_first(world).on_trip_finished(result.fate, world);
+
+        if let Some(spot) = self.parking {
+            match self.listener {
+                Some(listener) if keeps_parking(result.fate, true) => {
+                    listener.trip_parked(self.id, spot, world)
+                }
+                _ => spot.release(world),
+            }
+        }
+
         match result.fate {
             TripFate::Success(_) | TripFate::ForceStopped => {}
             reason => {
@@ -97,6 +299,15 @@ impl Trip {
     }
 }
 
+// Only a car that arrived keeps its parking space, and only if somebody is
+// there to release it later - otherwise the space would stay occupied forever
+fn keeps_parking(fate: TripFate, has_listener: bool) -> bool {
+    match fate {
+        TripFate::Success(_) => has_listener,
+        _ => false,
+    }
+}
+
 impl LocationRequester for Trip {
     fn location_resolved(
         &mut self,
@@ -106,6 +317,8 @@ impl LocationRequester for Trip {
         world: &mut World,
     ) {
         if let Some(precise) = location {
+            self.last_instant = instant;
+
             if rough_location == self.rough_source {
                 self.source = Some(precise);
 
@@ -125,27 +338,7 @@ impl LocationRequester for Trip {
                 unreachable!();
             }
 
-            if let (Some(source), Some(destination)) = (self.source, self.destination) {
-                // TODO: ugly: untyped RawID shenanigans
-                let source_as_lane: LaneLikeID =
-                    unsafe { LaneLikeID::from_raw(source.node.as_raw()) };
-                source_as_lane.add_car(
-                    LaneCar {
-                        trip: self.id,
-                        as_obstacle: Obstacle {
-                            position: OrderedFloat(source.offset),
-                            velocity: 0.0,
-                            max_velocity: 8.0,
-                        },
-                        acceleration: 0.0,
-                        destination,
-                        next_hop_interaction: None,
-                    },
-                    None,
-                    instant,
-                    world,
-                );
-            }
+            self.try_start(world);
         } else {
             println!(
                 "{:?} is not a source/destination yet",
@@ -164,7 +357,7 @@ impl LocationRequester for Trip {
 
 use simulation::{SimulationID, Sleeper, SleeperID};
 use simulation::Ticks;
-use super::super::microtraffic::{LaneLikeID, LaneCar, Obstacle};
+use super::super::microtraffic::{LaneLikeID, LaneCar, Obstacle, CarParking, VehicleType};
 
 pub trait TripListener {
     fn trip_created(&mut self, trip: TripID, world: &mut World);
@@ -176,6 +369,8 @@ pub trait TripListener {
         rough_destination: RoughLocationID,
         world: &mut World,
     );
+    // the parking space is kept occupied until the listener releases it
+    fn trip_parked(&mut self, _trip: TripID, _spot: ParkingSpot, _: &mut World) {}
 }
 
 #[derive(Compact, Clone)]
@@ -215,6 +410,7 @@ impl Sleeper for TripCreator {
                     (*source).into(),
                     (*dest).into(),
                     None,
+                    TripMode::Car,
                     current_instant,
                     world,
                 );
@@ -240,6 +436,25 @@ use super::{PositionRequester, PositionRequesterID};
 use stagemaster::debug::{add_debug_line, add_debug_point};
 use descartes::{P2, V2};
 
+impl PositionRequester for Trip {
+    fn position_resolved(
+        &mut self,
+        rough_location: RoughLocationID,
+        position: P2,
+        world: &mut World,
+    ) {
+        // source and destination can be the same
+        if rough_location == self.rough_source {
+            self.source_position = Some(position);
+        }
+        if rough_location == self.rough_destination {
+            self.destination_position = Some(position);
+        }
+
+        self.try_start(world);
+    }
+}
+
 #[derive(Compact, Clone)]
 pub struct FailedTripDebugger {
     id: FailedTripDebuggerID,
@@ -315,3 +530,13 @@ pub fn spawn(world: &mut World, simulation: SimulationID) {
 
 mod kay_auto;
 pub use self::kay_auto::*;
+
+#[test]
+fn test_parking_is_released_unless_kept_by_listener() {
+    let arrived = TripFate::Success(Instant::new(100));
+    assert!(keeps_parking(arrived, true));
+    assert!(!keeps_parking(arrived, false));
+    assert!(!keeps_parking(TripFate::NoParking, true));
+    assert!(!keeps_parking(TripFate::ForceStopped, true));
+    assert!(!keeps_parking(TripFate::HopDisconnected, false));
+}
@@ -0,0 +1,289 @@
+use kay::{ActorSystem, World, Fate, Actor, TypedID};
+use compact::CVec;
+use descartes::{N, P2, LinePath, RoughEq, Intersect};
+use itertools::Itertools;
+use ordered_float::OrderedFloat;
+
+use simulation::{Simulatable, SimulatableID, Instant, Duration};
+use planning::Prototype;
+use construction::{ConstructionID, Constructable, ConstructableID};
+use land_use::buildings::BuildingID;
+use style::dimensions::SIDEWALK_CONNECTION_TOLERANCE;
+use super::pathfinding::{Node, NodeID, PathfindingInfo, PreciseLocation};
+use super::pathfinding::trip::{TripID, TripResult, TripFate};
+
+pub const WALKING_SPEED: N = 1.4;
+// longer trips are made by car or transit
+pub const MAX_WALKING_TRIP_DISTANCE: N = 800.0;
+const MAX_SIDEWALK_BUILDING_DISTANCE: N = 30.0;
+const PATHFINDING_THROTTLING: usize = 10;
+
+#[derive(Copy, Clone)]
+pub struct SidewalkConnection {
+    pub partner: SidewalkID,
+    pub at: N,
+    pub partner_at: N,
+}
+
+// Pedestrians don't interact with each other, they just take
+// as long as it takes to walk to the next crossing or their destination
+#[derive(Copy, Clone)]
+pub struct Pedestrian {
+    pub trip: TripID,
+    pub destination: PreciseLocation,
+    pub next_hop: Option<SidewalkConnection>,
+    pub arrives_at: Instant,
+}
+
+#[derive(Compact, Clone)]
+pub struct Sidewalk {
+    pub id: SidewalkID,
+    pub path: LinePath,
+    pub connections: CVec<SidewalkConnection>,
+    pub pathfinding: PathfindingInfo,
+    pub pedestrians: CVec<Pedestrian>,
+}
+
+impl Sidewalk {
+    pub fn spawn_and_connect(
+        id: SidewalkID,
+        path: &LinePath,
+        report_to: ConstructionID,
+        world: &mut World,
+    ) -> Sidewalk {
+        Sidewalk::global_broadcast(world).connect_sidewalk(id, path.clone(), true, world);
+        report_to.action_done(id.into(), world);
+
+        let sidewalk = Sidewalk {
+            id,
+            path: path.clone(),
+            connections: CVec::new(),
+            pathfinding: PathfindingInfo::default(),
+            pedestrians: CVec::new(),
+        };
+        super::rendering::on_build_sidewalk(&sidewalk, world);
+        sidewalk
+    }
+
+    pub fn connect_sidewalk(
+        &mut self,
+        other_id: SidewalkID,
+        other_path: &LinePath,
+        reply_needed: bool,
+        world: &mut World,
+    ) {
+        if other_id == self.id {
+            return;
+        }
+
+        let ends = [
+            (0.0, self.path.start()),
+            (self.path.length(), self.path.end()),
+        ];
+        let other_ends = [
+            (0.0, other_path.start()),
+            (other_path.length(), other_path.end()),
+        ];
+
+        let crossings = (&self.path, other_path)
+            .intersect()
+            .iter()
+            .map(|intersection| (intersection.along_a, intersection.along_b))
+            .chain(
+                ends.iter()
+                    .cartesian_product(other_ends.iter())
+                    .filter(|&(&(_, end), &(_, other_end))| {
+                        end.rough_eq_by(other_end, SIDEWALK_CONNECTION_TOLERANCE)
+                    })
+                    .map(|(&(at, _), &(partner_at, _))| (at, partner_at)),
+            )
+            .collect::<Vec<_>>();
+
+        let mut connected = false;
+
+        for (at, partner_at) in crossings {
+            let already_connected_here = self.connections.iter().any(|connection| {
+                connection.partner == other_id
+                    && (connection.at - at).abs() < SIDEWALK_CONNECTION_TOLERANCE
+            });
+            if !already_connected_here {
+                self.connections.push(SidewalkConnection {
+                    partner: other_id,
+                    at,
+                    partner_at,
+                });
+                connected = true;
+            }
+        }
+
+        if connected {
+            super::pathfinding::on_sidewalk_connect(self);
+
+            if reply_needed {
+                other_id.connect_sidewalk(self.id, self.path.clone(), false, world);
+            }
+        }
+    }
+
+    pub fn disconnect_sidewalk(&mut self, other_id: SidewalkID, world: &mut World) {
+        self.connections
+            .retain(|connection| connection.partner != other_id);
+        super::pathfinding::on_sidewalk_disconnect(self, other_id);
+
+        self.pedestrians.retain(|pedestrian| {
+            if pedestrian.next_hop.map(|hop| hop.partner) == Some(other_id) {
+                pedestrian.trip.finish(
+                    TripResult {
+                        location_now: None,
+                        fate: TripFate::HopDisconnected,
+                    },
+                    world,
+                );
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    pub fn add_pedestrian(
+        &mut self,
+        trip: TripID,
+        destination: PreciseLocation,
+        offset: N,
+        instant: Instant,
+        world: &mut World,
+    ) {
+        let self_as_node: NodeID = self.id_as();
+
+        let next_hop = if destination.node == self_as_node {
+            None
+        } else if let Some(route) = self
+            .pathfinding
+            .routes
+            .get(destination.location)
+            .or_else(|| {
+                self.pathfinding
+                    .routes
+                    .get(destination.landmark_destination())
+            }) {
+            let partner = self.connections[route.outgoing_idx as usize].partner;
+            // two sidewalks can cross several times, so walk to the closest crossing
+            self.connections
+                .iter()
+                .filter(|connection| connection.partner == partner)
+                .min_by_key(|connection| OrderedFloat((connection.at - offset).abs()))
+                .cloned()
+        } else {
+            trip.finish(
+                TripResult {
+                    location_now: None,
+                    fate: TripFate::NoRoute,
+                },
+                world,
+            );
+            return;
+        };
+
+        let walk_to = next_hop
+            .map(|connection| connection.at)
+            .unwrap_or(destination.offset);
+
+        self.pedestrians.push(Pedestrian {
+            trip,
+            destination,
+            next_hop,
+            arrives_at: instant
+                + Duration::from_seconds(((walk_to - offset).abs() / WALKING_SPEED) as usize),
+        });
+    }
+
+    pub fn try_reconnect_building_sidewalk(
+        &mut self,
+        building: BuildingID,
+        lot_position: P2,
+        world: &mut World,
+    ) {
+        if let Some(location) = self.pathfinding.location {
+            if let Some((offset, _)) = self.path.project_with_max_distance(
+                lot_position,
+                MAX_SIDEWALK_BUILDING_DISTANCE,
+                0.5,
+            ) {
+                building.reconnect_sidewalk(PreciseLocation { location, offset }, world);
+            }
+        }
+    }
+}
+
+impl Simulatable for Sidewalk {
+    fn tick(&mut self, _dt: f32, current_instant: Instant, world: &mut World) {
+        if current_instant.ticks() % PATHFINDING_THROTTLING
+            == self.id.as_raw().instance_id as usize % PATHFINDING_THROTTLING
+        {
+            self.update_routes(world);
+        }
+
+        let arrived = self
+            .pedestrians
+            .iter()
+            .filter(|pedestrian| pedestrian.arrives_at <= current_instant)
+            .cloned()
+            .collect::<Vec<_>>();
+        self.pedestrians
+            .retain(|pedestrian| pedestrian.arrives_at > current_instant);
+
+        for pedestrian in arrived {
+            if let Some(connection) = pedestrian.next_hop {
+                connection.partner.add_pedestrian(
+                    pedestrian.trip,
+                    pedestrian.destination,
+                    connection.partner_at,
+                    pedestrian.arrives_at,
+                    world,
+                );
+            } else {
+                pedestrian.trip.finish(
+                    TripResult {
+                        location_now: None,
+                        fate: TripFate::Success(pedestrian.arrives_at),
+                    },
+                    world,
+                );
+            }
+        }
+    }
+}
+
+impl Constructable for Sidewalk {
+    fn morph(&mut self, _new_prototype: &Prototype, report_to: ConstructionID, world: &mut World) {
+        report_to.action_done(self.id_as(), world);
+    }
+
+    fn destruct(&mut self, report_to: ConstructionID, world: &mut World) -> Fate {
+        for connection in &self.connections {
+            connection.partner.disconnect_sidewalk(self.id, world);
+        }
+        super::pathfinding::on_sidewalk_unbuild(self, world);
+        super::rendering::on_unbuild_sidewalk(self, world);
+        for pedestrian in &self.pedestrians {
+            pedestrian.trip.finish(
+                TripResult {
+                    location_now: None,
+                    fate: TripFate::LaneUnbuilt,
+                },
+                world,
+            );
+        }
+        report_to.action_done(self.id_as(), world);
+        Fate::Die
+    }
+}
+
+pub fn setup(system: &mut ActorSystem) {
+    system.register::<Sidewalk>();
+    auto_setup(system);
+}
+
+mod kay_auto;
+pub use self::kay_auto::*;
@@ -3,43 +3,45 @@ use compact::CVec;
 use kay::{ActorSystem, World, Actor, TypedID};
 use monet::{Instance, Vertex, Mesh, Renderer, RendererID};
 use super::lane::{Lane, LaneID, SwitchLane, SwitchLaneID};
+use super::pedestrians::{Sidewalk, SidewalkID};
+use super::microtraffic::{LaneCar, VehicleType};
 use render_layers::RenderLayers;
 
 use style::colors;
 use style::dimensions::{LANE_DISTANCE, LANE_WIDTH, LANE_MARKER_WIDTH, LANE_MARKER_DASH_GAP,
-LANE_MARKER_DASH_LENGTH};
+LANE_MARKER_DASH_LENGTH, SIDEWALK_WIDTH};
 
 use itertools::Itertools;
 
 #[path = "./resources/car.rs"]
 mod car;
 
+#[path = "./resources/vehicles.rs"]
+mod vehicles;
+
 #[path = "./resources/traffic_light.rs"]
 mod traffic_light;
 
 use monet::{Renderable, RenderableID, GrouperID, GrouperIndividual, GrouperIndividualID};
 
 impl Lane {
-    fn car_instances(&self) -> CVec<Instance> {
+    fn car_instances(&self) -> Vec<(VehicleType, Instance)> {
         let mut cars_iter = self.microtraffic.cars.iter();
-        let mut car_instances = CVec::with_capacity(self.microtraffic.cars.len());
+        let mut car_instances = Vec::with_capacity(self.microtraffic.cars.len());
         for (segment, distance_pair) in self.construction.path.segments_with_distances() {
             for car in
                 cars_iter.take_while_ref(|car| *car.position - distance_pair[0] < segment.length())
             {
                 let position2d = segment.along(*car.position - distance_pair[0]);
                 let direction = segment.direction();
-                car_instances.push(Instance {
-                    instance_position: [position2d.x, position2d.y, 0.0],
-                    instance_direction: [direction.x, direction.y],
-                    instance_color: if DEBUG_VIEW_LANDMARKS {
-                        colors::RANDOM_COLORS[car.destination.landmark.as_raw().instance_id as usize
-                                                  % colors::RANDOM_COLORS.len()]
-                    } else {
-                        colors::RANDOM_COLORS
-                            [car.trip.as_raw().instance_id as usize % colors::RANDOM_COLORS.len()]
+                car_instances.push((
+                    car.vehicle,
+                    Instance {
+                        instance_position: [position2d.x, position2d.y, 0.0],
+                        instance_direction: [direction.x, direction.y],
+                        instance_color: car_color(car),
                     },
-                })
+                ))
             }
         }
 
@@ -47,7 +49,12 @@ impl Lane {
     }
 
     pub fn get_car_instances(&self, ui: BrowserUIID, world: &mut World) {
-        ui.on_car_instances(self.id.as_raw(), self.car_instances(), world);
+        let instances = self
+            .car_instances()
+            .into_iter()
+            .map(|(_, instance)| instance)
+            .collect();
+        ui.on_car_instances(self.id.as_raw(), instances, world);
     }
 }
 
@@ -67,22 +74,18 @@ impl Renderable for Lane {
                 };
                 let direction = self.construction.path.direction_along(*obstacle.position);
 
-                car_instances.push(Instance {
-                    instance_position: [position2d.x, position2d.y, 0.0],
-                    instance_direction: [direction.x, direction.y],
-                    instance_color: [1.0, 0.0, 0.0],
-                });
+                car_instances.push((
+                    VehicleType::Car,
+                    Instance {
+                        instance_position: [position2d.x, position2d.y, 0.0],
+                        instance_direction: [direction.x, direction.y],
+                        instance_color: [1.0, 0.0, 0.0],
+                    },
+                ));
             }
         }
 
-        if !car_instances.is_empty() {
-            renderer_id.add_several_instances(
-                RenderLayers::Car as u32,
-                frame,
-                car_instances,
-                world,
-            );
-        }
+        add_vehicle_instances(renderer_id, frame, car_instances, world);
         // no traffic light for u-turn
         if self.connectivity.on_intersection
             && !self
@@ -406,9 +409,9 @@ impl GrouperIndividual for Lane {
 }
 
 impl SwitchLane {
-    fn car_instances(&self) -> CVec<Instance> {
+    fn car_instances(&self) -> Vec<(VehicleType, Instance)> {
         let mut cars_iter = self.microtraffic.cars.iter();
-        let mut car_instances = CVec::with_capacity(self.microtraffic.cars.len());
+        let mut car_instances = Vec::with_capacity(self.microtraffic.cars.len());
         for (segment, distance_pair) in self.construction.path.segments_with_distances() {
             for car in
                 cars_iter.take_while_ref(|car| *car.position - distance_pair[0] < segment.length())
@@ -419,17 +422,14 @@ impl SwitchLane {
                     (direction + 0.3 * car.switch_velocity * direction.orthogonal()).normalize();
                 let shifted_position2d =
                     position2d + 2.5 * direction.orthogonal() * car.switch_position;
-                car_instances.push(Instance {
-                    instance_position: [shifted_position2d.x, shifted_position2d.y, 0.0],
-                    instance_direction: [rotated_direction.x, rotated_direction.y],
-                    instance_color: if DEBUG_VIEW_LANDMARKS {
-                        colors::RANDOM_COLORS[car.destination.landmark.as_raw().instance_id as usize
-                                                  % colors::RANDOM_COLORS.len()]
-                    } else {
-                        colors::RANDOM_COLORS
-                            [car.trip.as_raw().instance_id as usize % colors::RANDOM_COLORS.len()]
+                car_instances.push((
+                    car.vehicle,
+                    Instance {
+                        instance_position: [shifted_position2d.x, shifted_position2d.y, 0.0],
+                        instance_direction: [rotated_direction.x, rotated_direction.y],
+                        instance_color: car_color(car),
                     },
-                })
+                ))
             }
         }
 
@@ -437,7 +437,12 @@ impl SwitchLane {
     }
 
     pub fn get_car_instances(&mut self, ui: BrowserUIID, world: &mut World) {
-        ui.on_car_instances(self.id.as_raw(), self.car_instances(), world);
+        let instances = self
+            .car_instances()
+            .into_iter()
+            .map(|(_, instance)| instance)
+            .collect();
+        ui.on_car_instances(self.id.as_raw(), instances, world);
     }
 }
 
@@ -461,11 +466,14 @@ impl Renderable for SwitchLane {
                         .orthogonal();
                 let direction = self.construction.path.direction_along(*obstacle.position);
 
-                car_instances.push(Instance {
-                    instance_position: [position2d.x, position2d.y, 0.0],
-                    instance_direction: [direction.x, direction.y],
-                    instance_color: [1.0, 0.7, 0.7],
-                });
+                car_instances.push((
+                    VehicleType::Car,
+                    Instance {
+                        instance_position: [position2d.x, position2d.y, 0.0],
+                        instance_direction: [direction.x, direction.y],
+                        instance_color: [1.0, 0.7, 0.7],
+                    },
+                ));
             }
 
             for obstacle in &self.microtraffic.right_obstacles {
@@ -483,22 +491,18 @@ impl Renderable for SwitchLane {
                         .orthogonal();
                 let direction = self.construction.path.direction_along(*obstacle.position);
 
-                car_instances.push(Instance {
-                    instance_position: [position2d.x, position2d.y, 0.0],
-                    instance_direction: [direction.x, direction.y],
-                    instance_color: [1.0, 0.7, 0.7],
-                });
+                car_instances.push((
+                    VehicleType::Car,
+                    Instance {
+                        instance_position: [position2d.x, position2d.y, 0.0],
+                        instance_direction: [direction.x, direction.y],
+                        instance_color: [1.0, 0.7, 0.7],
+                    },
+                ));
             }
         }
 
-        if !car_instances.is_empty() {
-            renderer_id.add_several_instances(
-                RenderLayers::Car as u32,
-                frame,
-                car_instances,
-                world,
-            );
-        }
+        add_vehicle_instances(renderer_id, frame, car_instances, world);
 
         if self.connectivity.left.is_none() {
             let position = self.construction.path.along(self.construction.length / 2.0)
@@ -606,7 +610,20 @@ pub fn spawn(world: &mut World) {
         world,
     );
 
-    LaneRendererID::spawn(asphalt_group, marker_group, gaps_group, world);
+    let sidewalk_group = GrouperID::spawn(
+        colors::SIDEWALK,
+        RenderLayers::Sidewalk as u32,
+        false,
+        world,
+    );
+
+    LaneRendererID::spawn(
+        asphalt_group,
+        marker_group,
+        gaps_group,
+        sidewalk_group,
+        world,
+    );
 }
 
 const CONSTRUCTION_ANIMATION_DELAY: f32 = 120.0;
@@ -616,17 +633,71 @@ const DEBUG_VIEW_SIGNALS: bool = false;
 const DEBUG_VIEW_OBSTACLES: bool = false;
 const DEBUG_VIEW_TRANSFER_OBSTACLES: bool = false;
 
+fn car_color(car: &LaneCar) -> [f32; 3] {
+    if DEBUG_VIEW_LANDMARKS {
+        colors::RANDOM_COLORS
+            [car.destination.landmark.as_raw().instance_id as usize % colors::RANDOM_COLORS.len()]
+    } else {
+        colors::RANDOM_COLORS[car.trip.as_raw().instance_id as usize % colors::RANDOM_COLORS.len()]
+    }
+}
+
+fn vehicle_layer(vehicle: VehicleType) -> RenderLayers {
+    match vehicle {
+        VehicleType::Car => RenderLayers::Car,
+        VehicleType::DeliveryTruck => RenderLayers::DeliveryTruck,
+        VehicleType::Bus => RenderLayers::Bus,
+    }
+}
+
+const VEHICLE_TYPES: [VehicleType; 3] = [
+    VehicleType::Car,
+    VehicleType::DeliveryTruck,
+    VehicleType::Bus,
+];
+
+fn add_vehicle_instances(
+    renderer_id: RendererID,
+    frame: usize,
+    instances: Vec<(VehicleType, Instance)>,
+    world: &mut World,
+) {
+    for &vehicle in &VEHICLE_TYPES {
+        let vehicle_instances = instances
+            .iter()
+            .filter(|&&(instance_vehicle, _)| instance_vehicle == vehicle)
+            .map(|&(_, instance)| instance)
+            .collect::<CVec<_>>();
+
+        if !vehicle_instances.is_empty() {
+            renderer_id.add_several_instances(
+                vehicle_layer(vehicle) as u32,
+                frame,
+                vehicle_instances,
+                world,
+            );
+        }
+    }
+}
+
 #[derive(Compact, Clone)]
 pub struct LaneRenderer {
     id: LaneRendererID,
     asphalt_grouper: GrouperID,
     marker_grouper: GrouperID,
     gaps_grouper: GrouperID,
+    sidewalk_grouper: GrouperID,
 }
 
 impl Renderable for LaneRenderer {
     fn init(&mut self, renderer_id: RendererID, world: &mut World) {
         renderer_id.add_batch(RenderLayers::Car as u32, car::create(), world);
+        renderer_id.add_batch(
+            RenderLayers::DeliveryTruck as u32,
+            vehicles::create_delivery_truck(),
+            world,
+        );
+        renderer_id.add_batch(RenderLayers::Bus as u32, vehicles::create_bus(), world);
         renderer_id.add_batch(
             RenderLayers::TrafficLightBox as u32,
             traffic_light::create(),
@@ -672,17 +743,19 @@ impl Renderable for LaneRenderer {
     }
 
     fn render(&mut self, renderer_id: RendererID, frame: usize, world: &mut World) {
-        // Render a single invisible car to clean all instances every frame
-        renderer_id.add_instance(
-            RenderLayers::Car as u32,
-            frame,
-            Instance {
-                instance_position: [-1_000_000.0, -1_000_000.0, -1_000_000.0],
-                instance_direction: [0.0, 0.0],
-                instance_color: [0.0, 0.0, 0.0],
-            },
-            world,
-        );
+        // Render a single invisible vehicle of each type to clean all instances every frame
+        for &vehicle in &VEHICLE_TYPES {
+            renderer_id.add_instance(
+                vehicle_layer(vehicle) as u32,
+                frame,
+                Instance {
+                    instance_position: [-1_000_000.0, -1_000_000.0, -1_000_000.0],
+                    instance_direction: [0.0, 0.0],
+                    instance_color: [0.0, 0.0, 0.0],
+                },
+                world,
+            );
+        }
 
         let lanes_as_renderables: RenderableID = Lane::local_broadcast(world).into();
         lanes_as_renderables.render(renderer_id, frame, world);
@@ -698,6 +771,7 @@ impl LaneRenderer {
         asphalt_grouper: GrouperID,
         marker_grouper: GrouperID,
         gaps_grouper: GrouperID,
+        sidewalk_grouper: GrouperID,
         _: &mut World,
     ) -> LaneRenderer {
         LaneRenderer {
@@ -705,6 +779,7 @@ impl LaneRenderer {
             asphalt_grouper,
             marker_grouper,
             gaps_grouper,
+            sidewalk_grouper,
         }
     }
 
@@ -741,6 +816,22 @@ impl LaneRenderer {
     pub fn on_unbuild_switch(&mut self, lane: GrouperIndividualID, world: &mut World) {
         self.gaps_grouper.remove(lane, world);
     }
+
+    pub fn on_build_sidewalk(&mut self, sidewalk: SidewalkID, path: &LinePath, world: &mut World) {
+        // TODO: ugly: Sidewalk is not really a GrouperIndividual
+        self.sidewalk_grouper.add_frozen(
+            unsafe { GrouperIndividualID::from_raw(sidewalk.as_raw()) },
+            Mesh::from_path_as_band(path, SIDEWALK_WIDTH, 0.0),
+            world,
+        );
+    }
+
+    pub fn on_unbuild_sidewalk(&mut self, sidewalk: SidewalkID, world: &mut World) {
+        self.sidewalk_grouper.remove(
+            unsafe { GrouperIndividualID::from_raw(sidewalk.as_raw()) },
+            world,
+        );
+    }
 }
 
 use browser_ui::BrowserUI;
@@ -813,5 +904,13 @@ pub fn on_unbuild_switch(lane: &SwitchLane, world: &mut World) {
     BrowserUI::global_broadcast(world).on_lane_destructed(lane.id.as_raw(), true, false, world);
 }
 
+pub fn on_build_sidewalk(sidewalk: &Sidewalk, world: &mut World) {
+    LaneRenderer::local_first(world).on_build_sidewalk(sidewalk.id, sidewalk.path.clone(), world);
+}
+
+pub fn on_unbuild_sidewalk(sidewalk: &Sidewalk, world: &mut World) {
+    LaneRenderer::local_first(world).on_unbuild_sidewalk(sidewalk.id, world);
+}
+
 mod kay_auto;
 pub use self::kay_auto::*;
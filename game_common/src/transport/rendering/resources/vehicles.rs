@@ -0,0 +1,55 @@
+// vehicles bigger than cars, built from boxes that are centered sideways
+
+use monet::Vertex;
+
+fn add_box(
+    vertices: &mut Vec<Vertex>,
+    indices: &mut Vec<u16>,
+    back: f32,
+    front: f32,
+    half_width: f32,
+    height: f32,
+) {
+    let base = vertices.len() as u16;
+
+    for &(x, y) in &[
+        (back, -half_width),
+        (back, half_width),
+        (front, half_width),
+        (front, -half_width),
+    ] {
+        vertices.push(Vertex {
+            position: [x, y, 0.0],
+        });
+        vertices.push(Vertex {
+            position: [x, y, height],
+        });
+    }
+
+    // bottom corners are even, top corners odd
+    let sides = [(0, 2), (2, 4), (4, 6), (6, 0)];
+    for &(a, b) in &sides {
+        indices.extend(&[base + a, base + a + 1, base + b + 1]);
+        indices.extend(&[base + a, base + b + 1, base + b]);
+    }
+    // top
+    indices.extend(&[base + 1, base + 3, base + 5]);
+    indices.extend(&[base + 1, base + 5, base + 7]);
+}
+
+pub fn create_delivery_truck() -> ::monet::Mesh {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    // cargo box
+    add_box(&mut vertices, &mut indices, -4.0, 2.0, 1.1, 3.0);
+    // cab
+    add_box(&mut vertices, &mut indices, 2.2, 4.0, 1.0, 2.2);
+    ::monet::Mesh::new(vertices, indices)
+}
+
+pub fn create_bus() -> ::monet::Mesh {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    add_box(&mut vertices, &mut indices, -6.0, 6.0, 1.25, 3.0);
+    ::monet::Mesh::new(vertices, indices)
+}
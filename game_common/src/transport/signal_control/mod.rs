@@ -0,0 +1,344 @@
+use kay::{ActorSystem, World, Fate, Actor};
+use compact::CVec;
+use descartes::{N, P2, Area, PointContainer};
+
+use simulation::{Simulation, Sleeper, Instant, Duration, Ticks, TICKS_PER_SIM_SECOND};
+use planning::{Prototype, PrototypeKind};
+use construction::{ConstructionID, Constructable};
+use super::lane::LaneID;
+use super::transport_planning::{RoadPrototype, IntersectionPrototype, SignalProgram};
+use super::transport_planning::interaction::SignalProgramInteractableID;
+
+// cars closer to the end of an incoming lane than this count as demand for its signal
+pub const DETECTOR_DISTANCE: N = 60.0;
+// cars slower than this count as waiting at the signal
+pub const WAITING_VELOCITY: N = 1.0;
+// adaptive signals give green for at least this long, even without demand
+const MIN_GREEN_SECS: usize = 5;
+// loaded approaches can keep green for up to this multiple of the programmed green time
+const MAX_GREEN_EXTENSION_FACTOR: usize = 2;
+
+#[derive(Compact, Clone)]
+pub struct ControlledLane {
+    pub lane: LaneID,
+    timings: CVec<bool>,
+    demand: u16,
+    waiting: u16,
+    green: bool,
+    green_soon: bool,
+}
+
+impl ControlledLane {
+    pub fn new(lane: LaneID, timings: CVec<bool>) -> Self {
+        ControlledLane {
+            lane,
+            timings,
+            demand: 0,
+            waiting: 0,
+            green: false,
+            green_soon: false,
+        }
+    }
+
+    fn green_in(&self, phase: usize) -> bool {
+        self.timings.get(phase).cloned().unwrap_or(false)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SignalStage {
+    Green,
+    Yellow,
+    AllRed,
+}
+
+#[derive(Copy, Clone, Default, Debug)]
+pub struct SignalMetrics {
+    pub adaptive: bool,
+    pub cars_through: u32,
+    pub delay_car_secs: u32,
+    pub measured_secs: u32,
+}
+
+impl SignalMetrics {
+    pub fn new(adaptive: bool) -> Self {
+        SignalMetrics {
+            adaptive,
+            ..SignalMetrics::default()
+        }
+    }
+
+    pub fn throughput_per_hour(&self) -> f32 {
+        if self.measured_secs == 0 {
+            0.0
+        } else {
+            self.cars_through as f32 * 3600.0 / self.measured_secs as f32
+        }
+    }
+
+    pub fn average_delay_secs(&self) -> f32 {
+        if self.cars_through == 0 {
+            0.0
+        } else {
+            self.delay_car_secs as f32 / self.cars_through as f32
+        }
+    }
+}
+
+// Decides the signal state of all lanes of one intersection, either by following
+// the fixed-time program or, in adaptive mode, by the demand on incoming lanes
+#[derive(Compact, Clone)]
+pub struct SignalController {
+    id: SignalControllerID,
+    area: Area,
+    program: SignalProgram,
+    lanes: CVec<ControlledLane>,
+    phase: usize,
+    next_phase: usize,
+    stage: SignalStage,
+    stage_started: Instant,
+    metrics: SignalMetrics,
+    // measured before the last switch between fixed-time and adaptive mode
+    previous_metrics: Option<SignalMetrics>,
+    // only dies on its next wake, since it is always scheduled to wake up
+    destroyed: bool,
+}
+
+impl SignalController {
+    pub fn spawn(
+        id: SignalControllerID,
+        area: &Area,
+        program: &SignalProgram,
+        lanes: &CVec<ControlledLane>,
+        world: &mut World,
+    ) -> Self {
+        for controlled in lanes {
+            controlled.lane.set_signal_controller(id, world);
+            controlled
+                .lane
+                .set_signal_delay(program.expected_delay_secs(&controlled.timings), world);
+        }
+
+        Simulation::local_first(world).wake_up_in(Ticks(0), id.into(), world);
+
+        SignalController {
+            id,
+            area: area.clone(),
+            program: program.clone(),
+            lanes: lanes.clone(),
+            phase: 0,
+            next_phase: 0,
+            stage: SignalStage::Green,
+            stage_started: Instant::new(0),
+            metrics: SignalMetrics::new(program.adaptive),
+            previous_metrics: None,
+            destroyed: false,
+        }
+    }
+
+    pub fn report_approach(&mut self, lane: LaneID, demand: u16, waiting: u16, _: &mut World) {
+        if let Some(controlled) = self.lanes.iter_mut().find(|c| c.lane == lane) {
+            controlled.demand = demand;
+            controlled.waiting = waiting;
+        }
+    }
+
+    pub fn car_passed(&mut self, _: &mut World) {
+        self.metrics.cars_through += 1;
+    }
+
+    pub fn update_lane_timings(&mut self, lane: LaneID, timings: &CVec<bool>, world: &mut World) {
+        if let Some(controlled) = self.lanes.iter_mut().find(|c| c.lane == lane) {
+            controlled.timings = timings.clone();
+            lane.set_signal_delay(self.program.expected_delay_secs(timings), world);
+        }
+    }
+
+    pub fn report_metrics_at(
+        &mut self,
+        position: P2,
+        requester: SignalProgramInteractableID,
+        world: &mut World,
+    ) {
+        if !self.destroyed && self.area.contains(position) {
+            requester.on_signal_metrics(self.metrics, self.previous_metrics, &self.program, world);
+        }
+    }
+
+    pub fn finally_destroy(&mut self, _: &mut World) -> Fate {
+        Fate::Die
+    }
+
+    fn phase_demand(&self, phase: usize) -> u16 {
+        self.lanes
+            .iter()
+            .filter(|controlled| controlled.green_in(phase))
+            .map(|controlled| controlled.demand)
+            .sum()
+    }
+
+    fn choose_next_phase(&self, n_phases: usize) -> usize {
+        // skips phases that nobody is waiting for
+        (1..n_phases + 1)
+            .map(|i| (self.phase + i) % n_phases)
+            .find(|&phase| self.phase_demand(phase) > 0)
+            .unwrap_or((self.phase + 1) % n_phases)
+    }
+
+    fn update_adaptive_stage(&mut self, current_instant: Instant) {
+        let n_phases = self.program.phase_green_secs.len();
+        if n_phases == 0 {
+            return;
+        }
+
+        let elapsed_secs = current_instant
+            .ticks()
+            .saturating_sub(self.stage_started.ticks())
+            / TICKS_PER_SIM_SECOND;
+
+        let maybe_next_stage = match self.stage {
+            SignalStage::Green => {
+                let planned_secs = self.program.phase_green_secs[self.phase] as usize;
+                let demand_here = self.phase_demand(self.phase);
+                let demand_elsewhere: u16 = (0..n_phases)
+                    .filter(|&phase| phase != self.phase)
+                    .map(|phase| self.phase_demand(phase))
+                    .sum();
+
+                let keep_green = elapsed_secs < MIN_GREEN_SECS
+                    || demand_elsewhere == 0
+                    || (demand_here > 0
+                        && elapsed_secs < planned_secs * MAX_GREEN_EXTENSION_FACTOR);
+
+                if keep_green {
+                    None
+                } else {
+                    Some(SignalStage::Yellow)
+                }
+            }
+            SignalStage::Yellow => if elapsed_secs >= self.program.yellow_secs as usize {
+                self.next_phase = self.choose_next_phase(n_phases);
+                Some(SignalStage::AllRed)
+            } else {
+                None
+            },
+            SignalStage::AllRed => if elapsed_secs >= self.program.all_red_secs as usize {
+                self.phase = self.next_phase;
+                Some(SignalStage::Green)
+            } else {
+                None
+            },
+        };
+
+        if let Some(next_stage) = maybe_next_stage {
+            self.stage = next_stage;
+            self.stage_started = current_instant;
+        }
+    }
+
+    // returns whether the lane is green (including yellow) and whether it will be soon
+    fn signal_for(&self, controlled: &ControlledLane, current_instant: Instant) -> (bool, bool) {
+        if !self.program.adaptive {
+            (
+                self.program.is_green(&controlled.timings, current_instant),
+                self.program
+                    .is_green_soon(&controlled.timings, current_instant),
+            )
+        } else if controlled.timings.is_empty() {
+            (true, true)
+        } else {
+            match self.stage {
+                SignalStage::Green => (
+                    controlled.green_in(self.phase),
+                    controlled.green_in(self.phase),
+                ),
+                SignalStage::Yellow => (controlled.green_in(self.phase), false),
+                SignalStage::AllRed => (false, controlled.green_in(self.next_phase)),
+            }
+        }
+    }
+}
+
+impl Sleeper for SignalController {
+    fn wake(&mut self, current_instant: Instant, world: &mut World) {
+        if self.destroyed {
+            self.id.finally_destroy(world);
+            return;
+        }
+
+        self.metrics.measured_secs += 1;
+        self.metrics.delay_car_secs += self.lanes
+            .iter()
+            .map(|controlled| u32::from(controlled.waiting))
+            .sum::<u32>();
+
+        if self.program.adaptive {
+            self.update_adaptive_stage(current_instant);
+        }
+
+        let signals = self.lanes
+            .iter()
+            .map(|controlled| self.signal_for(controlled, current_instant))
+            .collect::<Vec<_>>();
+
+        for (controlled, (green, green_soon)) in self.lanes.iter_mut().zip(signals) {
+            if controlled.green != green || controlled.green_soon != green_soon {
+                controlled.green = green;
+                controlled.green_soon = green_soon;
+                controlled.lane.set_signal(green, green_soon, world);
+            }
+        }
+
+        Simulation::local_first(world).wake_up_in(
+            Ticks::from(Duration::from_seconds(1)),
+            self.id_as(),
+            world,
+        );
+    }
+}
+
+impl Constructable for SignalController {
+    fn morph(&mut self, new_prototype: &Prototype, report_to: ConstructionID, world: &mut World) {
+        if let PrototypeKind::Road(RoadPrototype::Intersection(IntersectionPrototype {
+            ref signal_program,
+            ..
+        })) = new_prototype.kind
+        {
+            if signal_program.adaptive != self.program.adaptive {
+                self.previous_metrics = Some(self.metrics);
+                self.metrics = SignalMetrics::new(signal_program.adaptive);
+            }
+            self.program = signal_program.clone();
+
+            for controlled in &self.lanes {
+                controlled.lane.set_signal_delay(
+                    self.program.expected_delay_secs(&controlled.timings),
+                    world,
+                );
+            }
+
+            if self.phase >= self.program.phase_green_secs.len() {
+                self.phase = 0;
+                self.next_phase = 0;
+                self.stage = SignalStage::Green;
+            }
+        } else {
+            unreachable!()
+        }
+        report_to.action_done(self.id_as(), world);
+    }
+
+    fn destruct(&mut self, report_to: ConstructionID, world: &mut World) -> Fate {
+        self.destroyed = true;
+        report_to.action_done(self.id_as(), world);
+        Fate::Live
+    }
+}
+
+pub fn setup(system: &mut ActorSystem) {
+    system.register::<SignalController>();
+    auto_setup(system);
+}
+
+mod kay_auto;
+pub use self::kay_auto::*;
@@ -0,0 +1,630 @@
+use kay::{ActorSystem, World, Fate, Actor};
+use compact::CVec;
+use descartes::{N, P2, AreaError};
+use ordered_float::OrderedFloat;
+
+use simulation::{Simulation, Sleeper, SleeperID, Instant, Duration, Ticks};
+use planning::{PlanHistory, PlanResult, VersionedGesture, GestureIntent, Prototype, PrototypeKind};
+use construction::{ConstructionID, Constructable, ConstructableID};
+use super::lane::Lane;
+use super::pathfinding::{Location, PreciseLocation, Attachee, AttacheeID, RoughLocation,
+RoughLocationID, RoughLocationResolve};
+use super::pathfinding::trip::{TripID, TripListener, TripListenerID, TripResult, TripFate,
+TripMode};
+
+pub const DEFAULT_HEADWAY_MINUTES: u16 = 15;
+const VEHICLE_CAPACITY: usize = 40;
+const MIN_DWELL_SECS: usize = 20;
+// used to plan the timetable, a bit slower than cars can actually go
+const SCHEDULED_SPEED: N = 6.0;
+const MAX_STOP_LANE_DISTANCE: N = 10.0;
+const STOP_RECONNECT_INTERVAL_MINUTES: usize = 1;
+// Passengers are not simulated on foot between stops and their origin or destination,
+// they just appear at the stop they board at and at the place they were going to.
+// This only lets them use stops within a short walk, so the shortcut stays small.
+pub const MAX_WALKING_DISTANCE: N = 500.0;
+pub const MIN_TRANSIT_TRIP_DISTANCE: N = 800.0;
+
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct TransitLineIntent {
+    pub headway_minutes: u16,
+}
+
+impl TransitLineIntent {
+    pub fn new(headway_minutes: u16) -> Self {
+        TransitLineIntent { headway_minutes }
+    }
+}
+
+// Each gesture point becomes a stop, vehicles drive between consecutive stops
+// over the existing roads, always in the order the line was drawn in
+#[derive(Compact, Clone, Serialize, Deserialize, Debug)]
+pub struct TransitLinePrototype {
+    pub stops: CVec<P2>,
+    pub headway_minutes: u16,
+}
+
+impl TransitLinePrototype {
+    pub fn construct(&self, report_to: ConstructionID, world: &mut World) -> CVec<ConstructableID> {
+        let id = TransitLineID::spawn(self.clone(), world).into();
+        report_to.action_done(id, world);
+        vec![id].into()
+    }
+
+    pub fn morphable_from(&self, other: &TransitLinePrototype) -> bool {
+        self.stops[..] == other.stops[..]
+    }
+
+    fn headway(&self) -> Duration {
+        Duration::from_minutes(self.headway_minutes as usize)
+    }
+
+    // time after departure from the first stop at which a vehicle leaves each stop
+    fn timetable(&self) -> CVec<Duration> {
+        let mut offset = Duration(0);
+        let mut timetable = CVec::new();
+
+        for (idx, stop) in self.stops.iter().enumerate() {
+            if idx > 0 {
+                let distance = (*stop - self.stops[idx - 1]).norm();
+                offset +=
+                    Duration::from_seconds((distance / SCHEDULED_SPEED) as usize + MIN_DWELL_SECS);
+            }
+            timetable.push(offset);
+        }
+
+        timetable
+    }
+}
+
+pub fn calculate_prototypes(
+    history: &PlanHistory,
+    _current_result: &PlanResult,
+) -> Result<Vec<Prototype>, AreaError> {
+    Ok(history
+        .gestures
+        .pairs()
+        .filter_map(
+            |(gesture_id, VersionedGesture(gesture, step_id))| match gesture.intent {
+                GestureIntent::TransitLine(intent) if gesture.points.len() >= 2 => {
+                    Some(Prototype::new_with_influences(
+                        (gesture_id, step_id),
+                        PrototypeKind::TransitLine(TransitLinePrototype {
+                            stops: gesture.points.clone(),
+                            headway_minutes: intent.headway_minutes,
+                        }),
+                    ))
+                }
+                _ => None,
+            },
+        )
+        .collect())
+}
+
+#[derive(Copy, Clone)]
+pub struct Passenger {
+    pub trip: TripID,
+    pub alight_at: TransitStopID,
+}
+
+#[derive(Compact, Clone)]
+pub struct TransitLine {
+    id: TransitLineID,
+    stops: CVec<TransitStopID>,
+    timetable: CVec<Duration>,
+    headway: Duration,
+    vehicles: CVec<TransitVehicleID>,
+    being_destroyed: bool,
+    // a new vehicle departs at every wake-up, until the line is destroyed
+    next_departure_scheduled: bool,
+}
+
+impl TransitLine {
+    pub fn spawn(id: TransitLineID, prototype: &TransitLinePrototype, world: &mut World) -> Self {
+        let stops = prototype
+            .stops
+            .iter()
+            .enumerate()
+            .map(|(idx, position)| TransitStopID::spawn(id, idx, *position, world))
+            .collect();
+
+        Simulation::local_first(world).wake_up_in(Ticks(0), id.into(), world);
+
+        TransitLine {
+            id,
+            stops,
+            timetable: prototype.timetable(),
+            headway: prototype.headway(),
+            vehicles: CVec::new(),
+            being_destroyed: false,
+            next_departure_scheduled: true,
+        }
+    }
+
+    pub fn vehicle_retired(&mut self, vehicle: TransitVehicleID, world: &mut World) {
+        self.vehicles.retain(|v| *v != vehicle);
+        self.try_finally_destroy(world);
+    }
+
+    // Stops are only removed once no vehicle drives to them anymore,
+    // and the line only dies once nothing is scheduled for it anymore
+    fn try_finally_destroy(&mut self, world: &mut World) {
+        if self.being_destroyed && !self.next_departure_scheduled && self.vehicles.is_empty() {
+            for stop in &self.stops {
+                stop.remove(world);
+            }
+            self.id.finally_destroy(world);
+        }
+    }
+
+    pub fn finally_destroy(&mut self, _: &mut World) -> Fate {
+        Fate::Die
+    }
+}
+
+impl Sleeper for TransitLine {
+    fn wake(&mut self, current_instant: Instant, world: &mut World) {
+        if self.being_destroyed {
+            self.next_departure_scheduled = false;
+            self.try_finally_destroy(world);
+            return;
+        }
+
+        let departures = self
+            .timetable
+            .iter()
+            .map(|offset| current_instant + *offset)
+            .collect();
+        let vehicle = TransitVehicleID::spawn(self.id, self.stops.clone(), departures, world);
+        self.vehicles.push(vehicle);
+
+        Simulation::local_first(world).wake_up_in(Ticks::from(self.headway), self.id_as(), world);
+    }
+}
+
+impl Constructable for TransitLine {
+    fn morph(&mut self, new_prototype: &Prototype, report_to: ConstructionID, world: &mut World) {
+        if let PrototypeKind::TransitLine(ref line_prototype) = new_prototype.kind {
+            self.headway = line_prototype.headway();
+            report_to.action_done(self.id.into(), world);
+        } else {
+            unreachable!()
+        }
+    }
+
+    fn destruct(&mut self, report_to: ConstructionID, world: &mut World) -> Fate {
+        self.being_destroyed = true;
+        for vehicle in &self.vehicles {
+            vehicle.stop_service(world);
+        }
+        report_to.action_done(self.id.into(), world);
+        Fate::Live
+    }
+}
+
+#[derive(Compact, Clone)]
+pub struct TransitStop {
+    id: TransitStopID,
+    line: TransitLineID,
+    idx: usize,
+    position: P2,
+    location: Option<PreciseLocation>,
+    waiting: CVec<Passenger>,
+    reconnect_scheduled: bool,
+    being_removed: bool,
+}
+
+impl TransitStop {
+    pub fn spawn(
+        id: TransitStopID,
+        line: TransitLineID,
+        idx: usize,
+        position: P2,
+        world: &mut World,
+    ) -> Self {
+        Lane::global_broadcast(world).try_reconnect_transit_stop(id, position, world);
+        Simulation::local_first(world).wake_up_in(
+            Ticks::from(Duration::from_minutes(STOP_RECONNECT_INTERVAL_MINUTES)),
+            id.into(),
+            world,
+        );
+
+        TransitStop {
+            id,
+            line,
+            idx,
+            position,
+            location: None,
+            waiting: CVec::new(),
+            reconnect_scheduled: true,
+            being_removed: false,
+        }
+    }
+
+    pub fn reconnect(&mut self, new_location: PreciseLocation, world: &mut World) {
+        if self.location.is_none() && !self.being_removed {
+            self.location = Some(new_location);
+            new_location.node.add_attachee(self.id_as(), world);
+            TransitNetwork::global_first(world).add_stop(
+                TransitStopInfo {
+                    stop: self.id,
+                    line: self.line,
+                    idx: self.idx,
+                    position: self.position,
+                },
+                world,
+            );
+        }
+    }
+
+    pub fn wait_for(&mut self, trip: TripID, alight_at: TransitStopID, world: &mut World) {
+        if self.being_removed {
+            trip.finish(
+                TripResult {
+                    location_now: Some(self.id_as()),
+                    fate: TripFate::RouteForgotten,
+                },
+                world,
+            );
+        } else {
+            self.waiting.push(Passenger { trip, alight_at });
+        }
+    }
+
+    pub fn vehicle_arrived(
+        &mut self,
+        vehicle: TransitVehicleID,
+        stops_ahead: &CVec<TransitStopID>,
+        free_places: usize,
+        world: &mut World,
+    ) {
+        // only passengers whose stop is still ahead on this vehicle's line can board
+        let mut boarding = CVec::new();
+        let mut still_waiting = CVec::new();
+        for passenger in &self.waiting {
+            if boarding.len() < free_places && stops_ahead.contains(&passenger.alight_at) {
+                boarding.push(*passenger);
+            } else {
+                still_waiting.push(*passenger);
+            }
+        }
+        self.waiting = still_waiting;
+
+        vehicle.board(boarding, world);
+    }
+
+    // A stop waiting to reconnect only dies at its next wake-up
+    pub fn remove(&mut self, world: &mut World) -> Fate {
+        self.being_removed = true;
+
+        for passenger in &self.waiting {
+            passenger.trip.finish(
+                TripResult {
+                    location_now: Some(self.id_as()),
+                    fate: TripFate::RouteForgotten,
+                },
+                world,
+            );
+        }
+        self.waiting.clear();
+        if let Some(location) = self.location {
+            location.node.remove_attachee(self.id_as(), world);
+            self.location = None;
+        }
+        TransitNetwork::global_first(world).remove_stop(self.id, world);
+
+        if self.reconnect_scheduled {
+            Fate::Live
+        } else {
+            Fate::Die
+        }
+    }
+
+    pub fn finally_remove(&mut self, _: &mut World) -> Fate {
+        Fate::Die
+    }
+}
+
+impl Attachee for TransitStop {
+    fn location_changed(
+        &mut self,
+        _old: Option<Location>,
+        maybe_new: Option<Location>,
+        world: &mut World,
+    ) {
+        if let Some(new) = maybe_new {
+            self.location
+                .as_mut()
+                .expect("Only an existing location can change")
+                .location = new;
+        } else {
+            self.location = None;
+            TransitNetwork::global_first(world).remove_stop(self.id, world);
+            if !self.reconnect_scheduled {
+                self.reconnect_scheduled = true;
+                Simulation::local_first(world).wake_up_in(
+                    Ticks::from(Duration::from_minutes(STOP_RECONNECT_INTERVAL_MINUTES)),
+                    self.id_as(),
+                    world,
+                );
+            }
+        }
+    }
+}
+
+impl Sleeper for TransitStop {
+    fn wake(&mut self, _: Instant, world: &mut World) {
+        self.reconnect_scheduled = false;
+
+        if self.being_removed {
+            self.id.finally_remove(world);
+        } else if self.location.is_none() {
+            Lane::global_broadcast(world).try_reconnect_transit_stop(self.id, self.position, world);
+            self.reconnect_scheduled = true;
+            Simulation::local_first(world).wake_up_in(
+                Ticks::from(Duration::from_minutes(STOP_RECONNECT_INTERVAL_MINUTES)),
+                self.id_as(),
+                world,
+            );
+        }
+    }
+}
+
+impl RoughLocation for TransitStop {
+    fn resolve(&self) -> RoughLocationResolve {
+        RoughLocationResolve::Done(self.location, self.position)
+    }
+}
+
+impl Lane {
+    pub fn try_reconnect_transit_stop(
+        &mut self,
+        stop: TransitStopID,
+        position: P2,
+        world: &mut World,
+    ) {
+        if let Some(location) = self.pathfinding.location {
+            if !self.connectivity.on_intersection {
+                if let Some((offset, _)) = self.construction.path.project_with_max_distance(
+                    position,
+                    MAX_STOP_LANE_DISTANCE,
+                    0.5,
+                ) {
+                    stop.reconnect(PreciseLocation { location, offset }, world);
+                }
+            }
+        }
+    }
+}
+
+// A vehicle serves a line once, from its first to its last stop,
+// driving between stops like any other car and keeping to the timetable
+#[derive(Compact, Clone)]
+pub struct TransitVehicle {
+    id: TransitVehicleID,
+    line: TransitLineID,
+    stops: CVec<TransitStopID>,
+    departures: CVec<Instant>,
+    current_stop: usize,
+    passengers: CVec<Passenger>,
+    // set when the line is destroyed, the vehicle then retires
+    // once its current trip or dwell time is over
+    out_of_service: bool,
+}
+
+impl TransitVehicle {
+    pub fn spawn(
+        id: TransitVehicleID,
+        line: TransitLineID,
+        stops: &CVec<TransitStopID>,
+        departures: &CVec<Instant>,
+        world: &mut World,
+    ) -> Self {
+        let mut vehicle = TransitVehicle {
+            id,
+            line,
+            stops: stops.clone(),
+            departures: departures.clone(),
+            current_stop: 0,
+            passengers: CVec::new(),
+            out_of_service: false,
+        };
+        vehicle.arrive(departures[0], world);
+        vehicle
+    }
+
+    fn arrive(&mut self, instant: Instant, world: &mut World) {
+        let stop = self.stops[self.current_stop];
+
+        let mut staying = CVec::new();
+        for passenger in &self.passengers {
+            if passenger.alight_at == stop {
+                passenger.trip.finish(
+                    TripResult {
+                        location_now: Some(stop.into()),
+                        fate: TripFate::Success(instant),
+                    },
+                    world,
+                );
+            } else {
+                staying.push(*passenger);
+            }
+        }
+        self.passengers = staying;
+
+        if self.current_stop + 1 < self.stops.len() {
+            let stops_ahead: CVec<TransitStopID> = self.stops[self.current_stop + 1..]
+                .iter()
+                .cloned()
+                .collect();
+            stop.vehicle_arrived(
+                self.id,
+                stops_ahead,
+                VEHICLE_CAPACITY - self.passengers.len(),
+                world,
+            );
+        }
+
+        let departure = self.departures[self.current_stop]
+            .max(instant + Duration::from_seconds(MIN_DWELL_SECS));
+        Simulation::local_first(world).wake_up_in(
+            Ticks(departure.ticks() - instant.ticks()),
+            self.id_as(),
+            world,
+        );
+    }
+
+    pub fn board(&mut self, passengers: &CVec<Passenger>, _: &mut World) {
+        self.passengers.extend(passengers.iter().cloned());
+    }
+
+    fn drop_passengers(&mut self, fate: TripFate, world: &mut World) {
+        for passenger in &self.passengers {
+            passenger.trip.finish(
+                TripResult {
+                    location_now: Some(self.stops[self.current_stop].into()),
+                    fate,
+                },
+                world,
+            );
+        }
+        self.passengers.clear();
+    }
+
+    pub fn stop_service(&mut self, world: &mut World) {
+        self.drop_passengers(TripFate::RouteForgotten, world);
+        self.out_of_service = true;
+    }
+
+    pub fn finish_service(&mut self, fate: TripFate, world: &mut World) -> Fate {
+        self.drop_passengers(fate, world);
+        self.line.vehicle_retired(self.id, world);
+        Fate::Die
+    }
+}
+
+impl Sleeper for TransitVehicle {
+    fn wake(&mut self, current_instant: Instant, world: &mut World) {
+        if !self.out_of_service && self.current_stop + 1 < self.stops.len() {
+            TripID::spawn(
+                self.stops[self.current_stop].into(),
+                self.stops[self.current_stop + 1].into(),
+                Some(self.id_as()),
+                TripMode::Bus,
+                current_instant,
+                world,
+            );
+        } else {
+            self.id.finish_service(TripFate::ForceStopped, world);
+        }
+    }
+}
+
+impl TripListener for TransitVehicle {
+    fn trip_created(&mut self, _trip: TripID, _: &mut World) {}
+
+    fn trip_result(
+        &mut self,
+        _trip: TripID,
+        result: TripResult,
+        _rough_source: RoughLocationID,
+        _rough_destination: RoughLocationID,
+        world: &mut World,
+    ) {
+        match result.fate {
+            _ if self.out_of_service => {
+                self.id.finish_service(TripFate::RouteForgotten, world);
+            }
+            TripFate::Success(instant) => {
+                self.current_stop += 1;
+                self.arrive(instant, world);
+            }
+            fate => {
+                self.id.finish_service(fate, world);
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct TransitStopInfo {
+    stop: TransitStopID,
+    line: TransitLineID,
+    idx: usize,
+    position: P2,
+}
+
+// Knows all connected stops, so trips can find a line
+// that takes them close to their destination
+#[derive(Compact, Clone)]
+pub struct TransitNetwork {
+    id: TransitNetworkID,
+    stops: CVec<TransitStopInfo>,
+}
+
+impl TransitNetwork {
+    pub fn spawn(id: TransitNetworkID, _: &mut World) -> Self {
+        TransitNetwork {
+            id,
+            stops: CVec::new(),
+        }
+    }
+
+    pub fn add_stop(&mut self, info: TransitStopInfo, _: &mut World) {
+        self.stops.push(info);
+    }
+
+    pub fn remove_stop(&mut self, stop: TransitStopID, _: &mut World) {
+        self.stops.retain(|info| info.stop != stop);
+    }
+
+    pub fn find_connection(
+        &mut self,
+        trip: TripID,
+        source: P2,
+        destination: P2,
+        world: &mut World,
+    ) {
+        let connection = if (destination - source).norm() < MIN_TRANSIT_TRIP_DISTANCE {
+            None
+        } else {
+            let stops = &self.stops;
+            stops
+                .iter()
+                .filter(|board| (board.position - source).norm() < MAX_WALKING_DISTANCE)
+                .flat_map(|board| {
+                    stops
+                        .iter()
+                        .filter(move |alight| {
+                            alight.line == board.line
+                                && alight.idx > board.idx
+                                && (alight.position - destination).norm() < MAX_WALKING_DISTANCE
+                        })
+                        .map(move |alight| (board, alight))
+                })
+                .min_by_key(|&(board, alight)| {
+                    OrderedFloat(
+                        (board.position - source).norm() + (alight.position - destination).norm(),
+                    )
+                })
+                .map(|(board, alight)| (board.stop, alight.stop))
+        };
+
+        trip.on_transit_connection(connection, world);
+    }
+}
+
+pub fn setup(system: &mut ActorSystem) {
+    system.register::<TransitLine>();
+    system.register::<TransitStop>();
+    system.register::<TransitVehicle>();
+    system.register::<TransitNetwork>();
+    auto_setup(system);
+}
+
+pub fn spawn(world: &mut World) {
+    TransitNetworkID::spawn(world);
+}
+
+mod kay_auto;
+pub use self::kay_auto::*;
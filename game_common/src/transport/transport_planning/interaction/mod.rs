@@ -1,20 +1,26 @@
 use kay::{World, MachineID, Fate, TypedID, ActorSystem};
 use compact::{CVec, COption};
-use descartes::{N, Band, LinePath, WithUniqueOrthogonal, Into2d};
+use descartes::{N, P2, Band, LinePath, WithUniqueOrthogonal, Into2d};
 use monet::{RendererID, Instance, Mesh};
 use stagemaster::user_interface::{UserInterfaceID, Interactable3d, Interactable3dID, Event3d};
+use stagemaster::{Interactable2d, Interactable2dID};
+#[cfg(feature = "server")]
+use imgui::ImGuiSetCond_FirstUseEver;
 use style::colors;
 use render_layers::RenderLayers;
 
 use ui_layers::UILayer;
 
 use planning::{PlanHistory, GestureIntent, PlanResult, PrototypeKind,
-GestureID, ProposalID, PlanManagerID};
+GestureID, ProposalID, PlanManagerID, VersionedGesture};
 use planning::interaction::{GestureInteractable, GestureInteractableID};
 use construction::Action;
 
-use super::{RoadIntent, RoadPrototype, LanePrototype, SwitchLanePrototype, IntersectionPrototype,
-gesture_intent_smooth_paths};
+use super::{RoadIntent, RoadClass, RoadPrototype, LanePrototype, SwitchLanePrototype,
+IntersectionPrototype, gesture_intent_smooth_paths};
+use super::signal_program::{SignalProgram, SignalProgramIntent, MAX_SIGNAL_SECS};
+use super::right_of_way::RightOfWay;
+use super::super::signal_control::{SignalController, SignalMetrics};
 use style::dimensions::{LANE_DISTANCE, CENTER_LANE_DISTANCE, LANE_MARKER_WIDTH,
 LANE_MARKER_DASH_GAP, LANE_MARKER_DASH_LENGTH};
 
@@ -44,7 +50,7 @@ pub fn render_preview(
                 });
             if corresponding_construction_action_exists {
                 match prototype.kind {
-                    PrototypeKind::Road(RoadPrototype::Lane(LanePrototype(ref lane_path, _))) => {
+                    PrototypeKind::Road(RoadPrototype::Lane(LanePrototype(ref lane_path, ..))) => {
                         lane_mesh += Mesh::from_path_as_band(lane_path, EFFECTIVE_LANE_WIDTH, 0.1);
                     }
                     PrototypeKind::Road(RoadPrototype::SwitchLane(SwitchLanePrototype(
@@ -67,7 +73,7 @@ pub fn render_preview(
                         intersection_mesh +=
                             Mesh::from_path_as_band(area.primitives[0].boundary.path(), 0.1, 0.1);
 
-                        for &LanePrototype(ref lane_path, ref timings) in
+                        for &LanePrototype(ref lane_path, ref timings, ..) in
                             connecting_lanes.values().flat_map(|lanes| lanes)
                         {
                             lane_mesh +=
@@ -108,9 +114,14 @@ pub fn render_preview(
     );
 }
 
+// drags shorter than this count as clicks
+const MAX_CLICK_DISTANCE: N = 1.0;
+
+// Dragging changes the lane count, clicking opens a window to change the road class
 #[derive(Compact, Clone)]
 pub struct LaneCountInteractable {
     id: LaneCountInteractableID,
+    user_interface: UserInterfaceID,
     plan_manager: PlanManagerID,
     for_machine: MachineID,
     proposal_id: ProposalID,
@@ -143,6 +154,7 @@ impl LaneCountInteractable {
 
         LaneCountInteractable {
             id,
+            user_interface,
             for_machine: user_interface.as_raw().machine,
             plan_manager,
             proposal_id,
@@ -157,6 +169,7 @@ impl LaneCountInteractable {
 impl GestureInteractable for LaneCountInteractable {
     fn remove(&self, user_interface: UserInterfaceID, world: &mut World) -> Fate {
         user_interface.remove(UILayer::Gesture as usize, self.id.into(), world);
+        user_interface.remove_2d(self.id.into(), world);
         Fate::Die
     }
 }
@@ -168,6 +181,11 @@ impl Interactable3d for LaneCountInteractable {
             Event3d::DragFinished { from, to, .. } => Some((from, to, true)),
             _ => None,
         } {
+            if is_drag_finished && (to.into_2d() - from.into_2d()).norm() < MAX_CLICK_DISTANCE {
+                self.user_interface.add_2d(self.id.into(), world);
+                return;
+            }
+
             if let Some((closest_point_along, closest_point)) =
                 self.path.project_with_tolerance(from.into_2d(), 3.0)
             {
@@ -182,14 +200,14 @@ impl Interactable3d for LaneCountInteractable {
                         n_lanes_forward: (self.initial_intent.n_lanes_forward as isize
                             + n_lanes_delta as isize)
                             .max(0) as u8,
-                        n_lanes_backward: self.initial_intent.n_lanes_backward,
+                        ..self.initial_intent
                     }
                 } else {
                     RoadIntent {
                         n_lanes_backward: (self.initial_intent.n_lanes_backward as isize
                             + n_lanes_delta as isize)
                             .max(0) as u8,
-                        n_lanes_forward: self.initial_intent.n_lanes_forward,
+                        ..self.initial_intent
                     }
                 };
 
@@ -205,6 +223,357 @@ impl Interactable3d for LaneCountInteractable {
     }
 }
 
+impl Interactable2d for LaneCountInteractable {
+    #[cfg(feature = "server")]
+    fn draw(&mut self, world: &mut World, ui: &::imgui::Ui<'static>) {
+        let mut maybe_new_class = None;
+        let mut close = false;
+
+        ui.window(im_str!("Road Class##{}{}", self.gesture_id.0, self.forward))
+            .size((200.0, 100.0), ImGuiSetCond_FirstUseEver)
+            .collapsible(false)
+            .build(|| {
+                ui.text(im_str!(
+                    "Current: {:?}, {:.0} km/h",
+                    self.initial_intent.class,
+                    self.initial_intent.class.speed_limit() * 3.6
+                ));
+
+                for &(label, class) in &[
+                    (im_str!("Residential"), RoadClass::Residential),
+                    (im_str!("Arterial"), RoadClass::Arterial),
+                    (im_str!("Highway"), RoadClass::Highway),
+                ] {
+                    if ui.small_button(label) && class != self.initial_intent.class {
+                        maybe_new_class = Some(class);
+                    }
+                }
+
+                if ui.small_button(im_str!("Close")) {
+                    close = true;
+                }
+            });
+
+        if let Some(new_class) = maybe_new_class {
+            self.initial_intent.class = new_class;
+            self.plan_manager.set_intent(
+                self.proposal_id,
+                self.gesture_id,
+                GestureIntent::Road(self.initial_intent),
+                true,
+                world,
+            );
+        }
+
+        if close {
+            self.user_interface.remove_2d(self.id.into(), world);
+        }
+    }
+
+    #[cfg(not(feature = "server"))]
+    fn draw(&mut self, world: &mut World, ui: &()) {}
+}
+
+// Lets the signal program of an intersection be tuned while its plan is open
+#[derive(Compact, Clone)]
+pub struct SignalProgramInteractable {
+    id: SignalProgramInteractableID,
+    plan_manager: PlanManagerID,
+    proposal_id: ProposalID,
+    gesture_id: GestureID,
+    position: P2,
+    intent: SignalProgramIntent,
+    metrics: Option<SignalMetrics>,
+    previous_metrics: Option<SignalMetrics>,
+    // what the intersection currently runs, its phases are the ones that can be edited
+    built_program: Option<SignalProgram>,
+}
+
+impl SignalProgramInteractable {
+    #[cfg_attr(feature = "cargo-clippy", allow(too_many_arguments))]
+    pub fn spawn(
+        id: SignalProgramInteractableID,
+        user_interface: UserInterfaceID,
+        plan_manager: PlanManagerID,
+        proposal_id: ProposalID,
+        gesture_id: GestureID,
+        position: P2,
+        intent: SignalProgramIntent,
+        world: &mut World,
+    ) -> Self {
+        user_interface.add_2d(id.into(), world);
+        SignalController::global_broadcast(world).report_metrics_at(position, id, world);
+
+        SignalProgramInteractable {
+            id,
+            plan_manager,
+            proposal_id,
+            gesture_id,
+            position,
+            intent,
+            metrics: None,
+            previous_metrics: None,
+            built_program: None,
+        }
+    }
+
+    pub fn on_signal_metrics(
+        &mut self,
+        metrics: SignalMetrics,
+        previous_metrics: Option<SignalMetrics>,
+        program: &SignalProgram,
+        world: &mut World,
+    ) {
+        self.metrics = Some(metrics);
+        self.previous_metrics = previous_metrics;
+        self.built_program = Some(program.clone());
+
+        // a fresh intent starts out as the program the intersection already runs
+        if self.intent.phase_green_secs.is_empty() && !program.phase_green_secs.is_empty() {
+            self.intent = SignalProgramIntent {
+                phase_green_secs: program.phase_green_secs.clone(),
+                yellow_secs: program.yellow_secs,
+                all_red_secs: program.all_red_secs,
+                offset_secs: program.offset_secs,
+                adaptive: program.adaptive,
+            };
+            self.plan_manager.set_intent(
+                self.proposal_id,
+                self.gesture_id,
+                GestureIntent::SignalProgram(self.intent.clone()),
+                true,
+                world,
+            );
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+fn signal_metrics_text(metrics: &SignalMetrics) -> ::imgui::ImString {
+    ::imgui::ImString::new(format!(
+        "{}: {:.0} cars/h, {:.1}s avg. delay",
+        if metrics.adaptive {
+            "Adaptive"
+        } else {
+            "Fixed-time"
+        },
+        metrics.throughput_per_hour(),
+        metrics.average_delay_secs()
+    ))
+}
+
+impl GestureInteractable for SignalProgramInteractable {
+    fn remove(&self, user_interface: UserInterfaceID, world: &mut World) -> Fate {
+        user_interface.remove_2d(self.id.into(), world);
+        Fate::Die
+    }
+}
+
+impl Interactable2d for SignalProgramInteractable {
+    #[cfg(feature = "server")]
+    fn draw(&mut self, world: &mut World, ui: &::imgui::Ui<'static>) {
+        let mut changed = false;
+        let mut refresh_metrics = false;
+
+        ui.window(im_str!("Signal Program##{}", self.gesture_id.0))
+            .size((250.0, 200.0), ImGuiSetCond_FirstUseEver)
+            .collapsible(true)
+            .build(|| {
+                // what will run once the plan is implemented
+                let running_program = self.built_program.as_ref().map(|built_program| {
+                    let mut program = built_program.clone();
+                    program.apply_intent(&self.intent);
+                    program
+                });
+
+                if let Some(ref program) = running_program {
+                    ui.text(im_str!(
+                        "{} phases, {}s cycle",
+                        program.phase_green_secs.len(),
+                        program.cycle_secs()
+                    ));
+                } else {
+                    ui.text(im_str!("Phases can be edited once built"));
+                }
+
+                if ui.checkbox(im_str!("Adaptive to queues"), &mut self.intent.adaptive) {
+                    changed = true;
+                }
+                ui.separator();
+
+                if let Some(ref program) = running_program {
+                    for (idx, &green_secs) in program.phase_green_secs.iter().enumerate() {
+                        let mut value = i32::from(green_secs);
+                        if ui
+                            .slider_int(
+                                im_str!("Phase {} green (s)", idx + 1),
+                                &mut value,
+                                1,
+                                i32::from(MAX_SIGNAL_SECS),
+                            )
+                            .build()
+                        {
+                            // earlier phases keep what they run now
+                            while self.intent.phase_green_secs.len() <= idx {
+                                let n_custom = self.intent.phase_green_secs.len();
+                                self.intent
+                                    .phase_green_secs
+                                    .push(program.phase_green_secs[n_custom]);
+                            }
+                            self.intent.phase_green_secs[idx] = value as u16;
+                            changed = true;
+                        }
+                    }
+                    ui.separator();
+                }
+
+                let mut yellow_secs = i32::from(self.intent.yellow_secs);
+                if ui
+                    .slider_int(im_str!("Yellow (s)"), &mut yellow_secs, 0, 10)
+                    .build()
+                {
+                    self.intent.yellow_secs = yellow_secs as u16;
+                    changed = true;
+                }
+
+                let mut all_red_secs = i32::from(self.intent.all_red_secs);
+                if ui
+                    .slider_int(im_str!("All red (s)"), &mut all_red_secs, 0, 10)
+                    .build()
+                {
+                    self.intent.all_red_secs = all_red_secs as u16;
+                    changed = true;
+                }
+
+                let mut offset_secs = i32::from(self.intent.offset_secs);
+                if ui
+                    .slider_int(
+                        im_str!("Offset (s)"),
+                        &mut offset_secs,
+                        0,
+                        i32::from(MAX_SIGNAL_SECS),
+                    )
+                    .build()
+                {
+                    self.intent.offset_secs = offset_secs as u16;
+                    changed = true;
+                }
+                ui.separator();
+
+                if let Some(ref metrics) = self.metrics {
+                    ui.text(&signal_metrics_text(metrics));
+                } else {
+                    ui.text(im_str!("Not built yet"));
+                }
+                if let Some(ref previous_metrics) = self.previous_metrics {
+                    ui.text(&signal_metrics_text(previous_metrics));
+                }
+                if ui.small_button(im_str!("Refresh metrics")) {
+                    refresh_metrics = true;
+                }
+            });
+
+        if refresh_metrics {
+            SignalController::global_broadcast(world).report_metrics_at(
+                self.position,
+                self.id,
+                world,
+            );
+        }
+
+        if changed {
+            self.plan_manager.set_intent(
+                self.proposal_id,
+                self.gesture_id,
+                GestureIntent::SignalProgram(self.intent.clone()),
+                true,
+                world,
+            );
+        }
+    }
+
+    #[cfg(not(feature = "server"))]
+    fn draw(&mut self, world: &mut World, ui: &()) {}
+}
+
+// Lets the right of way at an unsignalized intersection be switched while its plan is open
+#[derive(Compact, Clone)]
+pub struct RightOfWayInteractable {
+    id: RightOfWayInteractableID,
+    plan_manager: PlanManagerID,
+    proposal_id: ProposalID,
+    gesture_id: GestureID,
+    right_of_way: RightOfWay,
+}
+
+impl RightOfWayInteractable {
+    pub fn spawn(
+        id: RightOfWayInteractableID,
+        user_interface: UserInterfaceID,
+        plan_manager: PlanManagerID,
+        proposal_id: ProposalID,
+        gesture_id: GestureID,
+        right_of_way: RightOfWay,
+        world: &mut World,
+    ) -> Self {
+        user_interface.add_2d(id.into(), world);
+
+        RightOfWayInteractable {
+            id,
+            plan_manager,
+            proposal_id,
+            gesture_id,
+            right_of_way,
+        }
+    }
+}
+
+impl GestureInteractable for RightOfWayInteractable {
+    fn remove(&self, user_interface: UserInterfaceID, world: &mut World) -> Fate {
+        user_interface.remove_2d(self.id.into(), world);
+        Fate::Die
+    }
+}
+
+impl Interactable2d for RightOfWayInteractable {
+    #[cfg(feature = "server")]
+    fn draw(&mut self, world: &mut World, ui: &::imgui::Ui<'static>) {
+        let mut maybe_new_right_of_way = None;
+
+        ui.window(im_str!("Right of Way##{}", self.gesture_id.0))
+            .size((250.0, 100.0), ImGuiSetCond_FirstUseEver)
+            .collapsible(true)
+            .build(|| {
+                ui.text(im_str!("Current: {:?}", self.right_of_way));
+
+                for &(label, right_of_way) in &[
+                    (im_str!("Signals"), RightOfWay::Signals),
+                    (im_str!("Yield"), RightOfWay::Yield),
+                    (im_str!("Stop"), RightOfWay::Stop),
+                    (im_str!("All-way stop"), RightOfWay::AllWayStop),
+                ] {
+                    if ui.small_button(label) && right_of_way != self.right_of_way {
+                        maybe_new_right_of_way = Some(right_of_way);
+                    }
+                }
+            });
+
+        if let Some(new_right_of_way) = maybe_new_right_of_way {
+            self.right_of_way = new_right_of_way;
+            self.plan_manager.set_intent(
+                self.proposal_id,
+                self.gesture_id,
+                GestureIntent::RightOfWay(new_right_of_way),
+                true,
+                world,
+            );
+        }
+    }
+
+    #[cfg(not(feature = "server"))]
+    fn draw(&mut self, world: &mut World, ui: &()) {}
+}
+
 pub fn spawn_gesture_interactables(
     history: &PlanHistory,
     user_interface: UserInterfaceID,
@@ -214,7 +583,7 @@ pub fn spawn_gesture_interactables(
 ) -> Vec<GestureInteractableID> {
     let gesture_intent_smooth_paths = gesture_intent_smooth_paths(history);
 
-    gesture_intent_smooth_paths
+    let mut interactables = gesture_intent_smooth_paths
         .into_iter()
         .flat_map(|(gesture_id, _, road_intent, path)| {
             path.shift_orthogonally(
@@ -251,11 +620,42 @@ pub fn spawn_gesture_interactables(
                     }),
                 )
         })
-        .collect()
+        .collect::<Vec<GestureInteractableID>>();
+
+    interactables.extend(history.gestures.pairs().filter_map(
+        |(gesture_id, &VersionedGesture(ref gesture, _))| match gesture.intent {
+            GestureIntent::SignalProgram(ref intent) if !gesture.points.is_empty() => Some(
+                SignalProgramInteractableID::spawn(
+                    user_interface,
+                    plan_manager,
+                    proposal_id,
+                    *gesture_id,
+                    gesture.points[0],
+                    intent.clone(),
+                    world,
+                ).into(),
+            ),
+            GestureIntent::RightOfWay(right_of_way) => Some(
+                RightOfWayInteractableID::spawn(
+                    user_interface,
+                    plan_manager,
+                    proposal_id,
+                    *gesture_id,
+                    right_of_way,
+                    world,
+                ).into(),
+            ),
+            _ => None,
+        },
+    ));
+
+    interactables
 }
 
 pub fn setup(system: &mut ActorSystem) {
     system.register::<LaneCountInteractable>();
+    system.register::<SignalProgramInteractable>();
+    system.register::<RightOfWayInteractable>();
     auto_setup(system);
 }
 
@@ -5,7 +5,7 @@ use itertools::Itertools;
 use ordered_float::OrderedFloat;
 
 use super::{IntersectionPrototype, IntersectionConnector, ConnectionRole, LANE_DISTANCE,
-LanePrototype, GestureSideID};
+LanePrototype, GestureSideID, SignalProgram};
 
 pub fn create_connecting_lanes(intersection: &mut IntersectionPrototype) {
     // sort intersection connectors from inner to outer lanes
@@ -209,7 +209,12 @@ pub fn create_connecting_lanes(intersection: &mut IntersectionPrototype) {
                                         end.direction,
                                     )?.to_line_path();
 
-                                    Some(LanePrototype(path, CVec::new()))
+                                    Some(LanePrototype(
+                                        path,
+                                        CVec::new(),
+                                        false,
+                                        start.speed_limit.min(end.speed_limit),
+                                    ))
                                 })
                                 .collect::<Vec<_>>()
                         } else {
@@ -239,7 +244,7 @@ pub fn create_connecting_lanes(intersection: &mut IntersectionPrototype) {
 
         fn compatible(lanes_a: &[LanePrototype], lanes_b: &[LanePrototype]) -> bool {
             lanes_a.iter().cartesian_product(lanes_b).all(
-                |(&LanePrototype(ref path_a, _), &LanePrototype(ref path_b, _))| {
+                |(&LanePrototype(ref path_a, ..), &LanePrototype(ref path_b, ..))| {
                     path_a.start().rough_eq_by(path_b.start(), 0.1)
                         || (!path_a.end().rough_eq_by(path_b.end(), 0.1)
                             && (path_a, path_b).intersect().is_empty())
@@ -352,17 +357,23 @@ pub fn create_connecting_lanes(intersection: &mut IntersectionPrototype) {
             iteration += 1;
         }
 
+        intersection.signal_program = SignalProgram::from_phase_lanes(
+            &phases
+                .iter()
+                .map(|&(_, n_lanes)| n_lanes)
+                .collect::<Vec<_>>(),
+        );
+
         for ((incoming_id, outgoing_id), ref mut lanes) in intersection.connecting_lanes.pairs_mut()
         {
             let timings: CVec<bool> = phases
                 .iter()
-                .flat_map(|&(ref connections_in_phase, duration)| {
-                    let in_phase = connections_in_phase.contains(&(incoming_id, outgoing_id));
-                    vec![in_phase; duration]
+                .map(|&(ref connections_in_phase, _)| {
+                    connections_in_phase.contains(&(incoming_id, outgoing_id))
                 })
                 .collect();
 
-            for &mut LanePrototype(_, ref mut lane_timings) in lanes.iter_mut() {
+            for &mut LanePrototype(_, ref mut lane_timings, ..) in lanes.iter_mut() {
                 *lane_timings = timings.clone()
             }
         }
@@ -9,15 +9,25 @@ GestureIntent, Prototype, PrototypeKind, GestureID};
 
 mod intersection_connections;
 mod smooth_path;
+pub mod signal_program;
+pub use self::signal_program::{SignalProgram, SignalProgramIntent};
+pub mod right_of_way;
+pub use self::right_of_way::RightOfWay;
+pub mod roundabout;
+pub use self::roundabout::{Roundabout, RoundaboutIntent};
+pub mod road_class;
+pub use self::road_class::RoadClass;
 pub mod interaction;
 pub use self::interaction::setup;
 use style::dimensions::{LANE_DISTANCE, CENTER_LANE_DISTANCE, MIN_SWITCHING_LANE_LENGTH,
-SWITCHING_LANE_OVERLAP_TOLERANCE};
+SWITCHING_LANE_OVERLAP_TOLERANCE, SIDEWALK_WIDTH};
 
 #[derive(Copy, Clone, Serialize, Deserialize)]
 pub struct RoadIntent {
     pub n_lanes_forward: u8,
     pub n_lanes_backward: u8,
+    #[serde(default)]
+    pub class: RoadClass,
 }
 
 impl RoadIntent {
@@ -25,6 +35,7 @@ impl RoadIntent {
         RoadIntent {
             n_lanes_forward,
             n_lanes_backward,
+            class: RoadClass::default(),
         }
     }
 }
@@ -34,6 +45,7 @@ pub enum RoadPrototype {
     Lane(LanePrototype),
     SwitchLane(SwitchLanePrototype),
     Intersection(IntersectionPrototype),
+    Sidewalk(SidewalkPrototype),
     PavedArea(Area),
 }
 
@@ -50,21 +62,37 @@ impl RoadPrototype {
                 &RoadPrototype::Intersection(ref intersection_1),
                 &RoadPrototype::Intersection(ref intersection_2),
             ) => intersection_1.morphable_from(intersection_2),
+            (
+                &RoadPrototype::Sidewalk(ref sidewalk_1),
+                &RoadPrototype::Sidewalk(ref sidewalk_2),
+            ) => sidewalk_1.morphable_from(sidewalk_2),
             _ => false,
         }
     }
 }
 
 #[derive(Compact, Clone, Serialize, Deserialize, Debug)]
-pub struct LanePrototype(pub LinePath, pub CVec<bool>);
+pub struct LanePrototype(
+    pub LinePath,
+    // for lanes on intersections: in which phases of the signal program they are green
+    pub CVec<bool>,
+    // whether this is the outermost lane of a road, which has curbside parking
+    pub bool,
+    // speed limit in m/s
+    pub N,
+);
 
 impl LanePrototype {
     pub fn morphable_from(&self, other: &LanePrototype) -> bool {
         match (self, other) {
             (
-                &LanePrototype(ref path_1, ref timings_1),
-                &LanePrototype(ref path_2, ref timings_2),
-            ) => path_1.rough_eq_by(path_2, 0.05) && timings_1[..] == timings_2[..],
+                &LanePrototype(ref path_1, ref timings_1, curbside_1, _),
+                &LanePrototype(ref path_2, ref timings_2, curbside_2, _),
+            ) => {
+                path_1.rough_eq_by(path_2, 0.05)
+                    && timings_1[..] == timings_2[..]
+                    && curbside_1 == curbside_2
+            }
         }
     }
 }
@@ -82,6 +110,19 @@ impl SwitchLanePrototype {
     }
 }
 
+#[derive(Compact, Clone, Serialize, Deserialize, Debug)]
+pub struct SidewalkPrototype(pub LinePath);
+
+impl SidewalkPrototype {
+    pub fn morphable_from(&self, other: &SidewalkPrototype) -> bool {
+        match (self, other) {
+            (&SidewalkPrototype(ref path_1), &SidewalkPrototype(ref path_2)) => {
+                path_1.rough_eq_by(path_2, 0.05)
+            }
+        }
+    }
+}
+
 #[derive(Copy, Clone, Serialize, Deserialize, Debug)]
 pub struct ConnectionRole {
     straight: bool,
@@ -94,14 +135,16 @@ pub struct ConnectionRole {
 pub struct IntersectionConnector {
     position: P2,
     direction: V2,
+    speed_limit: N,
     role: ConnectionRole,
 }
 
 impl IntersectionConnector {
-    fn new(position: P2, direction: V2) -> Self {
+    fn new(position: P2, direction: V2, speed_limit: N) -> Self {
         IntersectionConnector {
             position,
             direction,
+            speed_limit,
             role: ConnectionRole {
                 straight: false,
                 u_turn: false,
@@ -123,20 +166,35 @@ impl GestureSideID {
     pub fn new_backward(gesture_idx: usize) -> Self {
         GestureSideID(-((gesture_idx + 1) as i16))
     }
+
+    // the ring of a roundabout isn't a side of any gesture
+    pub fn roundabout_ring() -> Self {
+        GestureSideID(0)
+    }
+
+    pub fn gesture_idx(&self) -> usize {
+        (self.0.abs() - 1) as usize
+    }
 }
 
 #[derive(Compact, Clone, Serialize, Deserialize, Debug)]
 pub struct IntersectionPrototype {
-    area: Area,
+    pub area: Area,
     incoming: CHashMap<GestureSideID, CVec<IntersectionConnector>>,
     outgoing: CHashMap<GestureSideID, CVec<IntersectionConnector>>,
     pub connecting_lanes: CHashMap<(GestureSideID, GestureSideID), CVec<LanePrototype>>,
+    pub signal_program: SignalProgram,
+    pub right_of_way: RightOfWay,
+    pub roundabout: Option<Roundabout>,
 }
 
 impl IntersectionPrototype {
     pub fn morphable_from(&self, other: &IntersectionPrototype) -> bool {
         // TODO: make this better!!
+        // (lane priorities are fixed once built, so changing them means rebuilding)
         (&self.area).rough_eq_by(&other.area, 0.1)
+            && self.right_of_way == other.right_of_way
+            && self.roundabout == other.roundabout
     }
 }
 
@@ -165,6 +223,20 @@ pub fn calculate_prototypes(
 ) -> Result<Vec<Prototype>, AreaError> {
     let gesture_intent_smooth_paths = gesture_intent_smooth_paths(history);
 
+    let roundabouts = history
+        .gestures
+        .pairs()
+        .filter_map(
+            |(gesture_id, VersionedGesture(gesture, step_id))| match gesture.intent {
+                GestureIntent::Roundabout(intent) => {
+                    Roundabout::from_gesture(&gesture.points, intent)
+                        .map(|roundabout| (*gesture_id, *step_id, roundabout))
+                }
+                _ => None,
+            },
+        )
+        .collect::<Vec<_>>();
+
     let gesture_areas_for_intersection = gesture_intent_smooth_paths
         .iter()
         .map(|&(gesture_id, step_id, road_intent, ref path)| {
@@ -260,6 +332,18 @@ pub fn calculate_prototypes(
         },
     ));
 
+    // roundabouts become intersections, so roads crossing them are cut at their edge
+    intersection_areas.extend(
+        roundabouts
+            .iter()
+            .map(|&(gesture_id, step_id, roundabout)| {
+                (
+                    roundabout.area(),
+                    PrototypeID::from_influences((gesture_id, step_id)),
+                )
+            }),
+    );
+
     // union overlapping intersections
 
     let mut unioned_intersection_areas = Vec::new();
@@ -303,6 +387,9 @@ pub fn calculate_prototypes(
                 incoming: CHashMap::new(),
                 outgoing: CHashMap::new(),
                 connecting_lanes: CHashMap::new(),
+                signal_program: SignalProgram::default(),
+                right_of_way: RightOfWay::default(),
+                roundabout: None,
             })),
             id,
         })
@@ -320,15 +407,17 @@ pub fn calculate_prototypes(
                             (
                                 CENTER_LANE_DISTANCE / 2.0 + f32::from(lane_i) * LANE_DISTANCE,
                                 lane_i as i8 + 1,
+                                lane_i + 1 == road_intent.n_lanes_forward,
                             )
                         })
                         .chain((0..road_intent.n_lanes_backward).into_iter().map(|lane_i| {
                             (
                                 -(CENTER_LANE_DISTANCE / 2.0 + f32::from(lane_i) * LANE_DISTANCE),
                                 -(lane_i as i8) - 1,
+                                lane_i + 1 == road_intent.n_lanes_backward,
                             )
                         }))
-                        .filter_map(|(offset, offset_i)| {
+                        .filter_map(|(offset, offset_i, curbside)| {
                             path.shift_orthogonally(offset).map(|path| {
                                 (
                                     if offset < 0.0 {
@@ -342,6 +431,8 @@ pub fn calculate_prototypes(
                                     } else {
                                         path
                                     },
+                                    curbside,
+                                    road_intent.class.speed_limit(),
                                 )
                             })
                         })
@@ -352,7 +443,7 @@ pub fn calculate_prototypes(
 
         raw_lane_paths
             .into_iter()
-            .flat_map(|(gesture_side_id, lane_influence_id, raw_lane_path)| {
+            .flat_map(|(gesture_side_id, lane_influence_id, raw_lane_path, curbside, speed_limit)| {
                 let mut start_trim = 0.0f32;
                 let mut start_influence = lane_influence_id;
                 let mut end_trim = raw_lane_path.length();
@@ -388,6 +479,7 @@ pub fn calculate_prototypes(
                                 IntersectionConnector::new(
                                     raw_lane_path.along(*entry_distance),
                                     raw_lane_path.direction_along(*entry_distance),
+                                    speed_limit,
                                 ),
                             );
                             intersection.outgoing.push_at(
@@ -395,6 +487,7 @@ pub fn calculate_prototypes(
                                 IntersectionConnector::new(
                                     raw_lane_path.along(*exit_distance),
                                     raw_lane_path.direction_along(*exit_distance),
+                                    speed_limit,
                                 ),
                             );
                             cuts.push((*entry_distance, *exit_distance, *intersection_id));
@@ -406,6 +499,7 @@ pub fn calculate_prototypes(
                                     IntersectionConnector::new(
                                         raw_lane_path.along(exit_distance),
                                         raw_lane_path.direction_along(exit_distance),
+                                        speed_limit,
                                     ),
                                 );
                                 if exit_distance > start_trim {
@@ -419,6 +513,7 @@ pub fn calculate_prototypes(
                                     IntersectionConnector::new(
                                         raw_lane_path.along(entry_distance),
                                         raw_lane_path.direction_along(entry_distance),
+                                        speed_limit,
                                     ),
                                 );
                                 if entry_distance < end_trim {
@@ -447,7 +542,7 @@ pub fn calculate_prototypes(
                             lane_influence_id.add_influences((exit_influence, entry_influence));
                         raw_lane_path
                             .subsection(exit_distance, entry_distance)
-                            .map(|subsection| (subsection, subsection_id))
+                            .map(|subsection| (subsection, subsection_id, curbside, speed_limit))
                     })
                     .collect::<Vec<_>>()
             })
@@ -457,7 +552,7 @@ pub fn calculate_prototypes(
     let switch_lane_paths = {
         let right_lane_paths_outlines_bands = intersected_lane_paths
             .iter()
-            .filter_map(|(path, id)| {
+            .filter_map(|(path, id, _, _)| {
                 path.shift_orthogonally(0.5 * LANE_DISTANCE)
                     .map(|right_path| {
                         let band = Band::new(right_path.clone(), SWITCHING_LANE_OVERLAP_TOLERANCE);
@@ -468,7 +563,7 @@ pub fn calculate_prototypes(
 
         let left_lane_paths_outlines_bands = intersected_lane_paths
             .iter()
-            .filter_map(|(path, id)| {
+            .filter_map(|(path, id, _, _)| {
                 path.shift_orthogonally(-0.5 * LANE_DISTANCE)
                     .map(|left_path| {
                         let band = Band::new(left_path.clone(), SWITCHING_LANE_OVERLAP_TOLERANCE);
@@ -550,25 +645,109 @@ pub fn calculate_prototypes(
             .collect::<Vec<_>>()
     };
 
+    let signal_program_intents = history
+        .gestures
+        .pairs()
+        .filter_map(
+            |(gesture_id, VersionedGesture(gesture, step_id))| match gesture.intent {
+                GestureIntent::SignalProgram(ref intent) if !gesture.points.is_empty() => {
+                    Some((*gesture_id, *step_id, gesture.points[0], intent.clone()))
+                }
+                _ => None,
+            },
+        )
+        .collect::<Vec<_>>();
+
+    let right_of_way_intents = history
+        .gestures
+        .pairs()
+        .filter_map(
+            |(gesture_id, VersionedGesture(gesture, step_id))| match gesture.intent {
+                GestureIntent::RightOfWay(right_of_way) if !gesture.points.is_empty() => {
+                    Some((*gesture_id, *step_id, gesture.points[0], right_of_way))
+                }
+                _ => None,
+            },
+        )
+        .collect::<Vec<_>>();
+
     for prototype in &mut intersection_prototypes {
-        if let PrototypeKind::Road(RoadPrototype::Intersection(ref mut intersection)) =
-            prototype.kind
+        if let Prototype {
+            ref mut id,
+            kind: PrototypeKind::Road(RoadPrototype::Intersection(ref mut intersection)),
+        } = *prototype
         {
+            if let Some(&(_, _, roundabout)) = roundabouts
+                .iter()
+                .find(|&&(_, _, roundabout)| intersection.area.contains(roundabout.center))
+            {
+                roundabout::create_roundabout_lanes(intersection, roundabout);
+                continue;
+            }
+
             intersection_connections::create_connecting_lanes(intersection);
+
+            if let Some(&(gesture_id, step_id, _, ref intent)) = signal_program_intents
+                .iter()
+                .find(|&&(_, _, point, _)| intersection.area.contains(point))
+            {
+                intersection.signal_program.apply_intent(intent);
+                // makes sure a changed program causes the intersection to be morphed
+                *id = id.add_influences((gesture_id, step_id));
+            }
+
+            if let Some(&(gesture_id, step_id, _, right_of_way)) = right_of_way_intents
+                .iter()
+                .find(|&&(_, _, point, _)| intersection.area.contains(point))
+            {
+                intersection.right_of_way = right_of_way;
+                *id = id.add_influences((gesture_id, step_id));
+            }
         } else {
             unreachable!()
         }
     }
 
+    // sidewalks run along the whole road on both sides,
+    // pedestrians cross other roads wherever sidewalks cross each other
+    let sidewalk_paths = gesture_intent_smooth_paths
+        .iter()
+        .flat_map(|&(gesture_id, step_id, road_intent, ref path)| {
+            vec![
+                (road_intent.n_lanes_forward, 1.0),
+                (road_intent.n_lanes_backward, -1.0),
+            ].into_iter()
+                .filter_map(|(n_lanes, side)| {
+                    let offset = f32::from(n_lanes) * LANE_DISTANCE
+                        + 0.4 * LANE_DISTANCE
+                        + SIDEWALK_WIDTH / 2.0;
+                    path.shift_orthogonally(side * offset).map(|sidewalk_path| {
+                        (
+                            sidewalk_path,
+                            PrototypeID::from_influences((
+                                gesture_id,
+                                step_id,
+                                "sidewalk",
+                                side > 0.0,
+                            )),
+                        )
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
     Ok(intersection_prototypes
         .into_iter()
         .chain(
             intersected_lane_paths
                 .into_iter()
-                .map(|(path, id)| Prototype {
+                .map(|(path, id, curbside, speed_limit)| Prototype {
                     kind: PrototypeKind::Road(RoadPrototype::Lane(LanePrototype(
                         path,
                         CVec::new(),
+                        curbside,
+                        speed_limit,
                     ))),
                     id,
                 }),
@@ -577,6 +756,10 @@ pub fn calculate_prototypes(
             kind: PrototypeKind::Road(RoadPrototype::SwitchLane(SwitchLanePrototype(path))),
             id,
         }))
+        .chain(sidewalk_paths.into_iter().map(|(path, id)| Prototype {
+            kind: PrototypeKind::Road(RoadPrototype::Sidewalk(SidewalkPrototype(path))),
+            id,
+        }))
         .chain(
             gesture_areas_for_intersection
                 .into_iter()
@@ -585,5 +768,13 @@ pub fn calculate_prototypes(
                     id: PrototypeID::from_influences((gesture_id, step_id)),
                 }),
         )
+        .chain(
+            roundabouts
+                .into_iter()
+                .map(|(gesture_id, step_id, roundabout)| Prototype {
+                    kind: PrototypeKind::Road(RoadPrototype::PavedArea(roundabout.area())),
+                    id: PrototypeID::from_influences((gesture_id, step_id, "paved")),
+                }),
+        )
         .collect())
 }
@@ -0,0 +1,68 @@
+use super::IntersectionPrototype;
+use super::GestureSideID;
+use super::super::lane::connectivity::LanePriority;
+
+// Like signal programs, drawn as a single point inside the intersection it applies to
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum RightOfWay {
+    Signals,
+    // the road with the most lanes has priority, cars on all other roads yield
+    Yield,
+    // like Yield, but cars on the other roads have to stop first
+    Stop,
+    AllWayStop,
+}
+
+impl Default for RightOfWay {
+    fn default() -> Self {
+        RightOfWay::Signals
+    }
+}
+
+impl IntersectionPrototype {
+    pub fn lane_priority(&self, incoming: GestureSideID) -> LanePriority {
+        // cars entering a roundabout yield to those already on the ring
+        if self.roundabout.is_some() {
+            return if incoming == GestureSideID::roundabout_ring() {
+                LanePriority::Major
+            } else {
+                LanePriority::Yield
+            };
+        }
+
+        let minor_priority = match self.right_of_way {
+            RightOfWay::Signals => return LanePriority::Signalized,
+            RightOfWay::AllWayStop => return LanePriority::Stop,
+            RightOfWay::Yield => LanePriority::Yield,
+            RightOfWay::Stop => LanePriority::Stop,
+        };
+
+        if Some(incoming.gesture_idx()) == self.major_gesture_idx() {
+            LanePriority::Major
+        } else {
+            minor_priority
+        }
+    }
+
+    fn major_gesture_idx(&self) -> Option<usize> {
+        let mut lanes_per_gesture = Vec::<(usize, usize)>::new();
+
+        for (side, connectors) in self.incoming.pairs() {
+            let gesture_idx = side.gesture_idx();
+            if let Some(&mut (_, ref mut n_lanes)) = lanes_per_gesture
+                .iter_mut()
+                .find(|&&mut (idx, _)| idx == gesture_idx)
+            {
+                *n_lanes += connectors.len();
+                continue;
+            }
+            lanes_per_gesture.push((gesture_idx, connectors.len()));
+        }
+
+        // ties are broken by gesture order, so the choice stays stable
+        lanes_per_gesture
+            .into_iter()
+            .max_by_key(|&(gesture_idx, n_lanes)| (n_lanes, -(gesture_idx as isize)))
+            .map(|(gesture_idx, _)| gesture_idx)
+    }
+}
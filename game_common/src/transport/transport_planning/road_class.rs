@@ -0,0 +1,26 @@
+use descartes::N;
+
+// Each class has its own speed limit, which cars on its lanes try to keep
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum RoadClass {
+    Residential,
+    Arterial,
+    Highway,
+}
+
+impl RoadClass {
+    // in m/s
+    pub fn speed_limit(&self) -> N {
+        match *self {
+            RoadClass::Residential => 50.0 / 3.6,
+            RoadClass::Arterial => 70.0 / 3.6,
+            RoadClass::Highway => 110.0 / 3.6,
+        }
+    }
+}
+
+impl Default for RoadClass {
+    fn default() -> Self {
+        RoadClass::Residential
+    }
+}
@@ -0,0 +1,259 @@
+use std::f32::consts::PI;
+use compact::{CHashMap, CVec};
+use descartes::{N, P2, V2, LinePath, ClosedLinePath, Area, CurvedPath, WithUniqueOrthogonal};
+use ordered_float::OrderedFloat;
+
+use super::{IntersectionPrototype, IntersectionConnector, LanePrototype, GestureSideID,
+SignalProgram, RightOfWay};
+use style::dimensions::LANE_DISTANCE;
+
+const MIN_ROUNDABOUT_RADIUS: N = 8.0;
+// roads are cut this far outside of the ring, to leave space for entries and exits
+const ROUNDABOUT_APPROACH_LENGTH: N = 8.0;
+const ROUNDABOUT_SEGMENT_LENGTH: N = 2.0;
+const ROUNDABOUT_SPEED_LIMIT: N = 30.0 / 3.6;
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug)]
+pub struct RoundaboutIntent {
+    pub n_lanes: u8,
+}
+
+impl RoundaboutIntent {
+    pub fn new(n_lanes: u8) -> Self {
+        RoundaboutIntent { n_lanes }
+    }
+}
+
+// Drawn as the center and a point on the ring. Roads crossing it are cut at its edge
+// and connected to a one-way ring, where entering cars have to yield
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub struct Roundabout {
+    pub center: P2,
+    pub radius: N,
+    pub n_lanes: u8,
+}
+
+impl Roundabout {
+    pub fn from_gesture(points: &[P2], intent: RoundaboutIntent) -> Option<Self> {
+        if points.len() < 2 {
+            return None;
+        }
+
+        let n_lanes = intent.n_lanes.max(1);
+        Some(Roundabout {
+            center: points[0],
+            radius: (points[1] - points[0])
+                .norm()
+                .max(MIN_ROUNDABOUT_RADIUS + f32::from(n_lanes) * LANE_DISTANCE / 2.0),
+            n_lanes,
+        })
+    }
+
+    fn lane_radius(&self, lane_i: u8) -> N {
+        self.radius + (f32::from(lane_i) - f32::from(self.n_lanes - 1) / 2.0) * LANE_DISTANCE
+    }
+
+    fn outer_radius(&self) -> N {
+        self.radius
+            + f32::from(self.n_lanes) * LANE_DISTANCE / 2.0
+            + 0.4 * LANE_DISTANCE
+            + ROUNDABOUT_APPROACH_LENGTH
+    }
+
+    pub fn area(&self) -> Area {
+        let n_corners =
+            (2.0 * PI * self.outer_radius() / ROUNDABOUT_SEGMENT_LENGTH).ceil() as usize;
+
+        Area::new_simple(
+            ClosedLinePath::new(
+                LinePath::new(
+                    (0..n_corners + 1)
+                        .map(|i| {
+                            let angle = 2.0 * PI * (i % n_corners) as N / n_corners as N;
+                            self.center + self.outer_radius() * V2::new(angle.cos(), angle.sin())
+                        })
+                        .collect(),
+                ).expect("Roundabout path should be valid"),
+            ).expect("Roundabout path should be closed"),
+        )
+    }
+
+    // traffic goes around the island so that it is on the same side
+    // as oncoming traffic would be on a normal road
+    fn sense(&self) -> N {
+        if V2::new(0.0, 1.0).orthogonal().dot(&V2::new(1.0, 0.0)) > 0.0 {
+            1.0
+        } else {
+            -1.0
+        }
+    }
+
+    // `along` is the angle travelled around the ring, in driving direction
+    fn ring_point(&self, radius: N, along: N) -> (P2, V2) {
+        let angle = self.sense() * along;
+        (
+            self.center + radius * V2::new(angle.cos(), angle.sin()),
+            self.sense() * V2::new(-angle.sin(), angle.cos()),
+        )
+    }
+
+    fn along_of(&self, position: P2) -> N {
+        let outwards = position - self.center;
+        normalize_angle(self.sense() * outwards.y.atan2(outwards.x))
+    }
+
+    // how far around the ring entries join and exits leave
+    fn approach_angle(&self, radius: N) -> N {
+        ((self.outer_radius() - radius) / radius).min(PI / 2.0)
+    }
+
+    fn arc(&self, radius: N, from: N, to: N) -> Option<LinePath> {
+        let n_segments = ((to - from) * radius / ROUNDABOUT_SEGMENT_LENGTH)
+            .ceil()
+            .max(2.0) as usize;
+
+        LinePath::new(
+            (0..n_segments + 1)
+                .map(|i| {
+                    self.ring_point(radius, from + (to - from) * i as N / n_segments as N)
+                        .0
+                })
+                .collect(),
+        )
+    }
+}
+
+fn normalize_angle(angle: N) -> N {
+    let angle = angle % (2.0 * PI);
+    if angle < 0.0 {
+        angle + 2.0 * PI
+    } else {
+        angle
+    }
+}
+
+// inner lanes of a road use the inner lanes of the ring
+fn connectors_with_ring_lanes(
+    group: &[IntersectionConnector],
+    n_ring_lanes: u8,
+) -> Vec<(&IntersectionConnector, u8)> {
+    let mut sorted = group.iter().collect::<Vec<_>>();
+    let base_position = group[0].position;
+    let direction_right = group[0].direction.orthogonal();
+    sorted.sort_by_key(|connector| {
+        OrderedFloat((connector.position - base_position).dot(&direction_right))
+    });
+
+    let n_connectors = sorted.len();
+    sorted
+        .into_iter()
+        .enumerate()
+        .map(|(l, connector)| {
+            let ring_lane_i = (n_ring_lanes as usize + l)
+                .saturating_sub(n_connectors)
+                .min(n_ring_lanes as usize - 1);
+            (connector, ring_lane_i as u8)
+        })
+        .collect()
+}
+
+pub fn create_roundabout_lanes(intersection: &mut IntersectionPrototype, roundabout: Roundabout) {
+    let ring = GestureSideID::roundabout_ring();
+    let mut connecting_lanes = CHashMap::new();
+    // where entries join and exits leave, for each ring lane
+    let mut attachments = vec![Vec::<N>::new(); roundabout.n_lanes as usize];
+
+    for (&incoming_side, incoming_group) in intersection.incoming.pairs() {
+        for (connector, ring_lane_i) in
+            connectors_with_ring_lanes(incoming_group, roundabout.n_lanes)
+        {
+            let radius = roundabout.lane_radius(ring_lane_i);
+            let join_along = normalize_angle(
+                roundabout.along_of(connector.position) + roundabout.approach_angle(radius),
+            );
+            let (join_position, join_direction) = roundabout.ring_point(radius, join_along);
+
+            if let Some(path) = CurvedPath::biarc(
+                connector.position,
+                connector.direction,
+                join_position,
+                join_direction,
+            ) {
+                connecting_lanes.push_at(
+                    (incoming_side, ring),
+                    LanePrototype(
+                        path.to_line_path(),
+                        CVec::new(),
+                        false,
+                        connector.speed_limit.min(ROUNDABOUT_SPEED_LIMIT),
+                    ),
+                );
+                attachments[ring_lane_i as usize].push(join_along);
+            }
+        }
+    }
+
+    for (&outgoing_side, outgoing_group) in intersection.outgoing.pairs() {
+        for (connector, ring_lane_i) in
+            connectors_with_ring_lanes(outgoing_group, roundabout.n_lanes)
+        {
+            let radius = roundabout.lane_radius(ring_lane_i);
+            let leave_along = normalize_angle(
+                roundabout.along_of(connector.position) - roundabout.approach_angle(radius),
+            );
+            let (leave_position, leave_direction) = roundabout.ring_point(radius, leave_along);
+
+            if let Some(path) = CurvedPath::biarc(
+                leave_position,
+                leave_direction,
+                connector.position,
+                connector.direction,
+            ) {
+                connecting_lanes.push_at(
+                    (ring, outgoing_side),
+                    LanePrototype(
+                        path.to_line_path(),
+                        CVec::new(),
+                        false,
+                        connector.speed_limit.min(ROUNDABOUT_SPEED_LIMIT),
+                    ),
+                );
+                attachments[ring_lane_i as usize].push(leave_along);
+            }
+        }
+    }
+
+    // lanes only connect at their ends, so the ring is split wherever entries join or exits leave
+    for (ring_lane_i, mut alongs) in attachments.into_iter().enumerate() {
+        alongs.sort_by_key(|&along| OrderedFloat(along));
+        alongs.dedup_by(|a, b| (*a - *b).abs() < 0.01);
+
+        match alongs.len() {
+            0 => alongs.extend(&[0.0, PI]),
+            1 => {
+                let opposite = normalize_angle(alongs[0] + PI);
+                alongs.push(opposite);
+                alongs.sort_by_key(|&along| OrderedFloat(along));
+            }
+            _ => {}
+        }
+
+        let radius = roundabout.lane_radius(ring_lane_i as u8);
+        let first_along = alongs[0];
+
+        for (i, &from) in alongs.iter().enumerate() {
+            let to = alongs.get(i + 1).cloned().unwrap_or(first_along + 2.0 * PI);
+            if let Some(path) = roundabout.arc(radius, from, to) {
+                connecting_lanes.push_at(
+                    (ring, ring),
+                    LanePrototype(path, CVec::new(), false, ROUNDABOUT_SPEED_LIMIT),
+                );
+            }
+        }
+    }
+
+    intersection.connecting_lanes = connecting_lanes;
+    intersection.signal_program = SignalProgram::default();
+    intersection.right_of_way = RightOfWay::Yield;
+    intersection.roundabout = Some(roundabout);
+}
@@ -0,0 +1,144 @@
+use compact::CVec;
+use simulation::{Instant, Duration, Ticks, TICKS_PER_SIM_SECOND};
+
+// the defaults reproduce the fixed timing all signals had before programs were editable:
+// every phase is green for 10s per lane in it and switches straight to the next one
+pub const DEFAULT_GREEN_SECS_PER_LANE: u16 = 10;
+pub const DEFAULT_YELLOW_SECS: u16 = 0;
+pub const DEFAULT_ALL_RED_SECS: u16 = 0;
+pub const MAX_SIGNAL_SECS: u16 = 120;
+// without a yellow time of its own, a signal announces the coming change this early
+const ANNOUNCE_CHANGE_TICKS: usize = 100;
+
+// Drawn as a single point inside the intersection it applies to, so it is
+// part of the plan and gets reapplied whenever that intersection is recalculated.
+// Phases without a green time of their own keep the computed default.
+#[derive(Compact, Clone, Serialize, Deserialize, Debug)]
+pub struct SignalProgramIntent {
+    pub phase_green_secs: CVec<u16>,
+    pub yellow_secs: u16,
+    pub all_red_secs: u16,
+    pub offset_secs: u16,
+    pub adaptive: bool,
+}
+
+impl SignalProgramIntent {
+    pub fn new() -> Self {
+        SignalProgramIntent {
+            phase_green_secs: CVec::new(),
+            yellow_secs: DEFAULT_YELLOW_SECS,
+            all_red_secs: DEFAULT_ALL_RED_SECS,
+            offset_secs: 0,
+            adaptive: false,
+        }
+    }
+}
+
+impl Default for SignalProgramIntent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Each phase is green, then yellow, then red for all lanes of the intersection.
+// Lanes without phases (everywhere outside of intersections) are always green.
+// Adaptive programs only use the green times as a guideline, see `SignalController`
+#[derive(Compact, Clone, Serialize, Deserialize, Debug, Default)]
+pub struct SignalProgram {
+    pub phase_green_secs: CVec<u16>,
+    pub yellow_secs: u16,
+    pub all_red_secs: u16,
+    pub offset_secs: u16,
+    pub adaptive: bool,
+}
+
+impl SignalProgram {
+    pub fn from_phase_lanes(lanes_per_phase: &[usize]) -> Self {
+        SignalProgram {
+            phase_green_secs: lanes_per_phase
+                .iter()
+                .map(|&n_lanes| n_lanes as u16 * DEFAULT_GREEN_SECS_PER_LANE)
+                .collect(),
+            yellow_secs: DEFAULT_YELLOW_SECS,
+            all_red_secs: DEFAULT_ALL_RED_SECS,
+            offset_secs: 0,
+            adaptive: false,
+        }
+    }
+
+    pub fn apply_intent(&mut self, intent: &SignalProgramIntent) {
+        for (green_secs, &custom_green_secs) in self
+            .phase_green_secs
+            .iter_mut()
+            .zip(intent.phase_green_secs.iter())
+        {
+            *green_secs = custom_green_secs;
+        }
+        self.yellow_secs = intent.yellow_secs;
+        self.all_red_secs = intent.all_red_secs;
+        self.offset_secs = intent.offset_secs;
+        self.adaptive = intent.adaptive;
+    }
+
+    fn phase_secs(&self, green_secs: u16) -> usize {
+        (green_secs + self.yellow_secs + self.all_red_secs) as usize
+    }
+
+    pub fn cycle_secs(&self) -> usize {
+        self.phase_green_secs
+            .iter()
+            .map(|&green_secs| self.phase_secs(green_secs))
+            .sum()
+    }
+
+    // includes yellow, which can still be driven through
+    pub fn is_green(&self, green_in_phases: &[bool], instant: Instant) -> bool {
+        let cycle_secs = self.cycle_secs();
+        if green_in_phases.is_empty() || cycle_secs == 0 {
+            return true;
+        }
+
+        let mut secs_into_phase =
+            (instant.ticks() / TICKS_PER_SIM_SECOND + self.offset_secs as usize) % cycle_secs;
+
+        for (phase, &green_secs) in self.phase_green_secs.iter().enumerate() {
+            if secs_into_phase < self.phase_secs(green_secs) {
+                return green_in_phases.get(phase).cloned().unwrap_or(false)
+                    && secs_into_phase < (green_secs + self.yellow_secs) as usize;
+            }
+            secs_into_phase -= self.phase_secs(green_secs);
+        }
+
+        unreachable!()
+    }
+
+    // the chance of arriving at red times the average red time left,
+    // for cars arriving at random
+    pub fn expected_delay_secs(&self, green_in_phases: &[bool]) -> f32 {
+        let cycle_secs = self.cycle_secs();
+        if green_in_phases.is_empty() || cycle_secs == 0 {
+            return 0.0;
+        }
+
+        let green_secs: usize = self
+            .phase_green_secs
+            .iter()
+            .zip(green_in_phases)
+            .filter(|&(_, &green)| green)
+            .map(|(&green_secs, _)| (green_secs + self.yellow_secs) as usize)
+            .sum();
+        let red_secs = cycle_secs.saturating_sub(green_secs) as f32;
+
+        red_secs * red_secs / (2.0 * cycle_secs as f32)
+    }
+
+    // whether the light will be green after the yellow time (or the announcement time)
+    pub fn is_green_soon(&self, green_in_phases: &[bool], instant: Instant) -> bool {
+        let lookahead = if self.yellow_secs == 0 {
+            Ticks(ANNOUNCE_CHANGE_TICKS)
+        } else {
+            Ticks::from(Duration::from_seconds(self.yellow_secs as usize))
+        };
+        self.is_green(green_in_phases, instant + lookahead)
+    }
+}
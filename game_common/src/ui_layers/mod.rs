@@ -2,6 +2,8 @@ use kay::{Actor, ActorSystem, World};
 use stagemaster::{Interactable2d, Interactable2dID, UserInterface, UserInterfaceID};
 #[cfg(feature = "server")]
 use imgui::ImGuiSetCond_FirstUseEver;
+#[cfg(feature = "server")]
+use land_use::land_value::LandValueRenderer;
 
 #[repr(usize)]
 pub enum UILayer {
@@ -37,15 +39,18 @@ impl Interactable2d for LayerSelection {
                 if ui.small_button(im_str!("Planning")) {
                     UserInterface::local_first(world)
                         .set_current_layer(Some(UILayer::Gesture as usize), world);
+                    LandValueRenderer::local_first(world).set_visible(false, world);
                 }
                 if ui.small_button(im_str!("Info")) {
                     UserInterface::local_first(world)
                         .set_current_layer(Some(UILayer::Info as usize), world);
+                    // the land value heatmap is part of the info layer
+                    LandValueRenderer::local_first(world).set_visible(true, world);
                 }
             });
     }
 
-    #[cfg(feature = "browser")]
+    #[cfg(not(feature = "server"))]
     fn draw(&mut self, world: &mut World, ui: &()) {}
 }
 
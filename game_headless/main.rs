@@ -0,0 +1,254 @@
+extern crate citybound_common;
+use citybound_common::*;
+
+use kay::Actor;
+use compact::CVec;
+use monet::Grouper;
+use transport::lane::{Lane, SwitchLane};
+use transport::pedestrians::Sidewalk;
+use transport::rendering::LaneRenderer;
+use economy::households::family::Family;
+use economy::households::grocery_shop::GroceryShop;
+use economy::households::grain_farm::GrainFarm;
+use economy::households::cow_farm::CowFarm;
+use economy::households::mill::Mill;
+use economy::households::bakery::Bakery;
+use economy::households::neighboring_town_trade::NeighboringTownTrade;
+use economy::households::tasks::TaskEndScheduler;
+use land_use::buildings::rendering::BuildingRenderer;
+use planning::PlanManager;
+use construction::Construction;
+use replay::{Journal, JournalMode};
+use scenario::Scenario;
+use statistics::Statistics;
+use governance::Treasury;
+
+use std::time::{Duration, Instant};
+
+const DEFAULT_TICKS_PER_SECOND: f32 = 60.0;
+// don't try to catch up forever if the simulation can't keep up
+const MAX_TICKS_PER_TURN: usize = 10;
+
+struct HeadlessConfig {
+    ticks_per_second: f32,
+    snapshot_path: String,
+    load_snapshot: bool,
+    scenario_path: Option<String>,
+    metrics_path: String,
+    statistics_path: String,
+}
+
+fn parse_args() -> HeadlessConfig {
+    let mut config = HeadlessConfig {
+        ticks_per_second: DEFAULT_TICKS_PER_SECOND,
+        snapshot_path: persistence::DEFAULT_SNAPSHOT_PATH.to_owned(),
+        load_snapshot: false,
+        scenario_path: None,
+        metrics_path: String::new(),
+        statistics_path: String::new(),
+    };
+
+    let mut args = ::std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--ticks-per-second" => {
+                config.ticks_per_second = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .expect("--ticks-per-second expects a number");
+            }
+            "--load" => {
+                config.snapshot_path = args.next().expect("--load expects a snapshot path");
+                config.load_snapshot = true;
+            }
+            "--scenario" => {
+                config.scenario_path = Some(args.next().expect("--scenario expects a path"));
+            }
+            "--metrics" => {
+                config.metrics_path = args.next().expect("--metrics expects a path");
+            }
+            "--statistics" => {
+                config.statistics_path = args.next().expect("--statistics expects a path");
+            }
+            "--record" | "--replay" => {
+                // handled by the journal
+                args.next();
+            }
+            other => println!("Ignoring unknown argument {}", other),
+        }
+    }
+
+    config
+}
+
+fn tick_interval(ticks_per_second: f32) -> Duration {
+    let nanos = (1_000_000_000.0 / ticks_per_second.max(0.001)) as u64;
+    Duration::new(nanos / 1_000_000_000, (nanos % 1_000_000_000) as u32)
+}
+
+// Runs as fast as possible for the duration of the scenario, then dumps metrics
+fn run_scenario(
+    system: &mut kay::ActorSystem,
+    scenario: &Scenario,
+    simulation: simulation::SimulationID,
+    plan_manager: planning::PlanManagerID,
+    metrics_path: &str,
+) {
+    let world = &mut system.world();
+    let runner = scenario::spawn(world, metrics_path);
+    scenario.load_into(plan_manager, world);
+    system.process_all_messages();
+
+    let duration_ticks = scenario.duration_ticks();
+    println!("Running scenario for {} ticks", duration_ticks);
+
+    // the simulation speed stays at 1, so every progress is one tick
+    for _ in 0..duration_ticks {
+        simulation.progress(world);
+        system.process_all_messages();
+    }
+
+    runner.collect_metrics(duration_ticks, world);
+    system.process_all_messages();
+    runner.write_metrics(world);
+    system.process_all_messages();
+}
+
+fn main() {
+    let config = parse_args();
+
+    let mut system = Box::new(kay::ActorSystem::new(kay::Networking::new(
+        0,
+        vec!["localhost:9999", "ws-client"],
+        50_000,
+        30,
+        1,
+    )));
+
+    setup_all(&mut system);
+
+    let world = &mut system.world();
+
+    system.networking_connect();
+
+    let simulatables = vec![
+        Lane::local_broadcast(world).into(),
+        SwitchLane::local_broadcast(world).into(),
+        Sidewalk::local_broadcast(world).into(),
+        Family::local_broadcast(world).into(),
+        GroceryShop::local_broadcast(world).into(),
+        GrainFarm::local_broadcast(world).into(),
+        CowFarm::local_broadcast(world).into(),
+        Mill::local_broadcast(world).into(),
+        Bakery::local_broadcast(world).into(),
+        NeighboringTownTrade::local_broadcast(world).into(),
+        TaskEndScheduler::local_first(world).into(),
+        Construction::global_first(world).into(),
+        Journal::global_first(world).into(),
+        Statistics::global_first(world).into(),
+        Treasury::global_first(world).into(),
+    ];
+    let simulation = simulation::spawn(world, simulatables);
+
+    let renderables: CVec<_> = vec![
+        LaneRenderer::global_broadcast(world).into(),
+        Grouper::global_broadcast(world).into(),
+        BuildingRenderer::global_broadcast(world).into(),
+        PlanManager::global_first(world).into(),
+    ].into();
+
+    // null renderer and user interface, they only exist so that
+    // the simulation can keep talking to them as usual
+    let (user_interface, _renderer) =
+        stagemaster::spawn(world, renderables, *ENV, (), style::colors::GRASS);
+
+    let plan_manager = planning::spawn(world, user_interface);
+    construction::spawn(world);
+    governance::spawn(world);
+    transport::spawn(world, simulation);
+    economy::spawn(world, simulation, plan_manager);
+    land_use::spawn(world, user_interface);
+    let persistence = persistence::spawn(world, simulation, plan_manager, &config.snapshot_path);
+    let (journal_mode, journal_path) = JournalMode::from_args(::std::env::args());
+    replay::spawn(world, simulation, plan_manager, journal_mode, &journal_path);
+    let statistics = statistics::spawn(world, &config.statistics_path);
+
+    if config.load_snapshot {
+        persistence.load(world);
+    }
+
+    system.process_all_messages();
+
+    if let Some(ref scenario_path) = config.scenario_path {
+        let scenario = match Scenario::load(scenario_path) {
+            Ok(scenario) => scenario,
+            Err(err) => {
+                println!("Error loading scenario from {}: {}", scenario_path, err);
+                return;
+            }
+        };
+
+        run_scenario(
+            &mut system,
+            &scenario,
+            simulation,
+            plan_manager,
+            &config.metrics_path,
+        );
+        statistics.export(world);
+        system.process_all_messages();
+        return;
+    }
+
+    println!(
+        "Running headless at {} ticks per second",
+        config.ticks_per_second
+    );
+
+    let tick_interval = tick_interval(config.ticks_per_second);
+    let mut next_tick = Instant::now();
+
+    loop {
+        system.process_all_messages();
+
+        if system.shutting_down {
+            break;
+        }
+
+        let mut ticks_this_turn = 0;
+
+        while next_tick <= Instant::now() && ticks_this_turn < MAX_TICKS_PER_TURN {
+            simulation.progress(world);
+            system.process_all_messages();
+            next_tick += tick_interval;
+            ticks_this_turn += 1;
+        }
+
+        if ticks_this_turn == MAX_TICKS_PER_TURN {
+            next_tick = Instant::now();
+        }
+
+        persistence.finish_pending_save(world);
+        persistence.finish_pending_restore(world);
+
+        system.process_all_messages();
+
+        system.networking_send_and_receive();
+
+        system.process_all_messages();
+
+        let maybe_sleep = system.networking_finish_turn();
+
+        let sleep = maybe_sleep.unwrap_or_else(|| {
+            let now = Instant::now();
+            if next_tick > now {
+                next_tick - now
+            } else {
+                Duration::from_millis(0)
+            }
+        });
+
+        ::std::thread::sleep(sleep);
+    }
+}
@@ -5,6 +5,7 @@ use kay::Actor;
 use compact::CVec;
 use monet::Grouper;
 use transport::lane::{Lane, SwitchLane};
+use transport::pedestrians::Sidewalk;
 use transport::rendering::LaneRenderer;
 use economy::households::family::Family;
 use economy::households::grocery_shop::GroceryShop;
@@ -17,6 +18,9 @@ use economy::households::tasks::TaskEndScheduler;
 use land_use::buildings::rendering::BuildingRenderer;
 use planning::PlanManager;
 use construction::Construction;
+use replay::{Journal, JournalMode};
+use statistics::Statistics;
+use governance::Treasury;
 
 fn main() {
     util::init::ensure_crossplatform_proper_thread(|| {
@@ -39,6 +43,7 @@ fn main() {
         let simulatables = vec![
             Lane::local_broadcast(world).into(),
             SwitchLane::local_broadcast(world).into(),
+            Sidewalk::local_broadcast(world).into(),
             Family::local_broadcast(world).into(),
             GroceryShop::local_broadcast(world).into(),
             GrainFarm::local_broadcast(world).into(),
@@ -48,6 +53,9 @@ fn main() {
             NeighboringTownTrade::local_broadcast(world).into(),
             TaskEndScheduler::local_first(world).into(),
             Construction::global_first(world).into(),
+            Journal::global_first(world).into(),
+            Statistics::global_first(world).into(),
+            Treasury::global_first(world).into(),
         ];
         let simulation = simulation::spawn(world, simulatables);
 
@@ -75,9 +83,22 @@ fn main() {
 
         let plan_manager = planning::spawn(world, user_interface);
         construction::spawn(world);
+        let treasury = governance::spawn(world);
+        treasury.add_to_ui(user_interface, world);
         transport::spawn(world, simulation);
         economy::spawn(world, simulation, plan_manager);
         land_use::spawn(world, user_interface);
+        let persistence = persistence::spawn(
+            world,
+            simulation,
+            plan_manager,
+            persistence::DEFAULT_SNAPSHOT_PATH,
+        );
+        persistence.add_to_ui(user_interface, world);
+        let (journal_mode, journal_path) = JournalMode::from_args(::std::env::args());
+        replay::spawn(world, simulation, plan_manager, journal_mode, &journal_path);
+        let statistics = statistics::spawn(world, statistics::DEFAULT_STATISTICS_PATH);
+        statistics.add_to_ui(user_interface, world);
 
         util::init::print_version(user_interface, world);
 
@@ -100,6 +121,11 @@ fn main() {
 
             system.process_all_messages();
 
+            persistence.finish_pending_save(world);
+            persistence.finish_pending_restore(world);
+
+            system.process_all_messages();
+
             renderer.prepare_render(world);
 
             system.process_all_messages();